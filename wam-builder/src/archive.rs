@@ -0,0 +1,149 @@
+/*
+    Archive layout (little endian), mirrored by `wimpy_engine::app::wam::WamArchive`:
+
+    magic           [u8;4] "WPAK"
+    version         u32
+    entry count     u32
+    entries         [entry; entry count]
+    data            every entry's stored bytes
+
+    entry:
+    name length     u16
+    name            utf-8, the hard asset source with forward slashes
+    offset          u64, from the start of the archive
+    stored length   u64
+    length          u64, after decompression
+    compression     u8, 0 is stored and 1 is zlib
+    checksum        u32, crc32 of the decompressed data
+*/
+
+const ARCHIVE_MAGIC: [u8;4] = *b"WPAK";
+const ARCHIVE_VERSION: u32 = 1;
+
+/// The manifest is stored next to the hard assets under this name
+pub const ARCHIVE_MANIFEST_ENTRY: &'static str = "wam.json";
+pub const ARCHIVE_EXTENSION: &'static str = "wpak";
+
+const COMPRESSION_NONE: u8 = 0;
+const COMPRESSION_ZLIB: u8 = 1;
+const COMPRESSION_LEVEL: u8 = 6;
+
+/// name length + offset + stored length + length + compression + checksum, without the name
+const ENTRY_HEADER_SIZE: usize = 2 + 8 + 8 + 8 + 1 + 4;
+
+use std::{collections::{BTreeMap, BTreeSet}, fs, path::Path};
+
+use crate::{definitions::Namespace, error::WamBuildError, validator::resolve_source};
+
+#[derive(Debug,Default)]
+pub struct ArchiveSummary {
+    pub entries:        usize,
+    pub compressed:     usize,
+    /// Size of the files that went into the archive
+    pub input_bytes:    u64,
+    pub output_bytes:   u64,
+}
+
+struct ArchiveEntry {
+    name:           String,
+    data:           Vec<u8>,
+    length:         u64,
+    compression:    u8,
+    checksum:       u32,
+}
+
+fn create_entry(name: String,data: Vec<u8>,compress: bool) -> ArchiveEntry {
+    let length = data.len() as u64;
+    let checksum = crc32fast::hash(&data);
+    if compress {
+        let compressed = miniz_oxide::deflate::compress_to_vec_zlib(&data,COMPRESSION_LEVEL);
+        /* Images are already compressed, storing them again would only cost decode time */
+        if compressed.len() < data.len() {
+            return ArchiveEntry { name, data: compressed, length, compression: COMPRESSION_ZLIB, checksum };
+        }
+    }
+    return ArchiveEntry { name, data, length, compression: COMPRESSION_NONE, checksum };
+}
+
+fn read_file(path: &Path) -> Result<Vec<u8>,WamBuildError> {
+    match fs::read(path) {
+        Ok(value) => Ok(value),
+        Err(error) => Err(WamBuildError::ReadFailure {
+            path: path.to_path_buf(),
+            error
+        }),
+    }
+}
+
+/// Packs a manifest and every hard asset it references into one file. Hard asset sources are resolved against `root`.
+///
+/// With `compress`, each entry is zlib compressed if that makes it smaller.
+pub fn pack_archive(manifest_path: &Path,root: &Path,destination: &Path,compress: bool) -> Result<ArchiveSummary,WamBuildError> {
+    let manifest_data = read_file(manifest_path)?;
+    let namespaces: BTreeMap<String,Namespace> = match serde_json::from_slice(&manifest_data) {
+        Ok(value) => value,
+        Err(error) => return Err(WamBuildError::InvalidJson {
+            path: manifest_path.to_path_buf(),
+            error
+        }),
+    };
+
+    /* Sorted and deduplicated, namespaces can share files */
+    let sources: BTreeSet<&str> = namespaces.values()
+        .flat_map(|namespace|namespace.hard_assets.iter())
+        .map(|hard_asset|hard_asset.source.as_str())
+        .collect();
+
+    let mut summary = ArchiveSummary::default();
+    let mut entries = Vec::with_capacity(sources.len() + 1);
+
+    summary.input_bytes += manifest_data.len() as u64;
+    entries.push(create_entry(ARCHIVE_MANIFEST_ENTRY.to_string(),manifest_data,compress));
+
+    for source in sources {
+        if source.len() > u16::MAX as usize || source == ARCHIVE_MANIFEST_ENTRY {
+            return Err(WamBuildError::InvalidArchiveEntry(source.to_string()));
+        }
+        let data = read_file(&resolve_source(root,source))?;
+        summary.input_bytes += data.len() as u64;
+        entries.push(create_entry(source.to_string(),data,compress));
+    }
+
+    let table_size: usize = entries.iter().map(|entry|ENTRY_HEADER_SIZE + entry.name.len()).sum();
+    let data_size: usize = entries.iter().map(|entry|entry.data.len()).sum();
+    let header_size = ARCHIVE_MAGIC.len() + 4 + 4;
+
+    let mut output: Vec<u8> = Vec::with_capacity(header_size + table_size + data_size);
+    output.extend_from_slice(&ARCHIVE_MAGIC);
+    output.extend_from_slice(&ARCHIVE_VERSION.to_le_bytes());
+    output.extend_from_slice(&(entries.len() as u32).to_le_bytes());
+
+    let mut offset = (header_size + table_size) as u64;
+    for entry in entries.iter() {
+        output.extend_from_slice(&(entry.name.len() as u16).to_le_bytes());
+        output.extend_from_slice(entry.name.as_bytes());
+        output.extend_from_slice(&offset.to_le_bytes());
+        output.extend_from_slice(&(entry.data.len() as u64).to_le_bytes());
+        output.extend_from_slice(&entry.length.to_le_bytes());
+        output.push(entry.compression);
+        output.extend_from_slice(&entry.checksum.to_le_bytes());
+        offset += entry.data.len() as u64;
+    }
+    for entry in entries.iter() {
+        output.extend_from_slice(&entry.data);
+        if entry.compression != COMPRESSION_NONE {
+            summary.compressed += 1;
+        }
+    }
+
+    summary.entries = entries.len();
+    summary.output_bytes = output.len() as u64;
+
+    if let Err(error) = fs::write(destination,&output) {
+        return Err(WamBuildError::WriteFailure {
+            path: destination.to_path_buf(),
+            error
+        });
+    }
+    return Ok(summary);
+}
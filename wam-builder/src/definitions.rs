@@ -0,0 +1,152 @@
+use std::{fmt, path::{Path, PathBuf}};
+use serde::{Deserialize, Serialize};
+
+/// A source file that is copied to the output as it is
+#[derive(Debug,Clone)]
+pub struct FileMap {
+    pub source: PathBuf,
+    pub destination: PathBuf,
+}
+
+/// A file created by the build, such as a texture pack surface
+#[derive(Debug,Clone)]
+pub struct GeneratedFile {
+    pub destination: PathBuf,
+    pub data: Vec<u8>,
+}
+
+/* Input: the files found in the source asset tree */
+
+/// A `manifest.json` at the root of a namespace directory
+#[derive(Deserialize,Debug,Default)]
+#[serde(rename_all = "kebab-case")]
+pub struct InputManifest {
+    pub name: Option<String>,
+    #[serde(default)]
+    pub includes: Vec<String>,
+}
+
+#[derive(Deserialize,Debug,Default)]
+#[serde(rename_all = "kebab-case")]
+pub struct ModelManifestMeshlet {
+    pub diffuse: Option<String>,
+    pub lightmap: Option<String>,
+}
+
+/// A `model.json` that turns its directory into a model
+#[derive(Deserialize,Debug,Default)]
+#[serde(rename_all = "kebab-case")]
+pub struct ModelManifest {
+    pub model: Option<String>,
+    #[serde(default)]
+    pub meshlets: Vec<ModelManifestMeshlet>,
+    /// Diffuse textures get a mip chain at runtime
+    #[serde(default)]
+    pub mipmaps: bool,
+}
+
+/*
+    Output: must stay in step with `wimpy_engine::app::wam::json_input`.
+    The manifest is a JSON object of namespace name to `Namespace`.
+*/
+
+#[derive(Serialize,Deserialize,Debug,Default)]
+#[serde(rename_all = "kebab-case")]
+pub struct Namespace {
+    pub hard_assets: Vec<HardAsset>,
+    pub virtual_assets: Vec<VirtualAsset>,
+    pub virtual_image_slice_assets: Vec<VirtualImageSliceAsset>,
+    pub virtual_model_assets: Vec<VirtualModelAsset>,
+    pub image_size_hints: Vec<ImageSizeHint>,
+}
+
+#[derive(Serialize,Deserialize,Debug,Clone,Copy,PartialEq,Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum FileType {
+    Image,
+    Text,
+    Model,
+    Audio,
+    Binary,
+    Data,
+}
+
+impl fmt::Display for FileType {
+    fn fmt(&self,f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FileType::Image =>  write!(f,"image"),
+            FileType::Text =>   write!(f,"text"),
+            FileType::Model =>  write!(f,"model"),
+            FileType::Audio =>  write!(f,"audio"),
+            FileType::Binary => write!(f,"binary"),
+            FileType::Data =>   write!(f,"data"),
+        }
+    }
+}
+
+impl FileType {
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+        return match extension.as_str() {
+            "png" | "jpg" | "jpeg" | "ktx2" => Some(FileType::Image),
+            "txt" =>                    Some(FileType::Text),
+            "glb" =>                    Some(FileType::Model),
+            "wav" | "ogg" | "flac" | "mp3" => Some(FileType::Audio),
+            "bin" =>                    Some(FileType::Binary),
+            "json" =>                   Some(FileType::Data),
+            _ =>                        None
+        };
+    }
+}
+
+#[derive(Serialize,Deserialize,Debug,Clone)]
+pub struct HardAsset {
+    pub id: u32,
+    pub r#type: FileType,
+    /// Relative to the manifest file, always uses forward slashes
+    pub source: String,
+}
+
+#[derive(Serialize,Deserialize,Debug,Clone)]
+pub struct VirtualAsset {
+    pub id: u32,
+    pub name: String,
+}
+
+#[derive(Serialize,Deserialize,Debug,Clone,Copy,PartialEq,Eq,Default)]
+pub struct Area {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+#[derive(Serialize,Deserialize,Debug,Clone)]
+pub struct VirtualImageSliceAsset {
+    pub id: u32,
+    pub name: String,
+    pub slice: Area,
+}
+
+#[derive(Serialize,Deserialize,Debug,Clone,Copy)]
+pub struct MeshletDescriptor {
+    pub diffuse: Option<u32>,
+    pub lightmap: Option<u32>,
+}
+
+#[derive(Serialize,Deserialize,Debug,Clone)]
+pub struct VirtualModelAsset {
+    pub id: u32,
+    pub name: String,
+    #[serde(default)]
+    pub meshlets: Vec<MeshletDescriptor>,
+}
+
+#[derive(Serialize,Deserialize,Debug,Clone,Copy)]
+pub struct ImageSizeHint {
+    pub id: u32,
+    pub x: u32,
+    pub y: u32,
+    #[serde(default,skip_serializing_if = "std::ops::Not::not")]
+    pub mipmaps: bool,
+}
@@ -0,0 +1,87 @@
+use std::{fmt, path::PathBuf};
+
+#[derive(Debug)]
+pub enum WamBuildError {
+    InvalidTargetNamespace,
+    SourceNotFound(PathBuf),
+    TargetNamespaceNotFound(String),
+    NamespaceCollision(String),
+    MissingInclude {
+        namespace: String,
+        include: String
+    },
+    ReadFailure {
+        path: PathBuf,
+        error: std::io::Error
+    },
+    InvalidJson {
+        path: PathBuf,
+        error: serde_json::Error
+    },
+    ConflictingDirectoryType(PathBuf),
+    ModelMissingMesh(PathBuf),
+    ModelItemNotFound {
+        model: String,
+        item: String
+    },
+    ModelItemWrongType {
+        model: String,
+        item: String
+    },
+    ImageDecodeFailure {
+        path: PathBuf,
+        error: image::ImageError
+    },
+    ImageEncodeFailure {
+        destination: PathBuf,
+        error: image::ImageError
+    },
+    PackItemTooBig {
+        path: PathBuf,
+        width: u32,
+        height: u32
+    },
+    PackOverflow(PathBuf),
+    JsonEncodeFailure(serde_json::Error),
+    WriteFailure {
+        path: PathBuf,
+        error: std::io::Error
+    },
+    InvalidArchiveEntry(String),
+    /// The destination isn't empty and `clean_destination` is off
+    DestinationNotEmpty(PathBuf),
+    /// Cleaning the destination would delete the source tree, the destination is the source or one of its parents
+    DestinationContainsSource {
+        destination: PathBuf,
+        source: PathBuf
+    },
+}
+
+impl fmt::Display for WamBuildError {
+    fn fmt(&self,f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WamBuildError::InvalidTargetNamespace => write!(f,"invalid target namespace"),
+            WamBuildError::SourceNotFound(path) => write!(f,"directory '{}' does not exist",path.display()),
+            WamBuildError::TargetNamespaceNotFound(name) => write!(f,"target namespace '{name}' not found"),
+            WamBuildError::NamespaceCollision(name) => write!(f,"namespace value collision for '{name}'"),
+            WamBuildError::MissingInclude { namespace, include } => write!(f,"namespace '{include}' not found, required by '{namespace}'"),
+            WamBuildError::ReadFailure { path, error } => write!(f,"could not read '{}': {error}",path.display()),
+            WamBuildError::InvalidJson { path, error } => write!(f,"invalid json in '{}': {error}",path.display()),
+            WamBuildError::ConflictingDirectoryType(path) => write!(f,"conflicting special directory type for '{}'; can't be a model and a texture pack",path.display()),
+            WamBuildError::ModelMissingMesh(path) => write!(f,"model manifest '{}' does not name a model file",path.display()),
+            WamBuildError::ModelItemNotFound { model, item } => write!(f,"model manifest '{model}' points to item '{item}' but it does not exist"),
+            WamBuildError::ModelItemWrongType { model, item } => write!(f,"model manifest '{model}' points to item '{item}' but it is not of the expected type"),
+            WamBuildError::ImageDecodeFailure { path, error } => write!(f,"could not decode image '{}': {error}",path.display()),
+            WamBuildError::ImageEncodeFailure { destination, error } => write!(f,"could not create pack for '{}': {error}",destination.display()),
+            WamBuildError::PackItemTooBig { path, width, height } => write!(f,"texture pack item '{}' is too big for pack (size: {width}x{height})",path.display()),
+            WamBuildError::PackOverflow(path) => write!(f,"texture pack construction failure for '{}'; spill over is needed but multiple surfaces are not allowed",path.display()),
+            WamBuildError::JsonEncodeFailure(error) => write!(f,"could not encode manifest: {error}"),
+            WamBuildError::WriteFailure { path, error } => write!(f,"could not write '{}': {error}",path.display()),
+            WamBuildError::InvalidArchiveEntry(name) => write!(f,"hard asset source '{name}' can't be stored in an archive"),
+            WamBuildError::DestinationNotEmpty(path) => write!(f,"destination '{}' is not empty; use -clean to delete its contents first",path.display()),
+            WamBuildError::DestinationContainsSource { destination, source } => write!(f,"destination '{}' is or contains source '{}'; refusing to clean it",destination.display(),source.display()),
+        }
+    }
+}
+
+impl std::error::Error for WamBuildError {}
@@ -0,0 +1,32 @@
+//! KTX2 containers are copied as they are. The `image` crate can't read them, so their size comes from the header.
+
+const IDENTIFIER: [u8;12] = [0xAB,0x4B,0x54,0x58,0x20,0x32,0x30,0xBB,0x0D,0x0A,0x1A,0x0A];
+/// The identifier and the header fields up to the pixel height
+const HEADER_PREFIX: usize = 28;
+
+pub const EXTENSION: &str = "ktx2";
+
+use std::{fs::File, io::Read, path::Path};
+use image::{ImageError, ImageResult, error::{DecodingError, ImageFormatHint}};
+
+pub fn is_ktx2(path: &Path) -> bool {
+    path.extension().and_then(|extension|extension.to_str()).is_some_and(|extension|extension.eq_ignore_ascii_case(EXTENSION))
+}
+
+fn decoding_error(message: &str) -> ImageError {
+    ImageError::Decoding(DecodingError::new(ImageFormatHint::Name("KTX2".to_string()),message.to_string()))
+}
+
+pub fn read_dimensions(path: &Path) -> ImageResult<(u32,u32)> {
+    let mut header = [0;HEADER_PREFIX];
+    File::open(path)?.read_exact(&mut header)?;
+    if header[..IDENTIFIER.len()] != IDENTIFIER {
+        return Err(decoding_error("invalid identifier"));
+    }
+    let read_u32 = |offset: usize|u32::from_le_bytes([header[offset],header[offset + 1],header[offset + 2],header[offset + 3]]);
+    let size = (read_u32(20),read_u32(24));
+    if size.0 == 0 || size.1 == 0 {
+        return Err(decoding_error("not a 2D texture"));
+    }
+    return Ok(size);
+}
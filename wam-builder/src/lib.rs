@@ -0,0 +1,30 @@
+//! Builds the WAM asset manifest consumed by `wimpy_engine::app::wam::WamManifest::create` from a source asset tree.
+//!
+//! The source directory holds one directory per namespace, each with a `manifest.json` naming the namespace and the namespaces it includes.
+//! Inside a namespace, a directory with a `model.json` is a model and a directory with a `pack` file is a texture pack.
+
+/* Explicit returns and spelled out 'static lifetimes are the style of the engine this tool builds for */
+#![allow(clippy::needless_return,clippy::redundant_static_lifetimes)]
+
+mod definitions;
+pub use definitions::*;
+
+mod settings;
+pub use settings::*;
+
+mod error;
+pub use error::WamBuildError;
+
+mod namespace_builder; /* Private */
+mod ktx2; /* Private */
+mod texture_pack;
+pub use texture_pack::TexturePack;
+
+mod wam_manifest;
+pub use wam_manifest::WamManifest;
+
+mod validator;
+pub use validator::{ValidationIssue, ValidationReport, validate_manifest};
+
+mod archive;
+pub use archive::{ARCHIVE_EXTENSION, ARCHIVE_MANIFEST_ENTRY, ArchiveSummary, pack_archive};
@@ -0,0 +1,300 @@
+/* Explicit returns and spelled out 'static lifetimes are the style of the engine this tool builds for */
+#![allow(clippy::needless_return,clippy::redundant_static_lifetimes)]
+
+use std::{env, path::{Path, PathBuf}, process::ExitCode};
+use wam_builder::{ARCHIVE_EXTENSION, WamManifest, WamManifestSettings, pack_archive, validate_manifest};
+
+struct Command {
+    name: &'static str,
+    description: &'static str,
+    action: fn(&[String]) -> ExitCode,
+}
+
+const COMMANDS: &[Command] = &[
+    Command {
+        name: "help",
+        description: "get a list of command names and their descriptions",
+        action: help,
+    },
+    Command {
+        name: "build-manifest",
+        description: "create a wam manifest: -i <src> -o <dest> -n <target namespace> (-m <manifest file name>) (-guid) (-compress) (-clean)",
+        action: build_manifest,
+    },
+    Command {
+        name: "validate-manifest",
+        description: "check a wam manifest against the files on disk: <manifest file> (-r <asset root>)",
+        action: validate,
+    },
+    Command {
+        name: "pack-archive",
+        description: "pack a wam manifest and its files into one archive: <manifest file> (-o <archive file>) (-r <asset root>) (-store)",
+        action: pack,
+    },
+];
+
+fn main() -> ExitCode {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let Some(command_name) = args.first() else {
+        eprintln!("no command (use 'help' for a list of commands)");
+        return ExitCode::FAILURE;
+    };
+    let command_name = command_name.to_lowercase();
+    match COMMANDS.iter().find(|command|command.name == command_name) {
+        Some(command) => (command.action)(&args[1..]),
+        None => {
+            eprintln!("unknown command: {command_name}");
+            ExitCode::FAILURE
+        },
+    }
+}
+
+fn help(_: &[String]) -> ExitCode {
+    for (index,command) in COMMANDS.iter().enumerate() {
+        println!("{}. {}: {}",index + 1,command.name,command.description);
+    }
+    return ExitCode::SUCCESS;
+}
+
+#[derive(Clone,Copy)]
+enum BuildParameter {
+    Source,
+    Destination,
+    TargetNamespace,
+    ManifestName,
+}
+
+fn set_parameter(slot: &mut Option<String>,value: &str,name: &str) {
+    if slot.is_some() {
+        eprintln!("warning: multiple {name} parameters in argument stream");
+    }
+    *slot = Some(value.to_string());
+}
+
+fn build_manifest(args: &[String]) -> ExitCode {
+    let mut parameter = None;
+
+    let mut source = None;
+    let mut destination = None;
+    let mut target_namespace = None;
+    let mut manifest_name = None;
+    let mut use_guids = false;
+    let mut compress = false;
+    let mut clean = false;
+
+    for arg in args {
+        if let Some(value) = parameter.take() {
+            match value {
+                BuildParameter::Source =>           set_parameter(&mut source,arg,"source"),
+                BuildParameter::Destination =>      set_parameter(&mut destination,arg,"destination"),
+                BuildParameter::TargetNamespace =>  set_parameter(&mut target_namespace,arg,"target namespace"),
+                BuildParameter::ManifestName =>     set_parameter(&mut manifest_name,arg,"manifest name"),
+            }
+            continue;
+        }
+        let Some(name) = arg.strip_prefix('-') else {
+            eprintln!("unexpected token '{arg}'");
+            return ExitCode::FAILURE;
+        };
+        match name.trim_start_matches('-').to_lowercase().as_str() {
+            "i" | "src" | "in" | "input" | "source" =>              parameter = Some(BuildParameter::Source),
+            "o" | "dst" | "out" | "output" | "destination" =>       parameter = Some(BuildParameter::Destination),
+            "n" | "ns" | "namespace" =>                             parameter = Some(BuildParameter::TargetNamespace),
+            "m" | "manifest" =>                                     parameter = Some(BuildParameter::ManifestName),
+            "guid" =>                                               use_guids = true,
+            "compress" =>                                           compress = true,
+            "clean" | "y" =>                                        clean = true,
+            unknown => {
+                eprintln!("unknown parameter '{unknown}'");
+                return ExitCode::FAILURE;
+            }
+        }
+    }
+
+    if parameter.is_some() {
+        eprintln!("unexpected end of parameter sequence");
+        return ExitCode::FAILURE;
+    }
+
+    let (Some(source),Some(destination),Some(target_namespace)) = (source,destination,target_namespace) else {
+        eprintln!("a source, destination, and target namespace are required (use \"-i <src> -o <dst> -n <namespace>\")");
+        return ExitCode::FAILURE;
+    };
+
+    let source = absolute(&source);
+    let destination = absolute(&destination);
+
+    let mut settings = WamManifestSettings::new(source.clone(),destination.clone(),target_namespace);
+    settings.use_guids = use_guids;
+    settings.compress_manifest = compress;
+    settings.clean_destination = clean;
+    if let Some(manifest_name) = manifest_name {
+        settings.manifest_output_file = manifest_name;
+    }
+
+    let manifest = match WamManifest::build(settings) {
+        Ok(value) => value,
+        Err(error) => {
+            eprintln!("{error}");
+            return ExitCode::FAILURE;
+        },
+    };
+
+    for warning in manifest.warnings.iter() {
+        eprintln!("warning: {warning}");
+    }
+
+    if let Err(error) = manifest.write_output() {
+        eprintln!("{error}");
+        return ExitCode::FAILURE;
+    }
+
+    for file in manifest.generated_files.iter() {
+        println!("created output file '{}' (size: {})",relative_display(&destination,&file.destination),file.data.len());
+    }
+    for file in manifest.file_maps.iter() {
+        println!(
+            "copied file to output '{}' from '{}'",
+            relative_display(&destination,&file.destination),
+            relative_display(&source,&file.source)
+        );
+    }
+    println!("created manifest file at '{}'",manifest.manifest_path().display());
+
+    return ExitCode::SUCCESS;
+}
+
+fn validate(args: &[String]) -> ExitCode {
+    let mut manifest_path = None;
+    let mut root = None;
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.strip_prefix('-').map(|name|name.trim_start_matches('-').to_lowercase()) {
+            Some(name) => match name.as_str() {
+                "r" | "root" => match args.next() {
+                    Some(value) => set_parameter(&mut root,value,"root"),
+                    None => {
+                        eprintln!("unexpected end of parameter sequence");
+                        return ExitCode::FAILURE;
+                    },
+                },
+                unknown => {
+                    eprintln!("unknown parameter '{unknown}'");
+                    return ExitCode::FAILURE;
+                },
+            },
+            None => set_parameter(&mut manifest_path,arg,"manifest"),
+        }
+    }
+
+    let Some(manifest_path) = manifest_path else {
+        eprintln!("a manifest file is required (use \"validate-manifest <manifest file>\")");
+        return ExitCode::FAILURE;
+    };
+    let manifest_path = absolute(&manifest_path);
+
+    /* Hard asset sources are relative to the manifest, the same as the engine resolves them */
+    let root = match root {
+        Some(value) => absolute(&value),
+        None => manifest_path.parent().map(Path::to_path_buf).unwrap_or_default(),
+    };
+
+    let report = match validate_manifest(&manifest_path,&root) {
+        Ok(value) => value,
+        Err(error) => {
+            eprintln!("{error}");
+            return ExitCode::FAILURE;
+        },
+    };
+
+    for issue in report.issues.iter() {
+        eprintln!("error: {issue}");
+    }
+
+    if !report.is_valid() {
+        eprintln!("manifest '{}' is invalid ({} issue(s), {} file(s) checked)",manifest_path.display(),report.issues.len(),report.checked_files);
+        return ExitCode::FAILURE;
+    }
+    println!("manifest '{}' is valid ({} file(s) checked)",manifest_path.display(),report.checked_files);
+    return ExitCode::SUCCESS;
+}
+
+fn pack(args: &[String]) -> ExitCode {
+    let mut manifest_path = None;
+    let mut root = None;
+    let mut destination = None;
+    let mut compress = true;
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.strip_prefix('-').map(|name|name.trim_start_matches('-').to_lowercase()) {
+            Some(name) => {
+                let slot = match name.as_str() {
+                    "r" | "root" => (&mut root,"root"),
+                    "o" | "out" | "output" => (&mut destination,"output"),
+                    "store" => {
+                        compress = false;
+                        continue;
+                    },
+                    unknown => {
+                        eprintln!("unknown parameter '{unknown}'");
+                        return ExitCode::FAILURE;
+                    },
+                };
+                match args.next() {
+                    Some(value) => set_parameter(slot.0,value,slot.1),
+                    None => {
+                        eprintln!("unexpected end of parameter sequence");
+                        return ExitCode::FAILURE;
+                    },
+                }
+            },
+            None => set_parameter(&mut manifest_path,arg,"manifest"),
+        }
+    }
+
+    let Some(manifest_path) = manifest_path else {
+        eprintln!("a manifest file is required (use \"pack-archive <manifest file>\")");
+        return ExitCode::FAILURE;
+    };
+    let manifest_path = absolute(&manifest_path);
+
+    let root = match root {
+        Some(value) => absolute(&value),
+        None => manifest_path.parent().map(Path::to_path_buf).unwrap_or_default(),
+    };
+    let destination = match destination {
+        Some(value) => absolute(&value),
+        None => manifest_path.with_extension(ARCHIVE_EXTENSION),
+    };
+
+    let summary = match pack_archive(&manifest_path,&root,&destination,compress) {
+        Ok(value) => value,
+        Err(error) => {
+            eprintln!("{error}");
+            return ExitCode::FAILURE;
+        },
+    };
+
+    println!(
+        "created archive '{}' ({} entries, {} compressed, size: {} from {})",
+        destination.display(),
+        summary.entries,
+        summary.compressed,
+        summary.output_bytes,
+        summary.input_bytes
+    );
+    return ExitCode::SUCCESS;
+}
+
+fn absolute(path: &str) -> PathBuf {
+    match std::path::absolute(path) {
+        Ok(value) => value,
+        Err(_) => PathBuf::from(path),
+    }
+}
+
+fn relative_display(root: &Path,path: &Path) -> String {
+    path.strip_prefix(root).unwrap_or(path).display().to_string()
+}
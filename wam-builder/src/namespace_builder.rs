@@ -0,0 +1,28 @@
+use std::collections::HashMap;
+use crate::definitions::*;
+
+/// Collects the assets of one namespace while its directory is walked
+#[derive(Default)]
+pub struct NamespaceBuilder {
+    pub namespace: Namespace,
+    used_names: HashMap<String,u32>,
+}
+
+impl NamespaceBuilder {
+    /// Output names must be unique, a repeated name gets a numbered suffix (e.g., `name - 1`)
+    pub fn qualify_asset_name(&mut self,name: String) -> String {
+        let Some(mut count) = self.used_names.get(&name).copied() else {
+            self.used_names.insert(name.clone(),1);
+            return name;
+        };
+        loop {
+            let candidate = format!("{name} - {count}");
+            count += 1;
+            if !self.used_names.contains_key(&candidate) {
+                self.used_names.insert(name,count);
+                self.used_names.insert(candidate.clone(),1);
+                return candidate;
+            }
+        }
+    }
+}
@@ -0,0 +1,83 @@
+use std::path::PathBuf;
+
+pub const DEFAULT_MANIFEST_OUTPUT_FILE: &'static str = "manifest.json";
+pub const DEFAULT_PACK_SIZE: u32 = 512;
+
+#[derive(Debug,Clone,Copy,PartialEq,Eq,Default)]
+pub enum PackPadding {
+    None,
+    /// A one pixel transparent border that may overlap the border of a neighbour
+    TransparentBuffer,
+    /// A one pixel border made by repeating the outermost pixels, prevents bleeding with linear filtering
+    #[default]
+    EdgeExtension,
+}
+
+#[derive(Debug,Clone,Copy,PartialEq,Eq,Default)]
+pub enum ImageFormat {
+    #[default]
+    Png,
+    /// Lossless
+    Webp,
+}
+
+impl ImageFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ImageFormat::Png => "png",
+            ImageFormat::Webp => "webp",
+        }
+    }
+}
+
+#[derive(Debug,Clone)]
+pub struct TexturePackSettings {
+    /// Width and height of each pack surface
+    pub size: u32,
+    /// If false, a pack that doesn't fit on one surface is an error
+    pub allow_multiple_surfaces: bool,
+    pub padding: PackPadding,
+    pub export_format: ImageFormat,
+}
+
+impl Default for TexturePackSettings {
+    fn default() -> Self {
+        Self {
+            size: DEFAULT_PACK_SIZE,
+            allow_multiple_surfaces: true,
+            padding: PackPadding::default(),
+            export_format: ImageFormat::default(),
+        }
+    }
+}
+
+#[derive(Debug,Clone)]
+pub struct WamManifestSettings {
+    /// The directory containing the namespace directories
+    pub source: PathBuf,
+    pub destination: PathBuf,
+    /// The namespace to build. Included namespaces are built along with it.
+    pub target_namespace: String,
+    pub texture_pack: TexturePackSettings,
+    /// Use random names for output files instead of asset names
+    pub use_guids: bool,
+    pub compress_manifest: bool,
+    pub manifest_output_file: String,
+    /// Delete everything in the destination before writing. Without it, a destination that isn't empty is an error.
+    pub clean_destination: bool,
+}
+
+impl WamManifestSettings {
+    pub fn new(source: PathBuf,destination: PathBuf,target_namespace: String) -> Self {
+        Self {
+            source,
+            destination,
+            target_namespace,
+            texture_pack: Default::default(),
+            use_guids: false,
+            compress_manifest: false,
+            manifest_output_file: DEFAULT_MANIFEST_OUTPUT_FILE.to_string(),
+            clean_destination: false,
+        }
+    }
+}
@@ -0,0 +1,136 @@
+use std::path::{Path, PathBuf};
+use image::RgbaImage;
+
+use crate::{definitions::*, error::WamBuildError, settings::TexturePackSettings};
+
+mod layout_surface;
+use layout_surface::LayoutSurface;
+
+pub struct TexturePack {
+    pub images: Vec<VirtualImageSliceAsset>,
+    pub files: Vec<GeneratedFile>,
+    pub image_size_hints: Vec<ImageSizeHint>,
+}
+
+struct PackImage {
+    bitmap: RgbaImage,
+    path: PathBuf,
+}
+
+impl PackImage {
+    fn largest_dimension(&self) -> u32 {
+        self.bitmap.width().max(self.bitmap.height())
+    }
+}
+
+/// Packs every image in `image_paths` into as few surfaces as possible.
+///
+/// `bind_surface` registers a new surface as a hard asset, returning its ID and output path.
+/// `runtime_name` is the pack directory relative to the namespace root, slices are named `<runtime_name>/<file stem>`.
+pub fn build_texture_pack<F>(
+    image_paths: &[PathBuf],
+    runtime_name: &str,
+    settings: &TexturePackSettings,
+    mut bind_surface: F
+) -> Result<TexturePack,WamBuildError>
+where
+    F: FnMut() -> (u32,PathBuf)
+{
+    let mut images = Vec::with_capacity(image_paths.len());
+    for path in image_paths {
+        match image::open(path) {
+            Ok(image) => images.push(PackImage {
+                bitmap: image.to_rgba8(),
+                path: path.clone()
+            }),
+            Err(error) => return Err(WamBuildError::ImageDecodeFailure {
+                path: path.clone(),
+                error
+            }),
+        }
+    }
+
+    // Biggest first packs best. The path keeps the order stable between builds.
+    images.sort_by(|a,b|b.largest_dimension().cmp(&a.largest_dimension()).then_with(||a.path.cmp(&b.path)));
+
+    let mut surfaces: Vec<(LayoutSurface,PathBuf)> = Vec::new();
+    let mut new_surface = |surfaces: &mut Vec<(LayoutSurface,PathBuf)>| {
+        let (id,destination) = bind_surface();
+        surfaces.push((LayoutSurface::new(settings.size,id),destination));
+    };
+    new_surface(&mut surfaces);
+
+    let mut slices = Vec::with_capacity(images.len());
+
+    for image in images.iter() {
+        let mut placed = place_image(&mut surfaces,image,runtime_name,settings,0);
+        if placed.is_none() {
+            if !settings.allow_multiple_surfaces {
+                return Err(WamBuildError::PackOverflow(image.path.clone()));
+            }
+            new_surface(&mut surfaces);
+            let last_surface = surfaces.len() - 1;
+            placed = place_image(&mut surfaces,image,runtime_name,settings,last_surface);
+        }
+        match placed {
+            Some(slice) => slices.push(slice),
+            None => return Err(WamBuildError::PackItemTooBig {
+                path: image.path.clone(),
+                width: image.bitmap.width(),
+                height: image.bitmap.height()
+            }),
+        }
+    }
+
+    let mut files = Vec::with_capacity(surfaces.len());
+    let mut image_size_hints = Vec::with_capacity(surfaces.len());
+
+    for (surface,destination) in surfaces {
+        let data = match surface.export(settings.export_format) {
+            Ok(value) => value,
+            Err(error) => return Err(WamBuildError::ImageEncodeFailure {
+                destination,
+                error
+            }),
+        };
+        image_size_hints.push(ImageSizeHint {
+            id: surface.id,
+            x: surface.size,
+            y: surface.size,
+            mipmaps: false
+        });
+        files.push(GeneratedFile {
+            destination,
+            data
+        });
+    }
+
+    return Ok(TexturePack {
+        images: slices,
+        files,
+        image_size_hints
+    });
+}
+
+fn place_image(
+    surfaces: &mut [(LayoutSurface,PathBuf)],
+    image: &PackImage,
+    runtime_name: &str,
+    settings: &TexturePackSettings,
+    first_surface: usize
+) -> Option<VirtualImageSliceAsset> {
+    for (surface,_) in surfaces[first_surface..].iter_mut() {
+        if let Some(slice) = surface.try_add(&image.bitmap,settings.padding) {
+            return Some(VirtualImageSliceAsset {
+                id: surface.id,
+                name: format!("{runtime_name}/{}",file_stem(&image.path)),
+                slice
+            });
+        }
+    }
+    return None;
+}
+
+fn file_stem(path: &Path) -> String {
+    path.file_stem().map(|value|value.to_string_lossy().into_owned()).unwrap_or_default()
+}
@@ -0,0 +1,150 @@
+use std::io::Cursor;
+use image::{DynamicImage, ImageError, Rgba, RgbaImage};
+
+use crate::{definitions::Area, settings::{ImageFormat, PackPadding}};
+
+#[derive(Debug,Clone,Copy,PartialEq,Eq)]
+enum CollisionValue {
+    Nothing,
+    Texture,
+    TransparentBuffer,
+}
+
+/// One square image in a texture pack, filled with a first fit scan (top to bottom, left to right)
+pub struct LayoutSurface {
+    pub id: u32,
+    pub size: u32,
+    collision_map: Vec<CollisionValue>,
+    image: RgbaImage,
+}
+
+impl LayoutSurface {
+    pub fn new(size: u32,id: u32) -> Self {
+        Self {
+            id,
+            size,
+            collision_map: vec![CollisionValue::Nothing;(size * size) as usize],
+            image: RgbaImage::new(size,size),
+        }
+    }
+
+    fn collision_value(&self,x: u32,y: u32) -> CollisionValue {
+        self.collision_map[(y * self.size + x) as usize]
+    }
+
+    /// The first column after `area.x` that could possibly fit, or `None` if `area` fits
+    fn find_collision(&self,area: Area,padding: PackPadding) -> Option<u32> {
+        let end_x = area.x + area.width;
+        let end_y = area.y + area.height;
+        for y in area.y..end_y {
+            for x in area.x..end_x {
+                let value = self.collision_value(x,y);
+                if value == CollisionValue::Nothing {
+                    continue;
+                }
+                let is_edge = x == area.x || x == end_x - 1 || y == area.y || y == end_y - 1;
+                if padding == PackPadding::TransparentBuffer && value == CollisionValue::TransparentBuffer {
+                    if is_edge {
+                        continue;
+                    }
+                    // Moving over by one could turn this into an edge
+                    return Some(area.x + 1);
+                }
+                return Some(x + 1);
+            }
+        }
+        return None;
+    }
+
+    fn fill_collision_map(&mut self,area: Area,padding: PackPadding) {
+        let end_x = area.x + area.width;
+        let end_y = area.y + area.height;
+        for y in area.y..end_y {
+            for x in area.x..end_x {
+                let is_edge = x == area.x || x == end_x - 1 || y == area.y || y == end_y - 1;
+                self.collision_map[(y * self.size + x) as usize] = match padding == PackPadding::TransparentBuffer && is_edge {
+                    true => CollisionValue::TransparentBuffer,
+                    false => CollisionValue::Texture,
+                };
+            }
+        }
+    }
+
+    fn draw(&mut self,bitmap: &RgbaImage,x: u32,y: u32,padding: PackPadding) {
+        let (width,height) = bitmap.dimensions();
+        match padding {
+            PackPadding::None => {
+                for (source_x,source_y,pixel) in bitmap.enumerate_pixels() {
+                    self.image.put_pixel(x + source_x,y + source_y,*pixel);
+                }
+            },
+            PackPadding::TransparentBuffer => {
+                for (source_x,source_y,pixel) in bitmap.enumerate_pixels() {
+                    self.image.put_pixel(x + 1 + source_x,y + 1 + source_y,*pixel);
+                }
+            },
+            PackPadding::EdgeExtension => {
+                // The border repeats the outermost pixels, corners included
+                for target_y in 0..height + 2 {
+                    let source_y = target_y.saturating_sub(1).min(height - 1);
+                    for target_x in 0..width + 2 {
+                        let source_x = target_x.saturating_sub(1).min(width - 1);
+                        let pixel: Rgba<u8> = *bitmap.get_pixel(source_x,source_y);
+                        self.image.put_pixel(x + target_x,y + target_y,pixel);
+                    }
+                }
+            },
+        }
+    }
+
+    /// Returns the area of the bitmap itself, excluding any padding
+    pub fn try_add(&mut self,bitmap: &RgbaImage,padding: PackPadding) -> Option<Area> {
+        let (bitmap_width,bitmap_height) = bitmap.dimensions();
+        if bitmap_width == 0 || bitmap_height == 0 {
+            return None;
+        }
+
+        let (width,height) = match padding {
+            PackPadding::None => (bitmap_width,bitmap_height),
+            _ => (bitmap_width + 2,bitmap_height + 2),
+        };
+
+        if width > self.size || height > self.size {
+            return None;
+        }
+
+        for y in 0..=self.size - height {
+            let mut x = 0;
+            while x <= self.size - width {
+                let area = Area { x, y, width, height };
+                match self.find_collision(area,padding) {
+                    Some(next_x) => x = next_x,
+                    None => {
+                        self.fill_collision_map(area,padding);
+                        self.draw(bitmap,x,y,padding);
+                        return Some(match padding {
+                            PackPadding::None => area,
+                            _ => Area {
+                                x: x + 1,
+                                y: y + 1,
+                                width: bitmap_width,
+                                height: bitmap_height
+                            },
+                        });
+                    },
+                }
+            }
+        }
+        return None;
+    }
+
+    pub fn export(&self,format: ImageFormat) -> Result<Vec<u8>,ImageError> {
+        let mut data = Cursor::new(Vec::new());
+        let image_format = match format {
+            ImageFormat::Png => image::ImageFormat::Png,
+            ImageFormat::Webp => image::ImageFormat::WebP,
+        };
+        DynamicImage::ImageRgba8(self.image.clone()).write_to(&mut data,image_format)?;
+        return Ok(data.into_inner());
+    }
+}
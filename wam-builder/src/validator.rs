@@ -0,0 +1,409 @@
+use std::{collections::{BTreeMap, HashMap, HashSet}, fmt, fs, path::{Path, PathBuf}};
+
+use crate::{definitions::*, error::WamBuildError, ktx2};
+
+/// A problem found by [`validate_manifest`]. Every issue is a failure, the engine would error (or silently lose an asset) at runtime.
+#[derive(Debug)]
+pub enum ValidationIssue {
+    DuplicateHardAssetId {
+        namespace: String,
+        id: u32
+    },
+    MissingFile {
+        namespace: String,
+        id: u32,
+        path: PathBuf
+    },
+    UnreadableImage {
+        namespace: String,
+        path: PathBuf,
+        error: image::ImageError
+    },
+    SizeMismatch {
+        namespace: String,
+        path: PathBuf,
+        hint: (u32,u32),
+        actual: (u32,u32)
+    },
+    InvalidModel {
+        namespace: String,
+        path: PathBuf,
+        error: gltf::Error
+    },
+    ModelWithoutMeshes {
+        namespace: String,
+        path: PathBuf
+    },
+    InvalidData {
+        namespace: String,
+        path: PathBuf,
+        error: serde_json::Error
+    },
+    SizeHintMissingOwner {
+        namespace: String,
+        id: u32
+    },
+    MissingSizeHint {
+        namespace: String,
+        name: String,
+        id: u32
+    },
+    MissingHardAsset {
+        namespace: String,
+        name: String,
+        id: u32
+    },
+    UnexpectedAssetType {
+        namespace: String,
+        name: String,
+        id: u32,
+        found: FileType
+    },
+    SliceOutOfBounds {
+        namespace: String,
+        name: String,
+        slice: Area,
+        size: (u32,u32)
+    },
+    MeshletCountMismatch {
+        namespace: String,
+        name: String,
+        descriptors: usize,
+        primitives: usize
+    },
+    DuplicateVirtualName {
+        namespace: String,
+        name: String
+    },
+    UnusedHardAsset {
+        namespace: String,
+        id: u32,
+        source: String
+    },
+}
+
+impl fmt::Display for ValidationIssue {
+    fn fmt(&self,f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ValidationIssue::DuplicateHardAssetId { namespace, id } => write!(f,"[{namespace}] hard asset id {id} is used more than once; ids must be unique within a namespace"),
+            ValidationIssue::MissingFile { namespace, id, path } => write!(f,"[{namespace}] hard asset {id} points to '{}' but it does not exist",path.display()),
+            ValidationIssue::UnreadableImage { namespace, path, error } => write!(f,"[{namespace}] could not decode image '{}': {error}",path.display()),
+            ValidationIssue::SizeMismatch { namespace, path, hint, actual } => write!(f,
+                "[{namespace}] image '{}' is {}x{} but its size hint is {}x{}; rebuild the manifest",
+                path.display(),actual.0,actual.1,hint.0,hint.1
+            ),
+            ValidationIssue::InvalidModel { namespace, path, error } => write!(f,"[{namespace}] could not parse model '{}' as gltf: {error}",path.display()),
+            ValidationIssue::ModelWithoutMeshes { namespace, path } => write!(f,"[{namespace}] model '{}' does not contain a mesh",path.display()),
+            ValidationIssue::InvalidData { namespace, path, error } => write!(f,"[{namespace}] could not parse data '{}' as json: {error}",path.display()),
+            ValidationIssue::SizeHintMissingOwner { namespace, id } => write!(f,"[{namespace}] size hint for id {id} has no hard asset"),
+            ValidationIssue::MissingSizeHint { namespace, name, id } => write!(f,"[{namespace}] '{name}' uses image {id} but the image has no size hint"),
+            ValidationIssue::MissingHardAsset { namespace, name, id } => write!(f,"[{namespace}] '{name}' points to hard asset {id} but it does not exist"),
+            ValidationIssue::UnexpectedAssetType { namespace, name, id, found } => write!(f,"[{namespace}] '{name}' points to hard asset {id} but it is of the wrong type ({found})"),
+            ValidationIssue::SliceOutOfBounds { namespace, name, slice, size } => write!(f,
+                "[{namespace}] slice '{name}' ({}, {}, {}x{}) does not fit its {}x{} image",
+                slice.x,slice.y,slice.width,slice.height,size.0,size.1
+            ),
+            ValidationIssue::MeshletCountMismatch { namespace, name, descriptors, primitives } => write!(f,
+                "[{namespace}] model '{name}' has {descriptors} meshlet descriptor(s) but its mesh has {primitives} primitive(s); update the meshlets in its model.json"
+            ),
+            ValidationIssue::DuplicateVirtualName { namespace, name } => write!(f,"[{namespace}] virtual asset name '{name}' is used more than once; only one of them will load"),
+            ValidationIssue::UnusedHardAsset { namespace, id, source } => write!(f,"[{namespace}] hard asset {id} ('{source}') is not used by any virtual asset"),
+        }
+    }
+}
+
+#[derive(Debug,Default)]
+pub struct ValidationReport {
+    pub issues: Vec<ValidationIssue>,
+    /// Hard assets whose files were found and checked
+    pub checked_files: usize,
+}
+
+impl ValidationReport {
+    pub fn is_valid(&self) -> bool {
+        self.issues.is_empty()
+    }
+}
+
+/// What was learned from a hard asset's file, if it could be read
+#[derive(Clone,Copy)]
+enum FileInfo {
+    Unknown,
+    Image { width: u32, height: u32 },
+    Model { primitives: usize },
+}
+
+/// Other images are fully decoded to make sure they can be, KTX2 containers only have their header read
+fn read_image_dimensions(path: &Path) -> image::ImageResult<(u32,u32)> {
+    match ktx2::is_ktx2(path) {
+        true => ktx2::read_dimensions(path),
+        false => image::open(path).map(|image|(image.width(),image.height())),
+    }
+}
+
+/// Checks a manifest against the files on disk. `root` is the directory hard asset sources are relative to, normally the manifest's parent.
+///
+/// Only failing to read or parse the manifest itself is an error, everything else is collected in the report.
+pub fn validate_manifest(manifest_path: &Path,root: &Path) -> Result<ValidationReport,WamBuildError> {
+    let json_text = match fs::read_to_string(manifest_path) {
+        Ok(value) => value,
+        Err(error) => return Err(WamBuildError::ReadFailure {
+            path: manifest_path.to_path_buf(),
+            error
+        }),
+    };
+    /* Sorted so the report is stable between runs */
+    let namespaces: BTreeMap<String,Namespace> = match serde_json::from_str(&json_text) {
+        Ok(value) => value,
+        Err(error) => return Err(WamBuildError::InvalidJson {
+            path: manifest_path.to_path_buf(),
+            error
+        }),
+    };
+
+    let mut report = ValidationReport::default();
+    for (name,namespace) in namespaces.iter() {
+        NamespaceValidator {
+            name,
+            root,
+            report: &mut report,
+            hard_assets: HashMap::with_capacity(namespace.hard_assets.len()),
+            size_hints: HashMap::with_capacity(namespace.image_size_hints.len()),
+            used_ids: HashSet::with_capacity(namespace.hard_assets.len()),
+        }.validate(namespace);
+    }
+    return Ok(report);
+}
+
+/// Mirrors `get_full_path` in the engine's asset manager
+pub(crate) fn resolve_source(root: &Path,source: &str) -> PathBuf {
+    let mut path = root.to_path_buf();
+    for component in source.split('/') {
+        path.push(component);
+    }
+    return path;
+}
+
+struct NamespaceValidator<'a> {
+    name: &'a str,
+    root: &'a Path,
+    report: &'a mut ValidationReport,
+    hard_assets: HashMap<u32,(FileType,FileInfo)>,
+    size_hints: HashMap<u32,(u32,u32)>,
+    used_ids: HashSet<u32>,
+}
+
+impl NamespaceValidator<'_> {
+    fn issue(&mut self,issue: ValidationIssue) {
+        self.report.issues.push(issue);
+    }
+
+    fn validate(mut self,namespace: &Namespace) {
+        for size_hint in namespace.image_size_hints.iter() {
+            self.size_hints.insert(size_hint.id,(size_hint.x,size_hint.y));
+        }
+
+        for hard_asset in namespace.hard_assets.iter() {
+            if self.hard_assets.contains_key(&hard_asset.id) {
+                self.issue(ValidationIssue::DuplicateHardAssetId {
+                    namespace: self.name.to_string(),
+                    id: hard_asset.id
+                });
+                continue;
+            }
+            let info = self.check_file(hard_asset);
+            self.hard_assets.insert(hard_asset.id,(hard_asset.r#type,info));
+        }
+
+        for size_hint in namespace.image_size_hints.iter() {
+            if !self.hard_assets.contains_key(&size_hint.id) {
+                self.issue(ValidationIssue::SizeHintMissingOwner {
+                    namespace: self.name.to_string(),
+                    id: size_hint.id
+                });
+            }
+        }
+
+        let mut names = HashSet::new();
+        let all_names = namespace.virtual_assets.iter().map(|asset|&asset.name)
+            .chain(namespace.virtual_image_slice_assets.iter().map(|asset|&asset.name))
+            .chain(namespace.virtual_model_assets.iter().map(|asset|&asset.name));
+        for name in all_names {
+            if !names.insert(name) {
+                self.issue(ValidationIssue::DuplicateVirtualName {
+                    namespace: self.name.to_string(),
+                    name: name.clone()
+                });
+            }
+        }
+
+        for asset in namespace.virtual_assets.iter() {
+            if let Some((FileType::Image,_)) = self.get_hard_asset(&asset.name,asset.id,&[FileType::Image,FileType::Text,FileType::Audio,FileType::Binary,FileType::Data]) {
+                self.check_size_hint(&asset.name,asset.id);
+            }
+        }
+
+        for asset in namespace.virtual_image_slice_assets.iter() {
+            let Some((_,info)) = self.get_hard_asset(&asset.name,asset.id,&[FileType::Image]) else {
+                continue;
+            };
+            let size = match (self.check_size_hint(&asset.name,asset.id),info) {
+                (_,FileInfo::Image { width, height }) => (width,height),
+                (Some(hint),_) => hint,
+                _ => continue,
+            };
+            let slice = asset.slice;
+            if slice.x as u64 + slice.width as u64 > size.0 as u64 || slice.y as u64 + slice.height as u64 > size.1 as u64 {
+                self.issue(ValidationIssue::SliceOutOfBounds {
+                    namespace: self.name.to_string(),
+                    name: asset.name.clone(),
+                    slice,
+                    size
+                });
+            }
+        }
+
+        for asset in namespace.virtual_model_assets.iter() {
+            for meshlet in asset.meshlets.iter() {
+                for id in [meshlet.diffuse,meshlet.lightmap].into_iter().flatten() {
+                    if self.get_hard_asset(&asset.name,id,&[FileType::Image]).is_some() {
+                        self.check_size_hint(&asset.name,id);
+                    }
+                }
+            }
+            let Some((_,FileInfo::Model { primitives })) = self.get_hard_asset(&asset.name,asset.id,&[FileType::Model]) else {
+                continue;
+            };
+            if asset.meshlets.len() != primitives {
+                self.issue(ValidationIssue::MeshletCountMismatch {
+                    namespace: self.name.to_string(),
+                    name: asset.name.clone(),
+                    descriptors: asset.meshlets.len(),
+                    primitives
+                });
+            }
+        }
+
+        for hard_asset in namespace.hard_assets.iter() {
+            if !self.used_ids.contains(&hard_asset.id) {
+                self.issue(ValidationIssue::UnusedHardAsset {
+                    namespace: self.name.to_string(),
+                    id: hard_asset.id,
+                    source: hard_asset.source.clone()
+                });
+                /* Only report each id once, even if it is a duplicate */
+                self.used_ids.insert(hard_asset.id);
+            }
+        }
+    }
+
+    fn check_file(&mut self,hard_asset: &HardAsset) -> FileInfo {
+        let path = resolve_source(self.root,&hard_asset.source);
+        if !path.is_file() {
+            self.issue(ValidationIssue::MissingFile {
+                namespace: self.name.to_string(),
+                id: hard_asset.id,
+                path
+            });
+            return FileInfo::Unknown;
+        }
+        self.report.checked_files += 1;
+
+        match hard_asset.r#type {
+            FileType::Text | FileType::Audio | FileType::Binary => FileInfo::Unknown,
+            FileType::Data => {
+                let parsed = fs::read(&path).map(|data|serde_json::from_slice::<serde_json::Value>(&data));
+                if let Ok(Err(error)) = parsed {
+                    self.issue(ValidationIssue::InvalidData {
+                        namespace: self.name.to_string(),
+                        path,
+                        error
+                    });
+                }
+                FileInfo::Unknown
+            },
+            FileType::Image => match read_image_dimensions(&path) {
+                Ok(actual) => {
+                    if let Some(hint) = self.size_hints.get(&hard_asset.id).copied() && hint != actual {
+                        self.issue(ValidationIssue::SizeMismatch {
+                            namespace: self.name.to_string(),
+                            path,
+                            hint,
+                            actual
+                        });
+                    }
+                    FileInfo::Image {
+                        width: actual.0,
+                        height: actual.1
+                    }
+                },
+                Err(error) => {
+                    self.issue(ValidationIssue::UnreadableImage {
+                        namespace: self.name.to_string(),
+                        path,
+                        error
+                    });
+                    FileInfo::Unknown
+                },
+            },
+            FileType::Model => match gltf::import(&path) {
+                /* The engine only imports the first mesh, one meshlet per primitive */
+                Ok((document,_,_)) => match document.meshes().next() {
+                    Some(mesh) => FileInfo::Model {
+                        primitives: mesh.primitives().len()
+                    },
+                    None => {
+                        self.issue(ValidationIssue::ModelWithoutMeshes {
+                            namespace: self.name.to_string(),
+                            path
+                        });
+                        FileInfo::Unknown
+                    },
+                },
+                Err(error) => {
+                    self.issue(ValidationIssue::InvalidModel {
+                        namespace: self.name.to_string(),
+                        path,
+                        error
+                    });
+                    FileInfo::Unknown
+                },
+            },
+        }
+    }
+
+    /// Marks the hard asset as used, reporting it if it is missing or not one of `expected`
+    fn get_hard_asset(&mut self,name: &str,id: u32,expected: &[FileType]) -> Option<(FileType,FileInfo)> {
+        self.used_ids.insert(id);
+        let Some((file_type,info)) = self.hard_assets.get(&id).copied() else {
+            self.issue(ValidationIssue::MissingHardAsset {
+                namespace: self.name.to_string(),
+                name: name.to_string(),
+                id
+            });
+            return None;
+        };
+        if !expected.contains(&file_type) {
+            self.issue(ValidationIssue::UnexpectedAssetType {
+                namespace: self.name.to_string(),
+                name: name.to_string(),
+                id,
+                found: file_type
+            });
+            return None;
+        }
+        return Some((file_type,info));
+    }
+
+    fn check_size_hint(&mut self,name: &str,id: u32) -> Option<(u32,u32)> {
+        let size_hint = self.size_hints.get(&id).copied();
+        if size_hint.is_none() {
+            self.issue(ValidationIssue::MissingSizeHint {
+                namespace: self.name.to_string(),
+                name: name.to_string(),
+                id
+            });
+        }
+        return size_hint;
+    }
+}
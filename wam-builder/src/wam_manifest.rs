@@ -0,0 +1,536 @@
+const INPUT_MANIFEST_NAME: &'static str = "manifest.json";
+const MODEL_MANIFEST_NAME: &'static str = "model.json";
+const PACK_FILE: &'static str = "pack";
+
+const MODEL_ITEM_KEY: &'static str = "model";
+const DIFFUSE_ITEM_KEY: &'static str = "diffuse";
+const LIGHTMAP_ITEM_KEY: &'static str = "lightmap";
+
+const JSON_INDENT: &'static [u8] = b"    ";
+
+use std::{collections::{BTreeMap, BTreeSet, HashMap, HashSet}, fs, path::{Path, PathBuf}};
+use serde::{Serialize, de::DeserializeOwned};
+
+use crate::{definitions::*, error::WamBuildError, ktx2, namespace_builder::NamespaceBuilder, settings::WamManifestSettings, texture_pack};
+
+/// A hard asset to register with `WamManifest::bind_asset`
+struct AssetBinding<'a> {
+    runtime_name: &'a str,
+    namespace_name: &'a str,
+    /// Copied to the output, `None` for files the builder generates
+    source: Option<&'a Path>,
+    extension: &'a str,
+    file_type: FileType,
+}
+
+/// A file named by a `model.json`, see `WamManifest::get_model_item`
+struct ModelItem<'a> {
+    runtime_name: &'a str,
+    item: Option<&'a str>,
+    item_key: &'a str,
+    required_type: FileType,
+}
+
+struct QualifiedInputManifest {
+    name: String,
+    includes: Vec<String>,
+    path: PathBuf,
+}
+
+/// The result of building a source asset tree. Nothing is written until `write_output` is called.
+pub struct WamManifest {
+    settings: WamManifestSettings,
+    next_id: u32,
+    used_guids: HashSet<String>,
+    pub namespaces: BTreeMap<String,Namespace>,
+    pub file_maps: Vec<FileMap>,
+    pub generated_files: Vec<GeneratedFile>,
+    /// Things that didn't stop the build, but probably should be looked at
+    pub warnings: Vec<String>,
+}
+
+impl WamManifest {
+    pub fn build(settings: WamManifestSettings) -> Result<Self,WamBuildError> {
+        let mut manifest = Self {
+            settings,
+            next_id: 0,
+            used_guids: HashSet::new(),
+            namespaces: BTreeMap::new(),
+            file_maps: Vec::new(),
+            generated_files: Vec::new(),
+            warnings: Vec::new(),
+        };
+
+        if manifest.settings.target_namespace.trim().is_empty() {
+            return Err(WamBuildError::InvalidTargetNamespace);
+        }
+
+        let source = manifest.settings.source.clone();
+        if !source.is_dir() {
+            return Err(WamBuildError::SourceNotFound(source));
+        }
+
+        let all_namespaces = manifest.find_namespaces(&source)?;
+        let target = manifest.settings.target_namespace.clone();
+
+        if !all_namespaces.contains_key(&target) {
+            return Err(WamBuildError::TargetNamespaceNotFound(target));
+        }
+
+        let mut required = BTreeSet::new();
+        let mut pending = vec![target];
+        while let Some(name) = pending.pop() {
+            if !required.insert(name.clone()) {
+                continue;
+            }
+            for include in all_namespaces[&name].includes.iter() {
+                if !all_namespaces.contains_key(include) {
+                    return Err(WamBuildError::MissingInclude {
+                        namespace: name,
+                        include: include.clone()
+                    });
+                }
+                pending.push(include.clone());
+            }
+        }
+
+        for name in required {
+            manifest.add_namespace(&all_namespaces[&name])?;
+        }
+
+        return Ok(manifest);
+    }
+
+    fn find_namespaces(&mut self,source: &Path) -> Result<HashMap<String,QualifiedInputManifest>,WamBuildError> {
+        let mut namespaces = HashMap::new();
+        for directory in read_directory(source)?.directories {
+            let manifest_path = directory.join(INPUT_MANIFEST_NAME);
+            if !manifest_path.is_file() {
+                self.warnings.push(format!("is '{}' a namespace? it does not include a manifest",directory.display()));
+                continue;
+            }
+            let input: InputManifest = match read_json(&manifest_path) {
+                Ok(value) => value,
+                Err(error) => {
+                    self.warnings.push(format!("is '{}' a namespace? its manifest is bad: {error}",directory.display()));
+                    continue;
+                },
+            };
+            let Some(name) = input.name.filter(|name|!name.trim().is_empty()) else {
+                self.warnings.push(format!("is '{}' a namespace? its manifest does not include a namespace identifier",directory.display()));
+                continue;
+            };
+            if namespaces.contains_key(&name) {
+                return Err(WamBuildError::NamespaceCollision(name));
+            }
+            namespaces.insert(name.clone(),QualifiedInputManifest {
+                name,
+                includes: input.includes,
+                path: directory
+            });
+        }
+        return Ok(namespaces);
+    }
+
+    fn add_namespace(&mut self,manifest: &QualifiedInputManifest) -> Result<(),WamBuildError> {
+        let mut builder = NamespaceBuilder::default();
+        self.walk_directory(manifest,&mut builder,&manifest.path)?;
+        self.namespaces.insert(manifest.name.clone(),builder.namespace);
+        return Ok(());
+    }
+
+    /// Registers a hard asset and schedules `source` (if any) to be copied to the output. Returns the ID and output path.
+    fn bind_asset(&mut self,builder: &mut NamespaceBuilder,binding: AssetBinding) -> (u32,PathBuf) {
+        let AssetBinding { runtime_name, namespace_name, source, extension, file_type } = binding;
+        let name = match self.settings.use_guids {
+            true => self.next_guid(),
+            false => format!("{namespace_name}/{runtime_name}"),
+        };
+        let name = builder.qualify_asset_name(name);
+        let id = self.next_id;
+        self.next_id += 1;
+
+        let file_source = format!("{name}.{extension}");
+        let destination = self.settings.destination.join(&file_source);
+
+        builder.namespace.hard_assets.push(HardAsset {
+            id,
+            r#type: file_type,
+            source: file_source
+        });
+
+        if let Some(source) = source {
+            self.file_maps.push(FileMap {
+                source: source.to_path_buf(),
+                destination: destination.clone()
+            });
+            if file_type == FileType::Image {
+                let dimensions = match ktx2::is_ktx2(source) {
+                    true => ktx2::read_dimensions(source),
+                    false => image::image_dimensions(source),
+                };
+                let (x,y) = match dimensions {
+                    Ok(value) => value,
+                    Err(error) => {
+                        self.warnings.push(format!("could not decode image bounds for '{}': {error}",source.display()));
+                        (0,0)
+                    },
+                };
+                builder.namespace.image_size_hints.push(ImageSizeHint { id, x, y, mipmaps: false });
+            }
+        }
+
+        return (id,destination);
+    }
+
+    fn next_guid(&mut self) -> String {
+        loop {
+            let guid = uuid::Uuid::new_v4().to_string();
+            if self.used_guids.insert(guid.clone()) {
+                return guid;
+            }
+        }
+    }
+
+    fn walk_directory(&mut self,manifest: &QualifiedInputManifest,builder: &mut NamespaceBuilder,directory: &Path) -> Result<(),WamBuildError> {
+        /* The namespace root can't be anything else, so it is never checked for special directory modes */
+        if directory != manifest.path {
+            /* Special directories don't recurse for generic assets, their contents belong to the mode */
+            let build_pack = directory.join(PACK_FILE).is_file();
+            let build_model = directory.join(MODEL_MANIFEST_NAME).is_file();
+
+            if build_pack && build_model {
+                return Err(WamBuildError::ConflictingDirectoryType(directory.to_path_buf()));
+            }
+            if build_model {
+                return self.build_model(manifest,builder,directory);
+            }
+            if build_pack {
+                return self.build_pack(manifest,builder,directory);
+            }
+        }
+
+        let listing = read_directory(directory)?;
+
+        for file in listing.files {
+            let Some(file_type) = FileType::from_path(&file) else {
+                continue;
+            };
+            /* The namespace's own manifest is json too, but it isn't data */
+            if directory == manifest.path && file.file_name().is_some_and(|name|name == INPUT_MANIFEST_NAME) {
+                continue;
+            }
+            let runtime_name = relative_name(&manifest.path,&file.with_extension(""));
+            let (id,_) = self.bind_asset(builder,AssetBinding {
+                runtime_name: &runtime_name,
+                namespace_name: &manifest.name,
+                source: Some(&file),
+                extension: &extension(&file),
+                file_type
+            });
+            match file_type {
+                // A loose model has no textures, but it can only be referenced as a model asset
+                FileType::Model => builder.namespace.virtual_model_assets.push(VirtualModelAsset {
+                    id,
+                    name: runtime_name,
+                    meshlets: Vec::new()
+                }),
+                _ => builder.namespace.virtual_assets.push(VirtualAsset {
+                    id,
+                    name: runtime_name
+                }),
+            }
+        }
+
+        for subdirectory in listing.directories {
+            self.walk_directory(manifest,builder,&subdirectory)?;
+        }
+
+        return Ok(());
+    }
+
+    fn get_model_item(
+        &mut self,
+        manifest: &QualifiedInputManifest,
+        builder: &mut NamespaceBuilder,
+        directory: &Path,
+        model_item: ModelItem
+    ) -> Result<Option<u32>,WamBuildError> {
+        let ModelItem { runtime_name, item, item_key, required_type } = model_item;
+        let Some(item) = item.filter(|item|!item.trim().is_empty()) else {
+            return Ok(None);
+        };
+        let item_path = directory.join(item);
+        if !item_path.is_file() {
+            return Err(WamBuildError::ModelItemNotFound {
+                model: runtime_name.to_string(),
+                item: item.to_string()
+            });
+        }
+        if FileType::from_path(&item_path) != Some(required_type) {
+            return Err(WamBuildError::ModelItemWrongType {
+                model: runtime_name.to_string(),
+                item: item.to_string()
+            });
+        }
+        let (id,_) = self.bind_asset(builder,AssetBinding {
+            runtime_name: &format!("{runtime_name}/{item_key}"),
+            namespace_name: &manifest.name,
+            source: Some(&item_path),
+            extension: &extension(&item_path),
+            file_type: required_type
+        });
+        return Ok(Some(id));
+    }
+
+    fn build_model(&mut self,manifest: &QualifiedInputManifest,builder: &mut NamespaceBuilder,directory: &Path) -> Result<(),WamBuildError> {
+        let runtime_name = relative_name(&manifest.path,directory);
+        let model_manifest: ModelManifest = read_json(&directory.join(MODEL_MANIFEST_NAME))?;
+
+        let model = self.get_model_item(manifest,builder,directory,ModelItem {
+            runtime_name: &runtime_name,
+            item: model_manifest.model.as_deref(),
+            item_key: MODEL_ITEM_KEY,
+            required_type: FileType::Model
+        })?;
+        let Some(id) = model else {
+            return Err(WamBuildError::ModelMissingMesh(directory.join(MODEL_MANIFEST_NAME)));
+        };
+
+        let mut meshlets = Vec::with_capacity(model_manifest.meshlets.len());
+        for meshlet in model_manifest.meshlets.iter() {
+            let diffuse = self.get_model_item(manifest,builder,directory,ModelItem {
+                runtime_name: &runtime_name,
+                item: meshlet.diffuse.as_deref(),
+                item_key: DIFFUSE_ITEM_KEY,
+                required_type: FileType::Image
+            })?;
+            let lightmap = self.get_model_item(manifest,builder,directory,ModelItem {
+                runtime_name: &runtime_name,
+                item: meshlet.lightmap.as_deref(),
+                item_key: LIGHTMAP_ITEM_KEY,
+                required_type: FileType::Image
+            })?;
+            if model_manifest.mipmaps && let Some(diffuse) = diffuse {
+                for size_hint in builder.namespace.image_size_hints.iter_mut().filter(|size_hint|size_hint.id == diffuse) {
+                    size_hint.mipmaps = true;
+                }
+            }
+            meshlets.push(MeshletDescriptor { diffuse, lightmap });
+        }
+
+        builder.namespace.virtual_model_assets.push(VirtualModelAsset {
+            id,
+            name: runtime_name,
+            meshlets
+        });
+
+        return Ok(());
+    }
+
+    fn build_pack(&mut self,manifest: &QualifiedInputManifest,builder: &mut NamespaceBuilder,directory: &Path) -> Result<(),WamBuildError> {
+        let mut image_paths: Vec<PathBuf> = read_directory(directory)?.files.into_iter().filter(|file|{
+            FileType::from_path(file) == Some(FileType::Image)
+        }).collect();
+
+        /* Packs are encoded again as a whole, compressed containers can't be decoded here */
+        image_paths.retain(|file|{
+            let packable = !ktx2::is_ktx2(file);
+            if !packable {
+                self.warnings.push(format!("KTX2 image '{}' can't be packed and was left out of its pack",file.display()));
+            }
+            packable
+        });
+
+        let runtime_name = relative_name(&manifest.path,directory);
+        let pack_settings = self.settings.texture_pack.clone();
+        let extension = pack_settings.export_format.extension();
+
+        let pack = texture_pack::build_texture_pack(&image_paths,&runtime_name,&pack_settings,||{
+            self.bind_asset(builder,AssetBinding {
+                runtime_name: &runtime_name,
+                namespace_name: &manifest.name,
+                source: None,
+                extension,
+                file_type: FileType::Image
+            })
+        })?;
+
+        builder.namespace.virtual_image_slice_assets.extend(pack.images);
+        builder.namespace.image_size_hints.extend(pack.image_size_hints);
+        self.generated_files.extend(pack.files);
+
+        return Ok(());
+    }
+
+    pub fn to_json(&self) -> Result<String,WamBuildError> {
+        let result = match self.settings.compress_manifest {
+            true => serde_json::to_vec(&self.namespaces),
+            false => {
+                let mut data = Vec::new();
+                let formatter = serde_json::ser::PrettyFormatter::with_indent(JSON_INDENT);
+                let mut serializer = serde_json::Serializer::with_formatter(&mut data,formatter);
+                self.namespaces.serialize(&mut serializer).map(|_|data)
+            },
+        };
+        return match result {
+            Ok(data) => Ok(String::from_utf8(data).expect("serde_json produces UTF-8")),
+            Err(error) => Err(WamBuildError::JsonEncodeFailure(error)),
+        };
+    }
+
+    pub fn manifest_path(&self) -> PathBuf {
+        self.settings.destination.join(&self.settings.manifest_output_file)
+    }
+
+    /// Clears the destination directory if `clean_destination` is set, then writes generated files, copies source files, and writes the manifest
+    pub fn write_output(&self) -> Result<(),WamBuildError> {
+        /* Generate the JSON before touching any files in case it fails */
+        let json = self.to_json()?;
+
+        let destination = &self.settings.destination;
+        match self.settings.clean_destination {
+            true => {
+                check_clean_destination(destination,&self.settings.source)?;
+                clear_directory(destination)?;
+            },
+            false => if !is_empty_directory(destination)? {
+                return Err(WamBuildError::DestinationNotEmpty(destination.clone()));
+            },
+        }
+
+        for file in self.generated_files.iter() {
+            create_parent(&file.destination)?;
+            if let Err(error) = fs::write(&file.destination,&file.data) {
+                return Err(WamBuildError::WriteFailure { path: file.destination.clone(), error });
+            }
+        }
+
+        for file in self.file_maps.iter() {
+            create_parent(&file.destination)?;
+            if let Err(error) = fs::copy(&file.source,&file.destination) {
+                return Err(WamBuildError::WriteFailure { path: file.destination.clone(), error });
+            }
+        }
+
+        let manifest_path = self.manifest_path();
+        create_parent(&manifest_path)?;
+        if let Err(error) = fs::write(&manifest_path,json) {
+            return Err(WamBuildError::WriteFailure { path: manifest_path, error });
+        }
+
+        return Ok(());
+    }
+}
+
+struct DirectoryListing {
+    files: Vec<PathBuf>,
+    directories: Vec<PathBuf>,
+}
+
+/// Sorted so builds are repeatable across platforms
+fn read_directory(directory: &Path) -> Result<DirectoryListing,WamBuildError> {
+    let entries = match fs::read_dir(directory) {
+        Ok(value) => value,
+        Err(error) => return Err(WamBuildError::ReadFailure { path: directory.to_path_buf(), error }),
+    };
+    let mut listing = DirectoryListing {
+        files: Vec::new(),
+        directories: Vec::new(),
+    };
+    for entry in entries {
+        let path = match entry {
+            Ok(value) => value.path(),
+            Err(error) => return Err(WamBuildError::ReadFailure { path: directory.to_path_buf(), error }),
+        };
+        if path.is_dir() {
+            listing.directories.push(path);
+        } else if path.is_file() {
+            listing.files.push(path);
+        }
+    }
+    listing.files.sort();
+    listing.directories.sort();
+    return Ok(listing);
+}
+
+fn read_json<T: DeserializeOwned>(path: &Path) -> Result<T,WamBuildError> {
+    let text = match fs::read_to_string(path) {
+        Ok(value) => value,
+        Err(error) => return Err(WamBuildError::ReadFailure { path: path.to_path_buf(), error }),
+    };
+    return match serde_json::from_str(&text) {
+        Ok(value) => Ok(value),
+        Err(error) => Err(WamBuildError::InvalidJson { path: path.to_path_buf(), error }),
+    };
+}
+
+/// Runtime names always use forward slashes
+fn relative_name(root: &Path,path: &Path) -> String {
+    let relative = path.strip_prefix(root).unwrap_or(path);
+    let components: Vec<String> = relative.components().map(|component|component.as_os_str().to_string_lossy().into_owned()).collect();
+    return components.join("/");
+}
+
+fn extension(path: &Path) -> String {
+    path.extension().map(|value|value.to_string_lossy().into_owned()).unwrap_or_default()
+}
+
+fn create_parent(path: &Path) -> Result<(),WamBuildError> {
+    let Some(parent) = path.parent() else {
+        return Ok(());
+    };
+    return match fs::create_dir_all(parent) {
+        Ok(()) => Ok(()),
+        Err(error) => Err(WamBuildError::WriteFailure { path: parent.to_path_buf(), error }),
+    };
+}
+
+/// A missing destination counts as empty, it is created on write
+fn is_empty_directory(directory: &Path) -> Result<bool,WamBuildError> {
+    return match fs::read_dir(directory) {
+        Ok(mut entries) => Ok(entries.next().is_none()),
+        Err(error) if error.kind() == std::io::ErrorKind::NotFound => Ok(true),
+        Err(error) => Err(WamBuildError::ReadFailure { path: directory.to_path_buf(), error }),
+    };
+}
+
+/// Refuses a destination that is the source root or one of its parents (such as '/' or a mistyped '..'). Links are resolved first.
+fn check_clean_destination(destination: &Path,source: &Path) -> Result<(),WamBuildError> {
+    let resolved_destination = match fs::canonicalize(destination) {
+        Ok(value) => value,
+        /* Nothing to clean */
+        Err(error) if error.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+        Err(error) => return Err(WamBuildError::ReadFailure { path: destination.to_path_buf(), error }),
+    };
+    let resolved_source = match fs::canonicalize(source) {
+        Ok(value) => value,
+        Err(error) => return Err(WamBuildError::ReadFailure { path: source.to_path_buf(), error }),
+    };
+    if resolved_source.starts_with(&resolved_destination) {
+        return Err(WamBuildError::DestinationContainsSource {
+            destination: destination.to_path_buf(),
+            source: source.to_path_buf()
+        });
+    }
+    return Ok(());
+}
+
+fn clear_directory(directory: &Path) -> Result<(),WamBuildError> {
+    let entries = match fs::read_dir(directory) {
+        Ok(value) => value,
+        Err(error) if error.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+        Err(error) => return Err(WamBuildError::WriteFailure { path: directory.to_path_buf(), error }),
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let result = match path.is_dir() {
+            true => fs::remove_dir_all(&path),
+            false => fs::remove_file(&path),
+        };
+        if let Err(error) = result {
+            return Err(WamBuildError::WriteFailure { path, error });
+        }
+    }
+    return Ok(());
+}
@@ -4,14 +4,16 @@ pub mod wam;
 pub mod kvs;
 pub mod input;
 pub mod fonts;
+pub mod localization;
 
-use std::{path::Path, rc::Rc};
+use std::{fmt, path::Path, rc::Rc};
 use graphics::{*,textures::*};
-use wam::AssetManager;
+use wam::{AssetId, AssetManager, AssetManagerError, AudioAsset, ManifestLoadMode, OverlayKey, PreloadGroup, PreloadGroupKey, PreloadRequest, PreloadResult};
 
 use debug_shell::DebugShell;
+use localization::Localization;
 use input::{InputManager, InputDevice};
-use kvs::KeyValueStore;
+use kvs::{KeyValueStore, KeyValueStoreError, KeyValueStoreMigrations, SaveSlotManager};
 use wgpu::{Queue, Texture};
 
 use crate::UWimpyPoint;
@@ -39,6 +41,10 @@ pub enum WimpyImageData<'a> {
     },
     Custom {
         data: Box<dyn WimpyImageDataWriter>
+    },
+    /// Read from a KTX2 container, see `CompressedImage`
+    Compressed {
+        image: CompressedImage
     }
 }
 
@@ -51,7 +57,8 @@ impl WimpyImageData<'_> {
     pub fn size(&self) -> UWimpyPoint {
         match self {
             WimpyImageData::Buffer { size, .. } => *size,
-            WimpyImageData::Custom{ data } => data.size()
+            WimpyImageData::Custom{ data } => data.size(),
+            WimpyImageData::Compressed { image } => image.size()
         }
     }
 }
@@ -63,23 +70,39 @@ pub trait WimpyIO {
     fn load_binary_file(path: &Path) ->     impl Future<Output = Result<Vec<u8>,FileError>>;
     fn load_text_file(path: &Path) ->       impl Future<Output = Result<String,FileError>>;
 
-    fn load_image_file(path: &Path) ->      impl Future<Output = Result<WimpyImageData<'_>,FileError>>;
+    fn load_image_file(path: &Path) ->      impl Future<Output = Result<WimpyImageData<'static>,FileError>>;
+    /// An encoded image (e.g., PNG) that was already loaded, such as an entry of a `wam::WamArchive`
+    fn decode_image(data: Vec<u8>) ->       impl Future<Output = Result<WimpyImageData<'static>,FileError>>;
+
+    /// Save slot names are produced by `kvs::SaveSlot::name` and are safe to use as file names.
+    fn save_slot(name: &str,data: &[u8],metadata: &[u8]) -> impl Future<Output = Result<(),FileError>>;
+    fn load_slot(name: &str) ->             impl Future<Output = Result<Vec<u8>,FileError>>;
+    fn load_slot_metadata(name: &str) ->    impl Future<Output = Result<Vec<u8>,FileError>>;
+    fn delete_slot(name: &str) ->           impl Future<Output = Result<(),FileError>>;
+    fn list_slots() ->                      impl Future<Output = Result<Vec<String>,FileError>>;
+
+    /// Seconds since the unix epoch
+    fn unix_timestamp() -> u64;
 }
 
 pub struct WimpyAppContext {
     pub graphics:           GraphicsContext,
     pub key_value_store:    KeyValueStore,
+    pub save_slots:         SaveSlotManager,
     pub input:              InputManager,
     pub assets:             AssetManager,
     pub debug_shell:        DebugShell,
+    pub localization:       Localization,
     pub missing_text:       Rc<str>,
 }
 
 pub struct WimpyContextCreationConfig<'a> {
-    pub manifest_path:          Option<&'a Path>,
-    pub input_device_hint:      InputDevice,
-    pub graphics_provider:      GraphicsProvider,
-    pub texture_stream_policy:  StreamingPolicy
+    pub manifest_path:              Option<&'a Path>,
+    pub input_device_hint:          InputDevice,
+    pub graphics_provider:          GraphicsProvider,
+    pub texture_stream_policy:      StreamingPolicy,
+    pub key_value_store_migrations: KeyValueStoreMigrations,
+    pub manifest_load_mode:         ManifestLoadMode,
 }
 
 pub struct EngineTextures {
@@ -108,10 +131,21 @@ where
 {
     fn create(context: &mut WimpyAppContext) -> impl Future<Output = Self>;
     fn update(&mut self,context: &mut WimpyAppContext);
+
+    /// Migrations for the app's key value store, applied when it is imported during context creation
+    fn key_value_store_migrations() -> KeyValueStoreMigrations {
+        KeyValueStoreMigrations::default()
+    }
+
+    /// `Strict` stops the platform from starting the app if its manifest is invalid
+    fn manifest_load_mode() -> ManifestLoadMode {
+        ManifestLoadMode::default()
+    }
 }
 
 impl WimpyAppContext {
-    pub async fn create<IO,TConfig>(config: WimpyContextCreationConfig<'_>) -> Self
+    /// Only fails with `ManifestLoadMode::Strict`
+    pub async fn create<IO,TConfig>(config: WimpyContextCreationConfig<'_>) -> Result<Self,AssetManagerError>
     where
         IO: WimpyIO,
         TConfig: GraphicsConfig
@@ -121,23 +155,46 @@ impl WimpyAppContext {
             config.texture_stream_policy
         );
 
-        let input =   input::InputManager::with_device_start_hint(config.input_device_hint);
-        let storage = kvs::KeyValueStore::default();
+        let mut input = input::InputManager::with_device_start_hint(config.input_device_hint);
+        let mut storage = kvs::KeyValueStore::with_migrations(config.key_value_store_migrations);
+        match storage.import::<IO>().await {
+            Ok(()) => {},
+            Err(KeyValueStoreError::FileError(FileError::NotFound)) => {
+                log::info!("No key value store found, starting with an empty store");
+            },
+            Err(error) => {
+                log::error!("Key value store import failure: {:?}",error);
+            },
+        }
+        if storage.has_key(input::KEY_BINDS_STORAGE_KEY) && let Err(error) = input.import_key_binds(&storage) {
+            log::error!("Key bind import failure, using engine defaults: {:?}",error);
+        }
         let debug =   debug_shell::DebugShell::default();
 
-        let assets = AssetManager::load_or_default::<IO>(
-            config.manifest_path
-        ).await;
+        let (assets,manifest_error) = match config.manifest_path {
+            Some(path) => match AssetManager::load::<IO>(path).await {
+                Ok(assets) => (assets,None),
+                Err(error) if config.manifest_load_mode == ManifestLoadMode::Strict => return Err(error),
+                Err(error) => (AssetManager::new(),Some((path,error))),
+            },
+            None => (AssetManager::new(),None),
+        };
 
         let mut context = Self {
             graphics,
             key_value_store: storage,
+            save_slots: SaveSlotManager::default(),
             input,
             assets,
             debug_shell: debug,
+            localization: Localization::default(),
             missing_text: Rc::from("<missing text asset>"),
         };
 
+        if let Some((path,error)) = manifest_error {
+            context.report_asset_failure(&format!("Manifest '{}'",path.display()),&error);
+        }
+
         context.graphics.texture_manager.engine_textures = EngineTextures {
             font_classic:         context.get_image( "wimpy/font/classic",        StreamingHint::Static),
             font_classic_outline: context.get_image("wimpy/font/classic-outline", StreamingHint::Static),
@@ -146,48 +203,263 @@ impl WimpyAppContext {
             font_mono_elf:        context.get_image("wimpy/font/mono-elf",        StreamingHint::Static),
         };
 
-        context
+        Ok(context)
+    }
+
+    /// Called by the platform once per frame, after the app update. Returns the encoded key value store when an autosave is due.
+    ///
+    /// The platform writes the data with `WimpyIO::save_key_value_store`, and calls `KeyValueStore::mark_dirty` if the write fails.
+    pub fn update_key_value_store(&mut self,delta_seconds: f32) -> Option<Vec<u8>> {
+        self.key_value_store.poll_autosave(delta_seconds)
+    }
+
+    /// Called by the platform when the app is closing or being hidden, the same contract as `update_key_value_store` applies
+    pub fn flush_key_value_store(&mut self) -> Option<Vec<u8>> {
+        self.key_value_store.request_flush();
+        self.key_value_store.poll_autosave(0.0)
+    }
+
+    /// Queues every asset in the group for loading. Poll the returned key with `AssetManager::get_preload_progress`.
+    pub fn preload_assets(&mut self,group: &PreloadGroup) -> PreloadGroupKey {
+        AssetManager::preload(group,self)
+    }
+
+    /// Called by the platform once per frame. Each request is loaded with `PreloadRequest::load` and handed back with `complete_preload`.
+    ///
+    /// Texture streaming loads are handed out after the preload queue, within the same limit.
+    pub fn take_preload_requests(&mut self,limit: usize) -> Vec<PreloadRequest> {
+        let mut requests = self.assets.take_preload_requests(limit);
+        let remaining = limit - requests.len();
+        if remaining > 0 {
+            requests.extend(AssetManager::take_stream_requests(remaining,self));
+        }
+        return requests;
+    }
+
+    /// Called by the platform once per frame, after the app update and before `take_preload_requests`. See `TextureManager::update`.
+    pub fn update_texture_streaming(&mut self,delta_seconds: f32) {
+        let graphics = &mut self.graphics;
+        graphics.texture_manager.update(&graphics.graphics_provider,delta_seconds);
+    }
+
+    pub fn complete_preload(&mut self,result: PreloadResult) {
+        AssetManager::complete_preload(result,self)
+    }
+
+    /// Gives back the group's references, assets that nothing else holds are unloaded
+    pub fn release_preload_group(&mut self,group: PreloadGroupKey) {
+        AssetManager::release_preload_group(group,self)
+    }
+
+    /// Called by the platform's development file watcher with the loaded result of `AssetManager::create_reload_request`
+    pub fn complete_reload(&mut self,result: PreloadResult) {
+        AssetManager::complete_reload(result,self)
+    }
+
+    /// Mounts manifest data (e.g., a mod or a patch) over everything already mounted. Its virtual assets override earlier ones by name.
+    pub fn mount_overlay(&mut self,json_text: &str,label: &str) -> Option<OverlayKey> {
+        match self.assets.mount_overlay(json_text,label) {
+            Ok(key) => Some(key),
+            Err(error) => {
+                self.report_asset_failure(&format!("Overlay '{label}'"),&error);
+                None
+            },
+        }
+    }
+
+    /// Loads another manifest file or `wpak` archive with its own root, and mounts it the same way as `mount_overlay`
+    pub async fn mount_manifest<IO: WimpyIO>(&mut self,path: &Path,label: &str) -> Option<OverlayKey> {
+        match self.assets.mount_manifest::<IO>(path,label).await {
+            Ok(key) => Some(key),
+            Err(error) => {
+                self.report_asset_failure(&format!("Manifest '{}'",path.display()),&error);
+                None
+            },
+        }
+    }
+
+    /// Unmounts the overlay and unloads its assets
+    pub fn unmount_overlay(&mut self,key: OverlayKey) -> bool {
+        AssetManager::unmount_overlay(key,self)
+    }
+
+    /// Logs the failure and lists it in the debug shell, see `DebugShell::set_asset_failure_display`.
+    /// Later failures of the same subject are dropped until `DebugShell::clear_asset_failures` or `DebugShell::forget_asset_failure`.
+    pub fn report_asset_failure(&mut self,subject: &str,error: &impl fmt::Display) {
+        let text = format!("{subject}: {error}");
+        if self.debug_shell.push_asset_failure(subject,&text) {
+            log::error!("{text}");
+        }
+    }
+
+    /// Interns a virtual asset name, such as one built from level data. Keep the id to skip the name lookup on later calls.
+    pub fn resolve_asset(&mut self,name: &str) -> AssetId {
+        self.assets.resolve(name)
     }
 
     // A series of assets that are 'always' expected to be a part of the runtime, such as fonts
     pub fn get_image(&mut self,name: &'static str,streaming_hint: StreamingHint) -> WimpyTexture {
-        match AssetManager::get_image_asset(name,self,streaming_hint) {
+        let id = self.resolve_asset(name);
+        self.get_image_by_id(id,streaming_hint)
+    }
+
+    pub fn get_image_slice(&mut self,name: &'static str,streaming_hint: StreamingHint) -> WimpyTexture {
+        let id = self.resolve_asset(name);
+        match AssetManager::get_image_asset(id,self,streaming_hint) {
             Ok(texture) => texture,
             Err(error) => {
-                log::error!("Image asset load failure: {:?}",error);
+                self.report_asset_failure(&format!("Image slice asset '{}'",self.assets.get_name(id)),&error);
                 self.graphics.texture_manager.runtime_textures.missing
             },
         }
     }
 
-    pub fn get_image_slice(&mut self,name: &'static str,streaming_hint: StreamingHint) -> WimpyTexture {
-        match AssetManager::get_image_asset(name,self,streaming_hint) {
+    /// Works for image slices too
+    pub fn get_image_by_id(&mut self,id: AssetId,streaming_hint: StreamingHint) -> WimpyTexture {
+        match AssetManager::get_image_asset(id,self,streaming_hint) {
             Ok(texture) => texture,
             Err(error) => {
-                log::error!("Image slice asset load failure: {:?}",error);
+                self.report_asset_failure(&format!("Image asset '{}'",self.assets.get_name(id)),&error);
                 self.graphics.texture_manager.runtime_textures.missing
             },
         }
     }
 
     pub async fn get_text<IO: WimpyIO>(&mut self,name: &'static str) -> Rc<str> {
-        match AssetManager::get_text_asset::<IO>(name,self).await {
+        let id = self.resolve_asset(name);
+        self.get_text_by_id::<IO>(id).await
+    }
+
+    /// Missing text is `missing_text` followed by the asset name
+    pub async fn get_text_by_id<IO: WimpyIO>(&mut self,id: AssetId) -> Rc<str> {
+        match AssetManager::get_text_asset::<IO>(id,self).await {
             Ok(text) => text,
             Err(error) => {
-                log::error!("Text asset load failure: {:?}",error);
-                self.missing_text.clone()
+                let name = self.assets.get_name(id);
+                self.report_asset_failure(&format!("Text asset '{name}'"),&error);
+                Rc::from(format!("{} ({name})",self.missing_text))
             },
         }
     }
 
-    // TODO: Create fallback textured mesh inside the mesh cache
-    pub async fn get_model<IO: WimpyIO>(&mut self,name: &'static str) -> Option<TexturedMesh> {
-        match AssetManager::get_model_asset::<IO>(name,self).await {
-            Ok(mesh) => Some(mesh),
+    /// Every successful `get_text` should be paired with one of these once the text is no longer needed
+    pub fn release_text(&mut self,name: &str) {
+        let id = self.resolve_asset(name);
+        AssetManager::release_text_asset(id,self)
+    }
+
+    /// Also releases images returned by `get_image_slice`
+    pub fn release_image(&mut self,name: &str) {
+        let id = self.resolve_asset(name);
+        AssetManager::release_image_asset(id,self)
+    }
+
+    pub fn release_model(&mut self,name: &str) {
+        let id = self.resolve_asset(name);
+        AssetManager::release_model_asset(id,self)
+    }
+
+    pub fn release_text_by_id(&mut self,id: AssetId) {
+        AssetManager::release_text_asset(id,self)
+    }
+
+    pub fn release_image_by_id(&mut self,id: AssetId) {
+        AssetManager::release_image_asset(id,self)
+    }
+
+    pub fn release_model_by_id(&mut self,id: AssetId) {
+        AssetManager::release_model_asset(id,self)
+    }
+
+    pub async fn get_model<IO: WimpyIO>(&mut self,name: &'static str) -> TexturedMesh {
+        let id = self.resolve_asset(name);
+        self.get_model_by_id::<IO>(id).await
+    }
+
+    /// Missing models are a cube with the missing texture, see `MeshCache::get_placeholder`
+    pub async fn get_model_by_id<IO: WimpyIO>(&mut self,id: AssetId) -> TexturedMesh {
+        match AssetManager::get_model_asset::<IO>(id,self).await {
+            Ok(mesh) => mesh,
             Err(error) => {
-                log::error!("Model asset load failure: {:?}",error);
-                None
+                self.report_asset_failure(&format!("Model asset '{}'",self.assets.get_name(id)),&error);
+                self.graphics.mesh_cache.get_placeholder()
+            },
+        }
+    }
+
+    pub async fn get_binary<IO: WimpyIO>(&mut self,name: &'static str) -> Rc<[u8]> {
+        let id = self.resolve_asset(name);
+        self.get_binary_by_id::<IO>(id).await
+    }
+
+    /// Missing binary assets are empty
+    pub async fn get_binary_by_id<IO: WimpyIO>(&mut self,id: AssetId) -> Rc<[u8]> {
+        match AssetManager::get_binary_asset::<IO>(id,self).await {
+            Ok(data) => data,
+            Err(error) => {
+                self.report_asset_failure(&format!("Binary asset '{}'",self.assets.get_name(id)),&error);
+                Rc::from([])
             },
         }
     }
+
+    pub async fn get_audio<IO: WimpyIO>(&mut self,name: &'static str) -> AudioAsset {
+        let id = self.resolve_asset(name);
+        self.get_audio_by_id::<IO>(id).await
+    }
+
+    /// Missing audio is `AudioAsset::silence`
+    pub async fn get_audio_by_id<IO: WimpyIO>(&mut self,id: AssetId) -> AudioAsset {
+        match AssetManager::get_audio_asset::<IO>(id,self).await {
+            Ok(audio) => audio,
+            Err(error) => {
+                self.report_asset_failure(&format!("Audio asset '{}'",self.assets.get_name(id)),&error);
+                AudioAsset::silence()
+            },
+        }
+    }
+
+    /// Deserializes a JSON data asset (e.g., level data) into `T`. The bytes stay cached until `release_data`.
+    pub async fn get_data<IO: WimpyIO,T: serde::de::DeserializeOwned + Default>(&mut self,name: &'static str) -> T {
+        let id = self.resolve_asset(name);
+        self.get_data_by_id::<IO,T>(id).await
+    }
+
+    /// Missing or malformed data is `T::default()`
+    pub async fn get_data_by_id<IO: WimpyIO,T: serde::de::DeserializeOwned + Default>(&mut self,id: AssetId) -> T {
+        match AssetManager::get_data_asset::<IO,T>(id,self).await {
+            Ok(value) => value,
+            Err(error) => {
+                self.report_asset_failure(&format!("Data asset '{}'",self.assets.get_name(id)),&error);
+                T::default()
+            },
+        }
+    }
+
+    pub fn release_binary(&mut self,name: &str) {
+        let id = self.resolve_asset(name);
+        AssetManager::release_binary_asset(id,self)
+    }
+
+    pub fn release_audio(&mut self,name: &str) {
+        let id = self.resolve_asset(name);
+        AssetManager::release_audio_asset(id,self)
+    }
+
+    pub fn release_data(&mut self,name: &str) {
+        let id = self.resolve_asset(name);
+        AssetManager::release_data_asset(id,self)
+    }
+
+    pub fn release_binary_by_id(&mut self,id: AssetId) {
+        AssetManager::release_binary_asset(id,self)
+    }
+
+    pub fn release_audio_by_id(&mut self,id: AssetId) {
+        AssetManager::release_audio_asset(id,self)
+    }
+
+    pub fn release_data_by_id(&mut self,id: AssetId) {
+        AssetManager::release_data_asset(id,self)
+    }
 }
@@ -1,7 +1,7 @@
-use std::{collections::VecDeque, marker::PhantomData, fmt::{self, Write}};
+use std::{collections::{HashSet, VecDeque}, marker::PhantomData, fmt::{self, Write}};
 
 use crate::{collections::pool::StringPool, WimpyColor, WimpyNamedColor, WimpyOpacity, WimpyRect, WimpyRectQuadrant, WimpyVec, WimpyVecAxis};
-use super::fonts;
+use super::fonts::{self, FontDefinition};
 use super::graphics::{textures::RenderTarget, RenderPassBuilder};
 use super::graphics::pipelines::{DrawData2D, LinePoint2D, TextDirection, TextRenderConfig};
 
@@ -9,6 +9,7 @@ const TEXT_MARGIN: f32 = 5.0;
 
 const LOG_LINE_SIZE: usize = 64;
 const LOG_LINE_COUNT: usize = 8;
+const ASSET_FAILURE_LINE_COUNT: usize = 8;
 const GRAPH_BUFFER_SIZE: usize = 1024;
 
 const LOG_CHANNEL_COUNT: usize = 5;
@@ -31,6 +32,11 @@ pub struct DebugShell {
     render_config: DebugRenderConfig,
     labels: LabelSet,
     log_display: LogDisplay,
+    /// Newest first
+    asset_failures: VecDeque<String>,
+    /// Subjects that were reported since the last clear, later failures of the same subject are dropped
+    asset_failure_subjects: HashSet<String>,
+    show_asset_failures: bool,
     buffers: DebugRenderBuffers
 }
 
@@ -363,6 +369,83 @@ impl DebugShell {
         label.clear();
     }
 
+    /// Called by `WimpyAppContext` when a manifest or asset fails to load. Only the most recent failures are kept.
+    ///
+    /// `false` if the subject was already reported, the failure isn't listed again until it is cleared or forgotten.
+    pub fn push_asset_failure(&mut self,subject: &str,text: &str) -> bool {
+        if !self.asset_failure_subjects.insert(subject.to_string()) {
+            return false;
+        }
+        if self.asset_failures.len() >= ASSET_FAILURE_LINE_COUNT {
+            self.asset_failures.pop_back();
+        }
+        self.asset_failures.push_front(text.to_string());
+        return true;
+    }
+
+    /// The next failure of the subject is reported again, e.g., after a reload of the asset succeeded
+    pub fn forget_asset_failure(&mut self,subject: &str) {
+        self.asset_failure_subjects.remove(subject);
+    }
+
+    /// Newest first
+    pub fn get_asset_failures(&self) -> impl Iterator<Item = &str> {
+        self.asset_failures.iter().map(String::as_str)
+    }
+
+    /// Failures that happen again afterwards are reported again
+    pub fn clear_asset_failures(&mut self) {
+        self.asset_failures.clear();
+        self.asset_failure_subjects.clear();
+    }
+
+    /// Lists recent asset failures along the bottom of the frame, on top of the panes
+    pub fn set_asset_failure_display(&mut self,enabled: bool) {
+        self.show_asset_failures = enabled;
+    }
+
+    fn draw_asset_failures<TRenderTarget>(&mut self,render_pass: &mut RenderPassBuilder<'_,TRenderTarget>,frame_size: WimpyVec)
+    where
+        TRenderTarget: RenderTarget
+    {
+        if !self.show_asset_failures || self.asset_failures.is_empty() {
+            return;
+        }
+        let config = &TextRenderConfig {
+            scale: 2.0,
+            line_height_scale: 1.0,
+            word_seperator: ' ',
+        };
+        let line_height = fonts::FontMonoElf::get_line_height(config.scale);
+        let height = line_height * self.asset_failures.len() as f32 + TEXT_MARGIN * 2.0;
+        let area = WimpyRect {
+            position: WimpyVec::from([0.0,frame_size.y - height]),
+            size: WimpyVec::from([frame_size.x,height]),
+        };
+
+        let mut pipeline = render_pass.set_pipeline_2d();
+        pipeline.draw_untextured([DrawData2D {
+            destination: area,
+            source: WimpyRect::ONE,
+            color: (WimpyNamedColor::Black,WimpyOpacity::Percent75).into_linear(),
+            rotation: 0.0,
+        }]);
+
+        let lines: Vec<&str> = self.asset_failures.iter().map(String::as_str).collect();
+        let mut text_pipeline = render_pass.set_pipeline_text::<fonts::FontMonoElf>();
+        text_pipeline.batch_text(
+            &lines,
+            TextDirection::LeftToRight,
+            WimpyVec {
+                x: area.left() + TEXT_MARGIN,
+                y: area.top() + TEXT_MARGIN
+            },
+            WimpyNamedColor::Red,
+            config
+        );
+        text_pipeline.submit();
+    }
+
     fn draw_graphs<TRenderTarget>(&mut self,render_pass: &mut RenderPassBuilder<'_,TRenderTarget>)
     where
         TRenderTarget: RenderTarget
@@ -484,6 +567,7 @@ impl DebugShell {
         self.draw_backgrounds(render_pass);
         self.draw_graphs(render_pass);
         self.draw_labels(render_pass);
+        self.draw_asset_failures(render_pass,frame_size);
 
         self.buffers.reset();
     }
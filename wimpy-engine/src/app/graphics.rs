@@ -12,9 +12,11 @@ pub mod pipelines;
 
 mod graphics_provider;
 mod graphics_context;
+mod readback;
 
 pub use graphics_provider::*;
 pub use graphics_context::*;
+pub use readback::{ReadbackError, ReadbackImage, RenderTargetReadback};
 
 #[derive(Debug)]
 pub enum SizeValidationError {
@@ -37,4 +39,6 @@ pub trait GraphicsConfig {
     const INSTANCE_BUFFER_SIZE_3D: usize;
     const TEXT_PIPELINE_BUFFER_SIZE: usize;
     const LINE_BUFFER_SIZE: usize;
+    /// Streamed textures are evicted, least recently used first, to stay under this. Zero is unlimited.
+    const TEXTURE_MEMORY_BUDGET: usize;
 }
@@ -1,11 +1,15 @@
+use std::borrow::Borrow;
+
 use wgpu::*;
 use crate::{UWimpyPoint, WimpyColor, WimpyVec, app::fonts::FontDefinition, world::{Frustum, WimpyCamera}};
-use super::{*, textures::*, pipelines::*};
+use super::{*, textures::*, pipelines::*, readback::{ReadbackTicket, encode_readback}};
 
 pub struct OutputBuilder<'a> {
     graphics_context: &'a mut GraphicsContext,
     encoder: CommandEncoder,
     output_surface: SurfaceTexture,
+    /// Mapped once the encoder is submitted
+    readbacks: Vec<ReadbackTicket>,
 }
 
 pub struct OutputBuilderContext<'a> {
@@ -76,7 +80,8 @@ impl GraphicsContext {
         let mut texture_manager = TextureManager::new(
             &graphics_provider,
             pipeline_core.texture_layout.clone(),
-            streaming_policy
+            streaming_policy,
+            TConfig::TEXTURE_MEMORY_BUDGET as u64
         );
 
         let mut mesh_cache = MeshCache::create(
@@ -85,6 +90,14 @@ impl GraphicsContext {
             TConfig::MESH_CACHE_INDEX_BUFFER_SIZE
         );
 
+        if let Err(error) = mesh_cache.create_placeholder(
+            graphics_provider.get_queue(),
+            texture_manager.runtime_textures.missing.key,
+            texture_manager.runtime_textures.opaque_white.key
+        ) {
+            log::error!("Could not create the placeholder mesh: {:?}",error);
+        }
+
         let pipelines = RenderPipelines::create::<TConfig>(PipelineCreationContext {
             graphics_provider: &graphics_provider,
             core: pipeline_core,
@@ -141,10 +154,34 @@ impl GraphicsContext {
 
     pub fn return_long_life_frame(&mut self,frame: LongLifeRenderTarget) -> Result<(),TextureCacheError> {
         let texture_key = frame.get_key();
-        self.texture_manager.cache.remove(texture_key)?;
+        self.texture_manager.remove_texture(texture_key)?;
         Ok(())
     }
 
+    /// Copies the frame's input size into a staging buffer and submits right away. Await the result for RGBA8 rows without padding.
+    ///
+    /// Copies what has been submitted so far, use `OutputBuilder::read_render_target` to read after the render passes of a frame
+    pub fn read_render_target<TRenderTarget>(&mut self,frame: &TRenderTarget) -> Result<RenderTargetReadback,ReadbackError>
+    where
+        TRenderTarget: RenderTarget
+    {
+        let device = self.graphics_provider.get_device();
+        let mut encoder = device.create_command_encoder(&CommandEncoderDescriptor {
+            label: Some("Readback Encoder")
+        });
+
+        let texture = match self.texture_manager.get_no_touch(frame.get_key()) {
+            Ok(entry) => entry.view.texture(),
+            Err(error) => return Err(ReadbackError::Texture(error)),
+        };
+        let (readback,ticket) = encode_readback(device,&mut encoder,texture,frame.get_input_size())?;
+
+        self.graphics_provider.get_queue().submit(std::iter::once(encoder.finish()));
+        ticket.start_map();
+
+        return Ok(readback);
+    }
+
     /// Preallocate a GPU texture for usage as a render target of this size if none exist or they are all presently leased
     pub fn ensure_temp_frame_for_size(&mut self,size: UWimpyPoint) {
         let cache_key = self.graphics_provider.get_safe_texture_power_of_two(match size.largest().checked_next_power_of_two() {
@@ -186,7 +223,8 @@ impl GraphicsContext {
             builder: OutputBuilder {
                 graphics_context: self,
                 encoder,
-                output_surface
+                output_surface,
+                readbacks: Vec::new(),
             },
             frame,
         };
@@ -269,6 +307,39 @@ impl OutputBuilder<'_> {
         self.graphics_context.pipelines.pipeline_3d.flush_atlases(&mut self.graphics_context.texture_manager,&mut self.encoder);
     }
 
+    /// Batch sprites through the sprite atlas, so sprites from different textures can share a draw. This must happen before a render pass is created
+    ///
+    /// Before the render pass, `submit_batched_sprites()` must be called. Later, `pipeline_2d_pass.draw_batched_sprites()` can be used during an active render pass
+    pub fn batch_sprites<I,T>(&mut self,texture: &T,draw_data: I)
+    where
+        I: IntoIterator,
+        I::Item: Borrow<DrawData2D>,
+        T: WimpyTextureKeyResolver,
+    {
+        Pipeline2D::batch(self.graphics_context,texture,draw_data);
+    }
+
+    /// Must be called before the first render pass that will draw batched sprites executes
+    pub fn submit_batched_sprites(&mut self) {
+        self.graphics_context.pipelines.pipeline_2d.flush_sprite_atlas(&mut self.graphics_context.texture_manager,&mut self.encoder);
+    }
+
+    /// Copies the frame's input size after everything encoded so far, such as the render passes of this frame. Works for the output frame too, if `GraphicsProvider::can_read_output_surface`.
+    ///
+    /// The result resolves after `present_output_surface`, don't await it before then.
+    pub fn read_render_target<TRenderTarget>(&mut self,frame: &TRenderTarget) -> Result<RenderTargetReadback,ReadbackError>
+    where
+        TRenderTarget: RenderTarget
+    {
+        let texture = match self.graphics_context.texture_manager.get_no_touch(frame.get_key()) {
+            Ok(entry) => entry.view.texture(),
+            Err(error) => return Err(ReadbackError::Texture(error)),
+        };
+        let (readback,ticket) = encode_readback(self.graphics_context.graphics_provider.get_device(),&mut self.encoder,texture,frame.get_input_size())?;
+        self.readbacks.push(ticket);
+        return Ok(readback);
+    }
+
     fn create_render_pass_internal<'a,TRenderTarget>(&'a mut self,frame: &'a TRenderTarget,depth_stencil_config: DepthStencilConfig) -> Result<RenderPassBuilder<'a,TRenderTarget>,TextureManagerError>
     where
         TRenderTarget: RenderTarget
@@ -372,9 +443,12 @@ impl OutputBuilderContext<'_> {
         let queue = graphics_context.graphics_provider.get_queue();
         graphics_context.pipelines.flush(queue);
         queue.submit(std::iter::once(self.builder.encoder.finish()));
+        for ticket in self.builder.readbacks {
+            ticket.start_map();
+        }
 
         let texture_key = self.frame.get_key();
-        if let Err(error) = graphics_context.texture_manager.cache.remove(texture_key) {
+        if let Err(error) = graphics_context.texture_manager.remove_texture(texture_key) {
             log::warn!("Output frame was not present in the frame cache: {:?}",error);
         };
         self.builder.output_surface.present();
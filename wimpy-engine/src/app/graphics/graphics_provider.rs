@@ -2,6 +2,11 @@ use wgpu::*;
 use crate::UWimpyPoint;
 use super::{SizeValidationError, constants};
 
+/// Requested when the adapter has them, see `GraphicsProvider::supports_texture_format`
+const TEXTURE_COMPRESSION_FEATURES: Features = Features::TEXTURE_COMPRESSION_BC
+    .union(Features::TEXTURE_COMPRESSION_ETC2)
+    .union(Features::TEXTURE_COMPRESSION_ASTC);
+
 pub struct GraphicsProvider {
     surface: Surface<'static>,
     device: Device, // TODO: Restrict access
@@ -10,6 +15,7 @@ pub struct GraphicsProvider {
     max_texture_dimension: u32,
     output_view_format: TextureFormat,
     max_texture_power_of_two: u32,
+    texture_compression: Features,
 }
 
 pub struct GraphicsProviderConfig {
@@ -37,13 +43,14 @@ impl GraphicsProvider {
 
         let max_texture_dimension = adapter.limits().max_texture_dimension_2d;
         let max_uniform_buffer_size = adapter.limits().max_uniform_buffer_binding_size;
+        let texture_compression = adapter.features() & TEXTURE_COMPRESSION_FEATURES;
 
         config.limits.max_texture_dimension_2d = max_texture_dimension;
         config.limits.max_uniform_buffer_binding_size = max_uniform_buffer_size;
 
         let (device,queue) = match adapter.request_device(&DeviceDescriptor {
             label: None,
-            required_features: Features::empty(),
+            required_features: texture_compression,
             experimental_features: ExperimentalFeatures::disabled(),
             required_limits: config.limits,
             memory_hints: Default::default(),
@@ -56,6 +63,7 @@ impl GraphicsProvider {
         log::info!("LIMITS INFO: min_uniform_buffer_offset_alignment: {}",adapter.limits().min_uniform_buffer_offset_alignment);
         log::info!("LIMITS INFO: max_texture_dimension_2d: {}",adapter.limits().max_texture_dimension_2d);
         log::info!("LIMITS INFO: max_uniform_buffer_size: {}",adapter.limits().max_uniform_buffer_binding_size);
+        log::info!("FEATURES INFO: texture compression: {:?}",texture_compression);
 
         let surface_capabilities = config.surface.get_capabilities(&adapter);
         log::info!("Available surface formats: {:?}",surface_capabilities.formats);
@@ -91,8 +99,11 @@ impl GraphicsProvider {
             }
         };
 
+        /* Copies out of the surface are needed for readbacks, not every platform allows them */
+        let surface_usage = TextureUsages::RENDER_ATTACHMENT | (surface_capabilities.usages & TextureUsages::COPY_SRC);
+
         let surface_config = SurfaceConfiguration {
-            usage: TextureUsages::RENDER_ATTACHMENT,
+            usage: surface_usage,
             format: primary_format,
             width: 0,
             height: 0,
@@ -112,6 +123,7 @@ impl GraphicsProvider {
             max_texture_dimension,
             max_texture_power_of_two,
             output_view_format: desired_format,
+            texture_compression,
         })
     }
 
@@ -153,6 +165,11 @@ impl GraphicsProvider {
         self.output_view_format
     }
 
+    /// The output frame can be given to `OutputBuilder::read_render_target`
+    pub fn can_read_output_surface(&self) -> bool {
+        self.config.usage.contains(TextureUsages::COPY_SRC)
+    }
+
     pub fn get_output_surface(&self) -> Result<SurfaceTexture,SurfaceError> {
        self.surface.get_current_texture()
     }
@@ -180,6 +197,16 @@ impl GraphicsProvider {
         self.max_texture_power_of_two
     }
 
+    /// The compression features that were enabled on the device
+    pub fn get_texture_compression(&self) -> Features {
+        self.texture_compression
+    }
+
+    /// Formats that need a compression feature the device doesn't have must be decoded before upload
+    pub fn supports_texture_format(&self,format: TextureFormat) -> bool {
+        self.texture_compression.contains(format.required_features())
+    }
+
     pub fn validate_size(&self,size: UWimpyPoint) -> Result<(),SizeValidationError> {
         use SizeValidationError::*;
         let upper_bound = self.max_texture_dimension;
@@ -1,4 +1,5 @@
 const TEXTURED_MESH_REFERENCE_START_CAPACITY: usize = 8;
+const PLACEHOLDER_CUBE_HALF_SIZE: f32 = 0.5;
 
 use std::{marker::PhantomData, num::NonZero};
 use bytemuck::{Pod,Zeroable};
@@ -15,7 +16,8 @@ slotmap::new_key_type! {
 pub struct MeshCache {
     mesh_descriptions: SlotMap<TexturedMesh,Vec<TexturedMeshlet>>,
     vertices: TypedBuffer<MeshVertex>,
-    indices: TypedBuffer<u32>
+    indices: TypedBuffer<u32>,
+    placeholder: TexturedMesh,
 }
 
 #[derive(Debug)]
@@ -32,9 +34,18 @@ pub struct TypedBuffer<T> {
 
     /// The offset for the next write to the buffer
     physical_length: BufferAddress,
+    /// Released ranges below `logical_length` that can be written again, sorted by start and never adjacent
+    free_ranges: Vec<BufferRange>,
     phantom: PhantomData<T>,
 }
 
+/// A range of instances in a `TypedBuffer`
+#[derive(Debug,Clone,Copy)]
+struct BufferRange {
+    start: usize,
+    length: usize,
+}
+
 struct BufferWriteFrame {
     view: QueueWriteBufferView,
     stride: BufferAddress
@@ -49,15 +60,16 @@ where
             value: buffer,
             logical_length: 0,
             physical_length: 0,
+            free_ranges: Vec::new(),
             phantom: Default::default()
         }
     }
 
-    fn get_view(&self,queue: &Queue,length: usize) -> Option<BufferWriteFrame> {
+    fn get_view(&self,queue: &Queue,start: usize,length: usize) -> Option<BufferWriteFrame> {
         let stride = (length * size_of::<T>()) as BufferAddress;
         match queue.write_buffer_with(
             &self.value,
-            self.physical_length,
+            (start * size_of::<T>()) as BufferAddress,
             match NonZero::new(stride) {
                 Some(value) => value,
                 None => return None
@@ -71,18 +83,64 @@ where
         }
     }
 
-    fn write(&mut self,queue: &Queue,values: &[T]) -> bool {
+    /// Returns the index of the first instance, or `None` if the values do not fit. Released ranges are reused first fit.
+    fn write(&mut self,queue: &Queue,values: &[T]) -> Option<usize> {
         let num_of_instances = values.len();
-        let Some(mut frame) = self.get_view(queue,num_of_instances) else {
-            return false;
+        let free_range = self.free_ranges.iter().position(|range|range.length >= num_of_instances);
+        let start = match free_range {
+            Some(index) => self.free_ranges[index].start,
+            None => self.logical_length,
         };
 
+        let mut frame = self.get_view(queue,start,num_of_instances)?;
+
         frame.view.copy_from_slice(bytemuck::cast_slice(&values));
 
-        self.logical_length += num_of_instances;
-        self.physical_length += frame.stride;
+        match free_range {
+            Some(index) => {
+                let range = &mut self.free_ranges[index];
+                range.start += num_of_instances;
+                range.length -= num_of_instances;
+                if range.length == 0 {
+                    self.free_ranges.remove(index);
+                }
+            },
+            None => {
+                self.logical_length += num_of_instances;
+                self.physical_length += frame.stride;
+            },
+        }
+
+        return Some(start);
+    }
+
+    /// Makes a range returned by `write` available again
+    fn free(&mut self,start: usize,length: usize) {
+        if length == 0 {
+            return;
+        }
+        let index = self.free_ranges.partition_point(|range|range.start < start);
+        self.free_ranges.insert(index,BufferRange { start, length });
+
+        /* Merge with the neighbors so large writes can still find room */
+        if index + 1 < self.free_ranges.len() && start + length == self.free_ranges[index + 1].start {
+            self.free_ranges[index].length += self.free_ranges[index + 1].length;
+            self.free_ranges.remove(index + 1);
+        }
+        if index > 0 {
+            let previous = self.free_ranges[index - 1];
+            if previous.start + previous.length == start {
+                self.free_ranges[index - 1].length += self.free_ranges[index].length;
+                self.free_ranges.remove(index);
+            }
+        }
 
-        return true;
+        /* A free range at the end gives the space back to the bump allocation */
+        if let Some(last) = self.free_ranges.last().copied() && last.start + last.length == self.logical_length {
+            self.free_ranges.pop();
+            self.logical_length = last.start;
+            self.physical_length = (last.start * size_of::<T>()) as BufferAddress;
+        }
     }
 
     pub fn get_buffer(&self) -> &Buffer {
@@ -120,6 +178,7 @@ pub struct MeshletRange {
     pub index_start: u32,
     pub index_count: u32,
     pub base_vertex: u32,
+    pub vertex_count: u32,
 }
 
 impl MeshCache {
@@ -170,27 +229,84 @@ impl MeshCache {
             vertices.push(vertex);
         }
 
-        let base_vertex = self.vertices.logical_length;
+        return self.insert_vertices(queue,&vertices,&indices);
+    }
 
-        if !self.vertices.write(queue,&vertices) {
+    /// Writes one meshlet of geometry that didn't come from a gltf file
+    pub fn insert_vertices(&mut self,queue: &Queue,vertices: &[MeshVertex],indices: &[u32]) -> Result<MeshletRange,ModelError> {
+        let Some(base_vertex) = self.vertices.write(queue,vertices) else {
             return Err(ModelError::VertexBufferWriteFailure);
         };
 
-        let index_start = self.indices.logical_length;
-        if !self.indices.write(queue,&indices) {
+        let Some(index_start) = self.indices.write(queue,indices) else {
+            self.vertices.free(base_vertex,vertices.len());
             return Err(ModelError::IndexBufferWriteFailure);
         };
 
-        let index_end = self.indices.logical_length;
-
         let entry = MeshletRange {
             base_vertex: base_vertex as u32,
             index_start: index_start as u32,
-            index_count: (index_end - index_start) as u32,
+            index_count: indices.len() as u32,
+            vertex_count: vertices.len() as u32,
         };
 
         return Ok(entry);
     }
+
+    /// A unit cube centered on the origin that shows `diffuse` on every face. Made once by `GraphicsContext::create`, see `MeshCache::get_placeholder`.
+    pub fn create_placeholder(&mut self,queue: &Queue,diffuse: WimpyTextureKey,lightmap: WimpyTextureKey) -> Result<(),ModelError> {
+        let (vertices,indices) = create_cube_geometry(PLACEHOLDER_CUBE_HALF_SIZE);
+        let range = self.insert_vertices(queue,&vertices,&indices)?;
+        self.placeholder = self.create_textured_mesh_reference(vec![TexturedMeshlet {
+            range,
+            diffuse,
+            lightmap
+        }]);
+        return Ok(());
+    }
+
+    /// Drawn in place of a model that failed to load. Draws nothing if `create_placeholder` failed.
+    pub fn get_placeholder(&self) -> TexturedMesh {
+        self.placeholder
+    }
+}
+
+/// Four vertices per face so each face gets the whole texture. Counter clockwise from the outside, like gltf.
+fn create_cube_geometry(half_size: f32) -> (Vec<MeshVertex>,Vec<u32>) {
+    /* Outward normal, then two axes whose cross product is the normal */
+    const FACES: [([f32;3],[f32;3],[f32;3]);6] = [
+        ([ 1.0, 0.0, 0.0],[ 0.0, 1.0, 0.0],[ 0.0, 0.0, 1.0]),
+        ([-1.0, 0.0, 0.0],[ 0.0, 0.0, 1.0],[ 0.0, 1.0, 0.0]),
+        ([ 0.0, 1.0, 0.0],[ 0.0, 0.0, 1.0],[ 1.0, 0.0, 0.0]),
+        ([ 0.0,-1.0, 0.0],[ 1.0, 0.0, 0.0],[ 0.0, 0.0, 1.0]),
+        ([ 0.0, 0.0, 1.0],[ 1.0, 0.0, 0.0],[ 0.0, 1.0, 0.0]),
+        ([ 0.0, 0.0,-1.0],[ 0.0, 1.0, 0.0],[ 1.0, 0.0, 0.0]),
+    ];
+    const CORNERS: [([f32;2],[f32;2]);4] = [
+        ([-1.0,-1.0],[0.0,1.0]),
+        ([ 1.0,-1.0],[1.0,1.0]),
+        ([ 1.0, 1.0],[1.0,0.0]),
+        ([-1.0, 1.0],[0.0,0.0]),
+    ];
+
+    let mut vertices = Vec::with_capacity(FACES.len() * CORNERS.len());
+    let mut indices = Vec::with_capacity(FACES.len() * 6);
+
+    for (normal,u,v) in FACES {
+        let base = vertices.len() as u32;
+        for ([a,b],uv) in CORNERS {
+            let position = [0,1,2].map(|axis|(normal[axis] + u[axis] * a + v[axis] * b) * half_size);
+            vertices.push(MeshVertex {
+                uv_diffuse: uv,
+                uv_lightmap: uv,
+                position,
+                _padding: 0.0
+            });
+        }
+        indices.extend_from_slice(&[base,base + 1,base + 2,base,base + 2,base + 3]);
+    }
+
+    return (vertices,indices);
 }
 
 fn find_model_mesh<'a>(document: &'a Document) -> Option<Mesh<'a>> {
@@ -220,6 +336,7 @@ impl MeshCache {
             mesh_descriptions: SlotMap::with_capacity_and_key(TEXTURED_MESH_REFERENCE_START_CAPACITY),
             indices,
             vertices,
+            placeholder: TexturedMesh::default(),
         }
     }
 
@@ -248,6 +365,10 @@ impl MeshCache {
                     buffer.push(value)
                 },
                 Err(error) => {
+                    /* Don't leak the primitives that made it in */
+                    for range in buffer.iter() {
+                        self.free_range(range);
+                    }
                     return Err(error);
                 },
             }
@@ -283,6 +404,38 @@ impl MeshCache {
         self.mesh_descriptions.insert(mesh)
     }
 
+    fn free_range(&mut self,range: &MeshletRange) {
+        self.vertices.free(range.base_vertex as usize,range.vertex_count as usize);
+        self.indices.free(range.index_start as usize,range.index_count as usize);
+    }
+
+    /// Swaps the meshlets behind an existing reference so that held handles pick up the change. A stale reference is replaced with a new one.
+    ///
+    /// The old geometry is released.
+    pub fn replace_textured_mesh(&mut self,reference: TexturedMesh,mesh: Vec<TexturedMeshlet>) -> TexturedMesh {
+        match self.mesh_descriptions.get_mut(reference) {
+            Some(value) => {
+                let old_mesh = std::mem::replace(value,mesh);
+                for meshlet in old_mesh.iter() {
+                    self.free_range(&meshlet.range);
+                }
+                reference
+            },
+            None => self.mesh_descriptions.insert(mesh),
+        }
+    }
+
+    /// Releases the geometry of a textured mesh so the space can be reused. Returns `false` if the reference is stale.
+    pub fn remove_textured_mesh(&mut self,reference: TexturedMesh) -> bool {
+        let Some(mesh) = self.mesh_descriptions.remove(reference) else {
+            return false;
+        };
+        for meshlet in mesh.iter() {
+            self.free_range(&meshlet.range);
+        }
+        true
+    }
+
     pub fn get_textured_mesh_ref<'a>(&'a self,reference: TexturedMesh) -> &'a [TexturedMeshlet] {
         match self.mesh_descriptions.get(reference) {
             Some(value) => value,
@@ -1,3 +1,8 @@
+pub const SPRITE_ATLAS_PAGE_SIZE: u32 =                 2048;
+pub const SPRITE_ATLAS_MAX_PAGES: usize =               4;
+pub const SPRITE_ATLAS_PADDING: u32 =                   1;
+pub const SPRITE_ATLAS_IDLE_FRAMES: u32 =               300;
+
 use std::{ borrow::Borrow, ops::Range };
 
 use {wgpu::*, wgpu::util::{BufferInitDescriptor,DeviceExt}};
@@ -10,6 +15,16 @@ pub struct Pipeline2D {
     vertex_buffer:      Buffer,
     index_buffer:       Buffer,
     instance_buffer:    DoubleBuffer<QuadInstance>,
+    sprite_atlas:       SpriteAtlas,
+    /// Sprites from `Pipeline2D::batch`, in submission order
+    sprite_batch:       Vec<SpriteBatchItem>,
+}
+
+/// A batched sprite and the texture it samples, an atlas page or the sprite's own texture if it couldn't be packed
+#[derive(Copy,Clone)]
+struct SpriteBatchItem {
+    texture:    WimpyTextureKey,
+    instance:   QuadInstance,
 }
 
 pub struct DrawData2D {
@@ -113,13 +128,77 @@ impl Pipeline2D {
             })
         );
 
+        let sprite_atlas = SpriteAtlas::new(context.graphics_provider,&SpriteAtlasConfig {
+            page_size:      SPRITE_ATLAS_PAGE_SIZE,
+            max_pages:      SPRITE_ATLAS_MAX_PAGES,
+            padding:        SPRITE_ATLAS_PADDING,
+            idle_frames:    SPRITE_ATLAS_IDLE_FRAMES,
+        });
+
         return Self {
             variants: pipelines,
             vertex_buffer,
             index_buffer,
             instance_buffer,
+            sprite_atlas,
+            sprite_batch: Vec::new(),
         }
     }
+
+    /// Packs the texture into the sprite atlas and queues the sprites. Sources can't wrap, atlas neighbours would show instead.
+    pub fn batch<I,T>(context: &mut GraphicsContext,texture: &T,draw_data: I)
+    where
+        I: IntoIterator,
+        I::Item: Borrow<DrawData2D>,
+        T: WimpyTextureKeyResolver,
+    {
+        let key = texture.get_key();
+        let pipeline_2d = &mut context.pipelines.pipeline_2d;
+
+        let (texture_key,uv_area) = match pipeline_2d.sprite_atlas.set_texture(&context.graphics_provider,&mut context.texture_manager,key) {
+            Some(region) => (region.page,region.uv_area),
+            None => (key,WimpyRect {
+                position: WimpyVec::ZERO,
+                size: context.texture_manager.get_or_default(key).get_uv_scale(),
+            }),
+        };
+
+        pipeline_2d.sprite_batch.extend(draw_data.into_iter().map(|item|{
+            let item = item.borrow();
+
+            let dst = item.destination.origin_top_left_to_center();
+            let src = item.source * uv_area.size;
+
+            SpriteBatchItem {
+                texture: texture_key,
+                instance: QuadInstance {
+                    position: dst.position.into(),
+                    size: dst.size.into(),
+                    uv_position: (uv_area.position + src.position).into(),
+                    uv_size: src.size.into(),
+                    color: item.color.into(),
+                    rotation: item.rotation
+                }
+            }
+        }));
+    }
+
+    pub fn flush_sprite_atlas(
+        &mut self,
+        texture_manager: &mut TextureManager,
+        encoder: &mut CommandEncoder,
+    ) {
+        self.sprite_atlas.flush(texture_manager,encoder);
+    }
+
+    /// Frees the sprite atlas pages, sprites are packed again as they are batched
+    pub fn destroy_sprite_atlas(&mut self,texture_manager: &mut TextureManager) {
+        self.sprite_atlas.destroy(texture_manager);
+    }
+
+    pub fn get_sprite_atlas(&self) -> &SpriteAtlas {
+        &self.sprite_atlas
+    }
 }
 
 pub struct Pipeline2DPass<'pass,'encoder> {
@@ -185,19 +264,7 @@ impl Pipeline2DPass<'_,'_> {
         let texture = self.context.texture_manager.get_or_default(key);
         let uv_scale = texture.get_uv_scale();
 
-        'update_bind_group: {
-            if !(self.needs_sampler_update || self.current_sampling_frame != Some(key)) {
-                break 'update_bind_group;
-            }
-            self.current_sampling_frame = Some(key);
-            self.needs_sampler_update = false;
-
-            let bind_group = self.context.texture_manager.get_bind_group_single_channel(self.context.graphics_provider.get_device(),BindGroupChannelConfig {
-                sampler_mode: self.sampler_mode,
-                texture_key: key,
-            });
-            self.render_pass.set_bind_group(TEXTURE_BIND_GROUP_INDEX,bind_group,&[]);
-        }
+        self.bind_texture(key);
 
         let range = self.context.pipelines.pipeline_2d.instance_buffer.push_set(draw_data.into_iter().map(|item|{
             let item = item.borrow();
@@ -221,6 +288,41 @@ impl Pipeline2DPass<'_,'_> {
         });
     }
 
+    fn bind_texture(&mut self,key: WimpyTextureKey) {
+        if !(self.needs_sampler_update || self.current_sampling_frame != Some(key)) {
+            return;
+        }
+        self.current_sampling_frame = Some(key);
+        self.needs_sampler_update = false;
+
+        let bind_group = self.context.texture_manager.get_bind_group_single_channel(self.context.graphics_provider.get_device(),BindGroupChannelConfig {
+            sampler_mode: self.sampler_mode,
+            texture_key: key,
+        });
+        self.render_pass.set_bind_group(TEXTURE_BIND_GROUP_INDEX,bind_group,&[]);
+    }
+
+    /// Draws and clears the sprites from `Pipeline2D::batch`, one instanced draw per run of sprites that share a texture.
+    ///
+    /// `output.builder.submit_batched_sprites()` must be called before this render pass was created
+    pub fn draw_batched_sprites(&mut self) {
+        let mut sprite_batch = std::mem::take(&mut self.context.pipelines.pipeline_2d.sprite_batch);
+
+        for run in sprite_batch.chunk_by(|a,b|a.texture == b.texture) {
+            self.bind_texture(run[0].texture);
+
+            let range = self.context.pipelines.pipeline_2d.instance_buffer.push_set(run.iter().map(|item|item.instance));
+
+            self.render_pass.draw_indexed(0..INDEX_BUFFER_SIZE,0,Range {
+                start: range.start as u32,
+                end: range.end as u32,
+            });
+        }
+
+        sprite_batch.clear();
+        self.context.pipelines.pipeline_2d.sprite_batch = sprite_batch;
+    }
+
     pub fn draw_untextured<I>(&mut self,draw_data: I)
     where
         I: IntoIterator,
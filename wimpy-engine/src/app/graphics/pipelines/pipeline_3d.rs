@@ -172,11 +172,13 @@ impl Pipeline3D {
         let diffuse_atlas = context.texture_manager.create_atlas(context.graphics_provider,&TextureAtlasConfig {
             slot_size:   ATLAS_SLOT_SIZE_DIFFUSE,
             slot_length: ATLAS_SLOT_LENGTH_DIFFUSE,
+            format:      constants::INTERNAL_TEXTURE_FORMAT,
         });
 
         let lightmap_atlas = context.texture_manager.create_atlas(context.graphics_provider,&TextureAtlasConfig {
             slot_size:   ATLAS_SLOT_SIZE_LIGHTMAP,
             slot_length: ATLAS_SLOT_LENGTH_LIGHTMAP,
+            format:      constants::INTERNAL_TEXTURE_FORMAT,
         });
 
         let storage_bind_group = device.create_bind_group(&BindGroupDescriptor {
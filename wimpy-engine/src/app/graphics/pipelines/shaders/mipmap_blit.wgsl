@@ -0,0 +1,20 @@
+@group(0) @binding(0) var source_texture: texture_2d<f32>;
+@group(0) @binding(1) var source_sampler: sampler;
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+};
+
+/* One triangle that covers the whole target */
+@vertex fn vs_main(@builtin(vertex_index) index: u32) -> VertexOutput {
+    var out: VertexOutput;
+    let uv = vec2<f32>(f32((index << 1u) & 2u),f32(index & 2u));
+    out.clip_position = vec4<f32>(uv.x * 2.0 - 1.0,1.0 - uv.y * 2.0,0.0,1.0);
+    out.uv = uv;
+    return out;
+}
+
+@fragment fn fs_main(fragment: VertexOutput) -> @location(0) vec4<f32> {
+    return textureSample(source_texture,source_sampler,fragment.uv);
+}
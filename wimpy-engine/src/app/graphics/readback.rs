@@ -0,0 +1,229 @@
+const BYTES_PER_PIXEL: u32 = 4;
+
+use std::{future::Future, pin::Pin, sync::{Arc, Mutex}, task::{Context, Poll, Waker}};
+
+use wgpu::*;
+
+use crate::{UWimpyPoint, app::WimpyImageData};
+use super::textures::TextureManagerError;
+
+#[derive(Debug)]
+pub enum ReadbackError {
+    Texture(TextureManagerError),
+    /// Only 8 bit RGBA and BGRA render targets can be read back
+    UnsupportedFormat(TextureFormat),
+    /// The platform's surface doesn't allow copies, see `GraphicsProvider::can_read_output_surface`
+    OutputSurfaceNotReadable,
+    MapFailed(BufferAsyncError),
+}
+
+/// Tightly packed RGBA8 rows, top to bottom. sRGB render targets (and the output surface, on most platforms) are gamma encoded.
+pub struct ReadbackImage {
+    pub size: UWimpyPoint,
+    pub data: Vec<u8>,
+}
+
+impl ReadbackImage {
+    /// For uploads back to the GPU, such as save game thumbnails
+    pub fn as_image_data(&self) -> WimpyImageData<'_> {
+        WimpyImageData::Buffer {
+            size: self.size,
+            data: &self.data,
+        }
+    }
+}
+
+enum ReadbackState {
+    /// The copy is encoded but the encoder wasn't submitted yet
+    Encoded {
+        waker: Option<Waker>,
+    },
+    Mapping {
+        waker: Option<Waker>,
+    },
+    Mapped(Result<(),BufferAsyncError>),
+}
+
+/// A staging buffer that is shared between a `RenderTargetReadback` and whoever submits its copy
+pub(super) struct ReadbackTicket {
+    buffer: Buffer,
+    state:  Arc<Mutex<ReadbackState>>,
+}
+
+impl ReadbackTicket {
+    /// Must come after the submission that holds the copy, mapping a buffer that is used by a later submission is a validation error
+    pub fn start_map(self) {
+        let waker = match self.state.lock() {
+            Ok(mut state) => match std::mem::replace(&mut *state,ReadbackState::Mapping { waker: None }) {
+                ReadbackState::Encoded { waker } => waker,
+                _ => None,
+            },
+            Err(_) => None,
+        };
+        let state = self.state;
+        self.buffer.map_async(MapMode::Read,..,move |result|{
+            let Ok(mut state) = state.lock() else {
+                return;
+            };
+            let previous = std::mem::replace(&mut *state,ReadbackState::Mapped(result));
+            if let ReadbackState::Mapping { waker: Some(waker) } = previous {
+                waker.wake();
+            }
+        });
+        /* A readback that was awaited early has to poll the device now */
+        if let Some(waker) = waker {
+            waker.wake();
+        }
+    }
+}
+
+/// Resolves once the GPU finished the copy. On native platforms, polling blocks until then.
+///
+/// Readbacks from `OutputBuilder::read_render_target` don't resolve before `present_output_surface`
+pub struct RenderTargetReadback {
+    device:         Device,
+    buffer:         Buffer,
+    size:           UWimpyPoint,
+    bytes_per_row:  u32,
+    /// BGRA surfaces are swapped to RGBA while the padding is removed
+    swap_red_blue:  bool,
+    state:          Arc<Mutex<ReadbackState>>,
+}
+
+/// Row padding of texture to buffer copies
+fn get_padded_bytes_per_row(width: u32) -> u32 {
+    (width * BYTES_PER_PIXEL).next_multiple_of(COPY_BYTES_PER_ROW_ALIGNMENT)
+}
+
+/// `Some(swap_red_blue)` for formats that can be read back
+fn get_swap_red_blue(format: TextureFormat) -> Option<bool> {
+    match format {
+        TextureFormat::Rgba8Unorm | TextureFormat::Rgba8UnormSrgb => Some(false),
+        TextureFormat::Bgra8Unorm | TextureFormat::Bgra8UnormSrgb => Some(true),
+        _ => None,
+    }
+}
+
+/// Encodes a copy of the top left `size` pixels of the texture into a new staging buffer
+pub(super) fn encode_readback(
+    device: &Device,
+    encoder: &mut CommandEncoder,
+    texture: &Texture,
+    size: UWimpyPoint
+) -> Result<(RenderTargetReadback,ReadbackTicket),ReadbackError> {
+    let Some(swap_red_blue) = get_swap_red_blue(texture.format()) else {
+        return Err(ReadbackError::UnsupportedFormat(texture.format()));
+    };
+    if !texture.usage().contains(TextureUsages::COPY_SRC) {
+        return Err(ReadbackError::OutputSurfaceNotReadable);
+    }
+
+    let size = UWimpyPoint {
+        x: size.x.clamp(1,texture.width()),
+        y: size.y.clamp(1,texture.height()),
+    };
+    let bytes_per_row = get_padded_bytes_per_row(size.x);
+
+    let buffer = device.create_buffer(&BufferDescriptor {
+        label: Some("Readback Buffer"),
+        size: bytes_per_row as u64 * size.y as u64,
+        usage: BufferUsages::COPY_DST | BufferUsages::MAP_READ,
+        mapped_at_creation: false,
+    });
+
+    encoder.copy_texture_to_buffer(
+        TexelCopyTextureInfo {
+            texture,
+            mip_level: 0,
+            origin: Origin3d::ZERO,
+            aspect: TextureAspect::All,
+        },
+        TexelCopyBufferInfo {
+            buffer: &buffer,
+            layout: TexelCopyBufferLayout {
+                offset: 0,
+                bytes_per_row: Some(bytes_per_row),
+                rows_per_image: Some(size.y),
+            },
+        },
+        Extent3d {
+            width: size.x,
+            height: size.y,
+            depth_or_array_layers: 1,
+        }
+    );
+
+    let state = Arc::new(Mutex::new(ReadbackState::Encoded { waker: None }));
+
+    let readback = RenderTargetReadback {
+        device: device.clone(),
+        buffer: buffer.clone(),
+        size,
+        bytes_per_row,
+        swap_red_blue,
+        state: state.clone(),
+    };
+    let ticket = ReadbackTicket {
+        buffer,
+        state,
+    };
+    return Ok((readback,ticket));
+}
+
+impl RenderTargetReadback {
+    fn read_image(&self) -> ReadbackImage {
+        let row_length = (self.size.x * BYTES_PER_PIXEL) as usize;
+        let mut data = Vec::with_capacity(row_length * self.size.y as usize);
+        {
+            let mapped = self.buffer.slice(..).get_mapped_range();
+            for row in mapped.chunks_exact(self.bytes_per_row as usize) {
+                data.extend_from_slice(&row[..row_length]);
+            }
+        }
+        self.buffer.unmap();
+
+        if self.swap_red_blue {
+            for pixel in data.chunks_exact_mut(BYTES_PER_PIXEL as usize) {
+                pixel.swap(0,2);
+            }
+        }
+        return ReadbackImage {
+            size: self.size,
+            data,
+        };
+    }
+}
+
+impl Future for RenderTargetReadback {
+    type Output = Result<ReadbackImage,ReadbackError>;
+
+    fn poll(self: Pin<&mut Self>,context: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut polled_device = false;
+        loop {
+            {
+                let Ok(mut state) = self.state.lock() else {
+                    return Poll::Ready(Err(ReadbackError::MapFailed(BufferAsyncError)));
+                };
+                match &mut *state {
+                    ReadbackState::Mapped(Ok(())) => break,
+                    ReadbackState::Mapped(Err(error)) => return Poll::Ready(Err(ReadbackError::MapFailed(error.clone()))),
+                    ReadbackState::Encoded { waker } => {
+                        *waker = Some(context.waker().clone());
+                        return Poll::Pending;
+                    },
+                    ReadbackState::Mapping { waker } => if polled_device {
+                        /* Browsers map on their own time and run the callback from the event loop */
+                        *waker = Some(context.waker().clone());
+                        return Poll::Pending;
+                    },
+                }
+            }
+            /* Native backends only run map callbacks while the device is polled */
+            if let Err(error) = self.device.poll(PollType::wait_indefinitely()) {
+                log::warn!("Device poll failure during readback: {:?}",error);
+            }
+            polled_device = true;
+        }
+        return Poll::Ready(Ok(self.read_image()));
+    }
+}
@@ -4,8 +4,21 @@ pub use texture_manager::*;
 mod texture_atlas;
 pub use texture_atlas::*;
 
+mod sprite_atlas;
+pub use sprite_atlas::*;
+
 mod bind_group_cache;
 
+mod mipmaps;
+pub use mipmaps::MipmapGenerator;
+
+mod compressed;
+pub use compressed::{CompressedImage, Ktx2Error, KTX2_EXTENSION};
+
+mod block_decode;
+mod bptc_decode;
+mod astc_decode;
+
 mod render_targets;
 
 pub use render_targets::{
@@ -33,6 +46,7 @@ slotmap::new_key_type! {
     pub struct WimpyTextureKey;
 }
 
+/// `Nearest` and `Linear` modes use the nearest mip level. `Trilinear` modes blend between mip levels, which only matters for mipmapped textures.
 #[derive(PartialEq,Eq,Copy,Clone,Hash)]
 pub enum SamplerMode {
     NearestClamp,
@@ -40,7 +54,10 @@ pub enum SamplerMode {
     NearestWrapMirror,
     LinearClamp,
     LinearWrap,
-    LinearWrapMirror
+    LinearWrapMirror,
+    TrilinearClamp,
+    TrilinearWrap,
+    TrilinearWrapMirror,
 }
 
 #[derive(Copy,Clone)]
@@ -131,6 +148,8 @@ pub struct TextureCacheEntry<'a> {
     /// May be a missing/placeholder texture if the texture isn't streamed yet.
     pub view:                   &'a wgpu::TextureView,
     pub load_state:             TextureLoadState,
+    /// Changes when the view is replaced, such as by a reload
+    pub bind_group_id:          BindGroupIdentity,
 }
 
 impl SizeInfo for TextureCacheEntry<'_> {
@@ -0,0 +1,596 @@
+/// Levels of each integer sequence encoding range. Weights use up to 32, color endpoints up to 256.
+const ISE_LEVELS: [u32;21] = [2,3,4,5,6,8,10,12,16,20,24,32,40,48,64,80,96,128,160,192,256];
+const MAX_WEIGHTS: usize = 64;
+const MIN_WEIGHT_BITS: u32 = 24;
+const MAX_WEIGHT_BITS: u32 = 96;
+const MAX_COLOR_VALUES: usize = 18;
+/// The smallest range color endpoints can be stored in
+const MIN_COLOR_LEVELS: u32 = 6;
+/// Blocks with fewer texels double their coordinates for the partition hash
+const SMALL_BLOCK_TEXELS: usize = 31;
+/// Invalid blocks and HDR content decode to magenta, as in the LDR profile
+const ERROR_COLOR: [u8;4] = [255,0,255,255];
+/// A void extent coordinate with every bit set, the block carries no extent
+const VOID_EXTENT_NONE: u32 = 0x1FFF;
+
+use super::block_decode::{Block, BlockBits};
+
+#[derive(Copy,Clone,PartialEq,Eq)]
+enum IseKind {
+    Bits,
+    Trits,
+    Quints,
+}
+
+/// A range with `levels` values, stored as a trit or quint with `bits` low bits, or only as bits
+#[derive(Copy,Clone)]
+struct IseRange {
+    kind: IseKind,
+    bits: u32,
+}
+
+impl IseRange {
+    fn new(levels: u32) -> Self {
+        let (kind,power) = match levels {
+            _ if levels.is_multiple_of(3) => (IseKind::Trits,levels / 3),
+            _ if levels.is_multiple_of(5) => (IseKind::Quints,levels / 5),
+            _ => (IseKind::Bits,levels),
+        };
+        return Self {
+            kind,
+            bits: power.trailing_zeros()
+        };
+    }
+
+    fn get_bit_count(self,count: u32) -> u32 {
+        let low_bits = self.bits * count;
+        match self.kind {
+            IseKind::Bits => low_bits,
+            IseKind::Trits => low_bits + (8 * count).div_ceil(5),
+            IseKind::Quints => low_bits + (7 * count).div_ceil(3),
+        }
+    }
+}
+
+/// Reads an integer sequence. Bits past `end` belong to something else and read as zero.
+struct IseReader<'a> {
+    bits:       &'a BlockBits,
+    position:   u32,
+    end:        u32,
+}
+
+impl IseReader<'_> {
+    fn read(&mut self,count: u32) -> u32 {
+        let available = self.end.saturating_sub(self.position).min(count);
+        let value = self.bits.get(self.position,available);
+        self.position += count;
+        return value;
+    }
+
+    fn read_values(&mut self,range: IseRange,output: &mut [u32]) {
+        let low_bits = range.bits;
+        let group_length = match range.kind {
+            IseKind::Bits => 1,
+            IseKind::Trits => 5,
+            IseKind::Quints => 3,
+        };
+        for group in output.chunks_mut(group_length) {
+            let mut low = [0;5];
+            let high = match range.kind {
+                IseKind::Bits => {
+                    low[0] = self.read(low_bits);
+                    [0;5]
+                },
+                IseKind::Trits => {
+                    low[0] = self.read(low_bits);
+                    let mut packed = self.read(2);
+                    low[1] = self.read(low_bits);
+                    packed |= self.read(2) << 2;
+                    low[2] = self.read(low_bits);
+                    packed |= self.read(1) << 4;
+                    low[3] = self.read(low_bits);
+                    packed |= self.read(2) << 5;
+                    low[4] = self.read(low_bits);
+                    packed |= self.read(1) << 7;
+                    decode_trits(packed)
+                },
+                IseKind::Quints => {
+                    low[0] = self.read(low_bits);
+                    let mut packed = self.read(3);
+                    low[1] = self.read(low_bits);
+                    packed |= self.read(2) << 3;
+                    low[2] = self.read(low_bits);
+                    packed |= self.read(2) << 5;
+                    let quints = decode_quints(packed);
+                    [quints[0],quints[1],quints[2],0,0]
+                },
+            };
+            for (index,value) in group.iter_mut().enumerate() {
+                *value = high[index] << low_bits | low[index];
+            }
+        }
+    }
+}
+
+fn get_bit(value: u32,bit: u32) -> u32 {
+    value >> bit & 1
+}
+
+fn decode_trits(packed: u32) -> [u32;5] {
+    let (c,t4,t3) = match packed >> 2 & 7 {
+        7 => ((packed >> 5 & 7) << 2 | packed & 3,2,2),
+        _ if packed >> 5 & 3 == 3 => (packed & 31,2,get_bit(packed,7)),
+        _ => (packed & 31,get_bit(packed,7),packed >> 5 & 3),
+    };
+    let (t2,t1,t0) = match (c & 3,c >> 2 & 3) {
+        (3,_) => (2,get_bit(c,4),get_bit(c,3) << 1 | get_bit(c,2) & !get_bit(c,3) & 1),
+        (_,3) => (2,2,c & 3),
+        _ => (get_bit(c,4),c >> 2 & 3,get_bit(c,1) << 1 | get_bit(c,0) & !get_bit(c,1) & 1),
+    };
+    return [t0,t1,t2,t3,t4];
+}
+
+fn decode_quints(packed: u32) -> [u32;3] {
+    if packed >> 1 & 3 == 3 && packed >> 5 & 3 == 0 {
+        let q0 = get_bit(packed,0);
+        let q2 = q0 << 2 | (get_bit(packed,4) & !q0 & 1) << 1 | get_bit(packed,3) & !q0 & 1;
+        return [4,4,q2];
+    }
+    let (q2,c) = match packed >> 1 & 3 {
+        3 => (4,(packed >> 3 & 3) << 3 | (!(packed >> 5) & 3) << 1 | packed & 1),
+        _ => (packed >> 5 & 3,packed & 31),
+    };
+    let (q1,q0) = match c & 7 {
+        5 => (4,c >> 3 & 3),
+        _ => (c >> 3 & 3,c & 7),
+    };
+    return [q0,q1,q2];
+}
+
+/// Repeats the bits of `value` from the top down until `target` bits are filled
+fn replicate(value: u32,bits: u32,target: u32) -> u32 {
+    if bits == 0 {
+        return 0;
+    }
+    let mut result = 0;
+    let mut shift = target as i32 - bits as i32;
+    while shift > -(bits as i32) {
+        result |= match shift >= 0 {
+            true => value << shift,
+            false => value >> -shift,
+        };
+        shift -= bits as i32;
+    }
+    return result & ((1 << target) - 1);
+}
+
+/// To 0 through 255
+fn unquantize_color(value: u32,range: IseRange) -> u32 {
+    let bits = range.bits;
+    if range.kind == IseKind::Bits {
+        return replicate(value,bits,8);
+    }
+    let low = value & ((1 << bits) - 1);
+    let high = value >> bits;
+    let top = low >> 1;
+    let (c,b) = match (range.kind,bits) {
+        (IseKind::Trits,1) => (204,0),
+        (IseKind::Quints,1) => (113,0),
+        (IseKind::Trits,2) => (93,top * 0x116),
+        (IseKind::Quints,2) => (54,top * 0x10C),
+        (IseKind::Trits,3) => (44,top * 0x85),
+        (IseKind::Quints,3) => (26,(top * 0x82) | (top >> 1)),
+        (IseKind::Trits,4) => (22,top * 0x41),
+        (IseKind::Quints,4) => (13,top << 6 | top >> 1),
+        (IseKind::Trits,5) => (11,top << 5 | top >> 2),
+        (IseKind::Quints,5) => (6,top << 5 | top >> 3),
+        _ => (5,top << 4 | top >> 4),
+    };
+    let a = (low & 1) * 0x1FF;
+    let t = (high * c + b) ^ a;
+    return (a & 0x80) | t >> 2;
+}
+
+/// To 0 through 64
+fn unquantize_weight(value: u32,range: IseRange) -> u32 {
+    let bits = range.bits;
+    let weight = match (range.kind,bits) {
+        (IseKind::Bits,_) => replicate(value,bits,6),
+        (IseKind::Trits,0) => [0,32,63][value as usize],
+        (IseKind::Quints,0) => [0,16,32,47,63][value as usize],
+        _ => {
+            let low = value & ((1 << bits) - 1);
+            let high = value >> bits;
+            let top = low >> 1;
+            let (c,b) = match (range.kind,bits) {
+                (IseKind::Trits,1) => (50,0),
+                (IseKind::Quints,1) => (28,0),
+                (IseKind::Trits,2) => (23,top * 0x45),
+                (IseKind::Quints,2) => (13,top * 0x42),
+                _ => (11,top * 0x21),
+            };
+            let a = (low & 1) * 0x7F;
+            let t = (high * c + b) ^ a;
+            (a & 0x20) | t >> 2
+        },
+    };
+    return match weight > 32 {
+        true => weight + 1,
+        false => weight,
+    };
+}
+
+struct BlockMode {
+    grid_width:     usize,
+    grid_height:    usize,
+    dual_plane:     bool,
+    weight_range:   IseRange,
+}
+
+/// `None` for reserved modes
+fn decode_block_mode(mode: u32) -> Option<BlockMode> {
+    let a = (mode >> 5 & 3) as usize;
+    let mut high_precision = get_bit(mode,9) == 1;
+    let mut dual_plane = get_bit(mode,10) == 1;
+    let range_low = get_bit(mode,4);
+
+    let (range,grid_width,grid_height) = match mode & 3 {
+        0 => {
+            let b = (mode >> 9 & 3) as usize;
+            let range = range_low | (mode >> 2 & 3) << 1;
+            if range < 2 {
+                return None;
+            }
+            let (width,height) = match mode >> 7 & 3 {
+                0 => (12,a + 2),
+                1 => (a + 2,12),
+                2 => {
+                    high_precision = false;
+                    dual_plane = false;
+                    (a + 6,b + 6)
+                },
+                _ => match a {
+                    0 => (6,10),
+                    1 => (10,6),
+                    _ => return None,
+                },
+            };
+            (range,width,height)
+        },
+        low => {
+            let b = (mode >> 7 & 3) as usize;
+            let (width,height) = match mode >> 2 & 3 {
+                0 => (b + 4,a + 2),
+                1 => (b + 8,a + 2),
+                2 => (a + 2,b + 8),
+                _ => match get_bit(mode,8) {
+                    1 => ((b & 1) + 2,a + 2),
+                    _ => (a + 2,(b & 1) + 6),
+                },
+            };
+            (range_low | low << 1,width,height)
+        },
+    };
+    let levels_index = (range - 2) as usize + 6 * high_precision as usize;
+    return Some(BlockMode {
+        grid_width,
+        grid_height,
+        dual_plane,
+        weight_range: IseRange::new(ISE_LEVELS[levels_index])
+    });
+}
+
+fn hash_partition_seed(seed: u32) -> u32 {
+    let mut p = seed;
+    p ^= p >> 15;
+    p = p.wrapping_sub(p << 17);
+    p = p.wrapping_add(p << 7);
+    p = p.wrapping_add(p << 4);
+    p ^= p >> 5;
+    p = p.wrapping_add(p << 16);
+    p ^= p >> 7;
+    p ^= p >> 3;
+    p ^= p << 6;
+    p ^= p >> 17;
+    return p;
+}
+
+fn select_partition(seed: u32,x: usize,y: usize,partition_count: u32,small_block: bool) -> usize {
+    let (x,y) = match small_block {
+        true => (x as u32 * 2,y as u32 * 2),
+        false => (x as u32,y as u32),
+    };
+    let seed = seed + (partition_count - 1) * 1024;
+    let random = hash_partition_seed(seed);
+
+    let mut seeds = [0u32;8];
+    for (index,value) in seeds.iter_mut().enumerate() {
+        let nibble = random >> (index * 4) & 15;
+        *value = nibble * nibble;
+    }
+    let partition_shift = match partition_count {
+        3 => 6,
+        _ => 5,
+    };
+    let seed_shift = match seed & 2 {
+        0 => 5,
+        _ => 4,
+    };
+    let (shift_1,shift_2) = match seed & 1 {
+        0 => (partition_shift,seed_shift),
+        _ => (seed_shift,partition_shift),
+    };
+    for (index,value) in seeds.iter_mut().enumerate() {
+        *value >>= match index % 2 {
+            0 => shift_1,
+            _ => shift_2,
+        };
+    }
+
+    /* The z terms are left out, these are 2D blocks */
+    let a = (seeds[0] * x + seeds[1] * y + (random >> 14)) & 63;
+    let b = (seeds[2] * x + seeds[3] * y + (random >> 10)) & 63;
+    let c = match partition_count >= 3 {
+        true => (seeds[4] * x + seeds[5] * y + (random >> 6)) & 63,
+        false => 0,
+    };
+    let d = match partition_count >= 4 {
+        true => (seeds[6] * x + seeds[7] * y + (random >> 2)) & 63,
+        false => 0,
+    };
+
+    return match () {
+        _ if a >= b && a >= c && a >= d => 0,
+        _ if b >= c && b >= d => 1,
+        _ if c >= d => 2,
+        _ => 3,
+    };
+}
+
+fn bit_transfer_signed(a: i32,b: i32) -> (i32,i32) {
+    let b = b >> 1 | (a & 0x80);
+    let a = (a >> 1) & 0x3F;
+    return match a & 0x20 {
+        0 => (a,b),
+        _ => (a - 0x40,b),
+    };
+}
+
+fn blue_contract(r: i32,g: i32,b: i32,a: i32) -> [i32;4] {
+    [(r + b) >> 1,(g + b) >> 1,b,a]
+}
+
+/// The two endpoints of an LDR color endpoint mode, `None` for HDR modes
+fn decode_endpoints(mode: u32,v: &[i32]) -> Option<([i32;4],[i32;4])> {
+    let (a,b) = match mode {
+        0 => ([v[0],v[0],v[0],255],[v[1],v[1],v[1],255]),
+        1 => {
+            let l0 = (v[0] >> 2) | (v[1] & 0xC0);
+            let l1 = (l0 + (v[1] & 0x3F)).min(255);
+            ([l0,l0,l0,255],[l1,l1,l1,255])
+        },
+        4 => ([v[0],v[0],v[0],v[2]],[v[1],v[1],v[1],v[3]]),
+        5 => {
+            let (d0,b0) = bit_transfer_signed(v[1],v[0]);
+            let (d1,b1) = bit_transfer_signed(v[3],v[2]);
+            ([b0,b0,b0,b1],[b0 + d0,b0 + d0,b0 + d0,b1 + d1])
+        },
+        6 => (
+            [(v[0] * v[3]) >> 8,(v[1] * v[3]) >> 8,(v[2] * v[3]) >> 8,255],
+            [v[0],v[1],v[2],255]
+        ),
+        8 => match v[1] + v[3] + v[5] >= v[0] + v[2] + v[4] {
+            true => ([v[0],v[2],v[4],255],[v[1],v[3],v[5],255]),
+            false => (blue_contract(v[1],v[3],v[5],255),blue_contract(v[0],v[2],v[4],255)),
+        },
+        9 | 13 => {
+            let (dr,r) = bit_transfer_signed(v[1],v[0]);
+            let (dg,g) = bit_transfer_signed(v[3],v[2]);
+            let (db,b) = bit_transfer_signed(v[5],v[4]);
+            let (da,a) = match mode {
+                13 => bit_transfer_signed(v[7],v[6]),
+                _ => (0,255),
+            };
+            match dr + dg + db >= 0 {
+                true => ([r,g,b,a],[r + dr,g + dg,b + db,a + da]),
+                false => (blue_contract(r + dr,g + dg,b + db,a + da),blue_contract(r,g,b,a)),
+            }
+        },
+        10 => (
+            [(v[0] * v[3]) >> 8,(v[1] * v[3]) >> 8,(v[2] * v[3]) >> 8,v[4]],
+            [v[0],v[1],v[2],v[5]]
+        ),
+        12 => match v[1] + v[3] + v[5] >= v[0] + v[2] + v[4] {
+            true => ([v[0],v[2],v[4],v[6]],[v[1],v[3],v[5],v[7]]),
+            false => (blue_contract(v[1],v[3],v[5],v[7]),blue_contract(v[0],v[2],v[4],v[6])),
+        },
+        _ => return None,
+    };
+    return Some((a.map(|channel|channel.clamp(0,255)),b.map(|channel|channel.clamp(0,255))));
+}
+
+/// Endpoints are widened to 16 bits before they are blended. sRGB keeps the top 8 bits, UNORM is rounded.
+fn interpolate(a: i32,b: i32,weight: i32,srgb: bool) -> u8 {
+    let (a,b) = match srgb {
+        true => (a << 8 | 0x80,b << 8 | 0x80),
+        false => (a << 8 | a,b << 8 | b),
+    };
+    let value = (a * (64 - weight) + b * weight + 32) >> 6;
+    return match srgb {
+        true => (value >> 8) as u8,
+        false => ((value * 255 + 32767) / 65535) as u8,
+    };
+}
+
+/// A 128 bit ASTC LDR block with a `width` by `height` footprint
+pub fn decode_block(data: &[u8],width: usize,height: usize,srgb: bool,block: &mut Block) {
+    let bits = BlockBits::new(data);
+    match decode_block_checked(&bits,width,height,srgb,block) {
+        Some(()) => {},
+        None => block.fill(ERROR_COLOR),
+    }
+}
+
+fn decode_void_extent(bits: &BlockBits,srgb: bool,block: &mut Block) -> Option<()> {
+    let header = bits.get(0,12);
+    /* A constant HDR color */
+    if get_bit(header,9) == 1 {
+        return None;
+    }
+    /* The reserved bits must be set and the extent, unless it is all ones, can't be empty */
+    let extent = [bits.get(12,13),bits.get(25,13),bits.get(38,13),bits.get(51,13)];
+    if header >> 10 != 3 || extent != [VOID_EXTENT_NONE;4] && (extent[0] >= extent[1] || extent[2] >= extent[3]) {
+        return None;
+    }
+    let mut color = [0;4];
+    for (channel,value) in color.iter_mut().enumerate() {
+        let channel = bits.get(64 + channel as u32 * 16,16);
+        *value = match srgb {
+            true => (channel >> 8) as u8,
+            false => ((channel * 255 + 32767) / 65535) as u8,
+        };
+    }
+    block.fill(color);
+    return Some(());
+}
+
+fn decode_block_checked(bits: &BlockBits,width: usize,height: usize,srgb: bool,block: &mut Block) -> Option<()> {
+    let mode_bits = bits.get(0,11);
+    if mode_bits & 0x1FF == 0x1FC {
+        return decode_void_extent(bits,srgb,block);
+    }
+    let mode = decode_block_mode(mode_bits)?;
+    if mode.grid_width > width || mode.grid_height > height {
+        return None;
+    }
+    let plane_count = 1 + mode.dual_plane as usize;
+    let weight_count = mode.grid_width * mode.grid_height * plane_count;
+    if weight_count > MAX_WEIGHTS {
+        return None;
+    }
+    let weight_bits = mode.weight_range.get_bit_count(weight_count as u32);
+    if !(MIN_WEIGHT_BITS..=MAX_WEIGHT_BITS).contains(&weight_bits) {
+        return None;
+    }
+
+    let partition_count = bits.get(11,2) + 1;
+    if partition_count == 4 && mode.dual_plane {
+        return None;
+    }
+    let mut endpoint_modes = [0;4];
+    let mut partition_seed = 0;
+    let mut extra_mode_bits = 0;
+    let color_start = match partition_count {
+        1 => {
+            endpoint_modes[0] = bits.get(13,4);
+            17
+        },
+        _ => {
+            partition_seed = bits.get(13,10);
+            let mode_field = bits.get(23,6);
+            if mode_field & 3 == 0 {
+                endpoint_modes.fill(mode_field >> 2);
+            } else {
+                /* Per partition modes continue right below the weights */
+                extra_mode_bits = 3 * partition_count - 4;
+                let extra = bits.get(128 - weight_bits - extra_mode_bits,extra_mode_bits);
+                let encoded = mode_field >> 2 | extra << 4;
+                let base_class = (mode_field & 3) - 1;
+                for (partition,endpoint_mode) in endpoint_modes.iter_mut().enumerate().take(partition_count as usize) {
+                    let class = base_class + get_bit(encoded,partition as u32);
+                    let mode = encoded >> (partition_count + 2 * partition as u32) & 3;
+                    *endpoint_mode = class << 2 | mode;
+                }
+            }
+            29
+        },
+    };
+    let color_end = 128 - weight_bits - extra_mode_bits - 2 * mode.dual_plane as u32;
+    let plane_channel = bits.get(color_end,2) as usize;
+
+    let partitions = &endpoint_modes[..partition_count as usize];
+    let color_count: usize = partitions.iter().map(|mode|((mode >> 2) as usize + 1) * 2).sum();
+    if color_count > MAX_COLOR_VALUES || color_end < color_start {
+        return None;
+    }
+    let color_bits = color_end - color_start;
+    let color_levels = *ISE_LEVELS.iter().rev().find(|levels|IseRange::new(**levels).get_bit_count(color_count as u32) <= color_bits)?;
+    if color_levels < MIN_COLOR_LEVELS {
+        return None;
+    }
+    let color_range = IseRange::new(color_levels);
+    let mut colors = [0;MAX_COLOR_VALUES];
+    let color_stream_end = color_start + color_range.get_bit_count(color_count as u32);
+    IseReader { bits, position: color_start, end: color_stream_end }.read_values(color_range,&mut colors[..color_count]);
+
+    let mut endpoints = [([0;4],[0;4]);4];
+    let mut color_offset = 0;
+    for (endpoint,endpoint_mode) in endpoints.iter_mut().zip(partitions) {
+        let length = ((endpoint_mode >> 2) as usize + 1) * 2;
+        let mut values = [0;8];
+        for (value,color) in values.iter_mut().zip(&colors[color_offset..color_offset + length]) {
+            *value = unquantize_color(*color,color_range) as i32;
+        }
+        *endpoint = decode_endpoints(*endpoint_mode,&values)?;
+        color_offset += length;
+    }
+
+    /* Weights are stored bit reversed from the top of the block */
+    let reversed = BlockBits {
+        bits: bits.bits.reverse_bits(),
+        position: 0
+    };
+    let mut weights = [0;MAX_WEIGHTS];
+    IseReader { bits: &reversed, position: 0, end: weight_bits }.read_values(mode.weight_range,&mut weights[..weight_count]);
+    for weight in weights.iter_mut().take(weight_count) {
+        *weight = unquantize_weight(*weight,mode.weight_range);
+    }
+
+    let small_block = width * height < SMALL_BLOCK_TEXELS;
+    let scale_x = (1024 + width / 2) / (width - 1);
+    let scale_y = (1024 + height / 2) / (height - 1);
+    let grid_width = mode.grid_width;
+    let get_weight = |index: usize,plane: usize|weights.get(index * plane_count + plane).copied().unwrap_or(0) as usize;
+
+    for (index,pixel) in block.iter_mut().enumerate() {
+        let x = index % width;
+        let y = index / width;
+
+        /* Bilinear infill from the weight grid, in sixteenths */
+        let grid_x = (scale_x * x * (grid_width - 1) + 32) >> 6;
+        let grid_y = (scale_y * y * (mode.grid_height - 1) + 32) >> 6;
+        let fraction_x = grid_x & 15;
+        let fraction_y = grid_y & 15;
+        let origin = (grid_x >> 4) + (grid_y >> 4) * grid_width;
+        let w11 = (fraction_x * fraction_y + 8) >> 4;
+        let w10 = fraction_y - w11;
+        let w01 = fraction_x - w11;
+        let w00 = 16 + w11 - fraction_x - fraction_y;
+        let infill = |plane: usize|{
+            let sum = get_weight(origin,plane) * w00 +
+                get_weight(origin + 1,plane) * w01 +
+                get_weight(origin + grid_width,plane) * w10 +
+                get_weight(origin + grid_width + 1,plane) * w11;
+            ((sum + 8) >> 4) as i32
+        };
+        let weight = infill(0);
+        let plane_weight = match mode.dual_plane {
+            true => infill(1),
+            false => weight,
+        };
+
+        let partition = match partition_count {
+            1 => 0,
+            _ => select_partition(partition_seed,x,y,partition_count,small_block),
+        };
+        let (a,b) = endpoints[partition];
+        for (channel,value) in pixel.iter_mut().enumerate() {
+            let weight = match mode.dual_plane && channel == plane_channel {
+                true => plane_weight,
+                false => weight,
+            };
+            *value = interpolate(a[channel],b[channel],weight,srgb);
+        }
+    }
+    return Some(());
+}
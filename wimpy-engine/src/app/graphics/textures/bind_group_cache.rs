@@ -30,9 +30,12 @@ impl FilterSet {
             NearestClamp =>        Self::new(FilterMode::Nearest,   MipmapFilterMode::Nearest,  AddressMode::ClampToEdge),
             NearestWrap =>         Self::new(FilterMode::Nearest,   MipmapFilterMode::Nearest,  AddressMode::Repeat),
             NearestWrapMirror =>   Self::new(FilterMode::Nearest,   MipmapFilterMode::Nearest,  AddressMode::MirrorRepeat),
-            LinearClamp =>         Self::new(FilterMode::Linear,    MipmapFilterMode::Linear,   AddressMode::ClampToEdge),
-            LinearWrap =>          Self::new(FilterMode::Linear,    MipmapFilterMode::Linear,   AddressMode::Repeat),
-            LinearWrapMirror =>    Self::new(FilterMode::Linear,    MipmapFilterMode::Linear,   AddressMode::MirrorRepeat),
+            LinearClamp =>         Self::new(FilterMode::Linear,    MipmapFilterMode::Nearest,  AddressMode::ClampToEdge),
+            LinearWrap =>          Self::new(FilterMode::Linear,    MipmapFilterMode::Nearest,  AddressMode::Repeat),
+            LinearWrapMirror =>    Self::new(FilterMode::Linear,    MipmapFilterMode::Nearest,  AddressMode::MirrorRepeat),
+            TrilinearClamp =>      Self::new(FilterMode::Linear,    MipmapFilterMode::Linear,   AddressMode::ClampToEdge),
+            TrilinearWrap =>       Self::new(FilterMode::Linear,    MipmapFilterMode::Linear,   AddressMode::Repeat),
+            TrilinearWrapMirror => Self::new(FilterMode::Linear,    MipmapFilterMode::Linear,   AddressMode::MirrorRepeat),
         }
     }
 }
@@ -43,7 +46,10 @@ pub struct Samplers {
     nearest_wrap_mirror:    Sampler,
     linear_clamp:           Sampler,
     linear_wrap:            Sampler,
-    linear_wrap_mirror:     Sampler
+    linear_wrap_mirror:     Sampler,
+    trilinear_clamp:        Sampler,
+    trilinear_wrap:         Sampler,
+    trilinear_wrap_mirror:  Sampler,
 }
 
 impl Samplers {
@@ -55,6 +61,9 @@ impl Samplers {
             SamplerMode::LinearClamp =>         &self.linear_clamp,
             SamplerMode::LinearWrap =>          &self.linear_wrap,
             SamplerMode::LinearWrapMirror =>    &self.linear_wrap_mirror,
+            SamplerMode::TrilinearClamp =>      &self.trilinear_clamp,
+            SamplerMode::TrilinearWrap =>       &self.trilinear_wrap,
+            SamplerMode::TrilinearWrapMirror => &self.trilinear_wrap_mirror,
         }
     }
 }
@@ -67,7 +76,7 @@ fn create_sampler(device: &Device,sampler_mode: SamplerMode) -> Sampler {
         address_mode_w:     filter_set.address,
         mag_filter:         filter_set.filter,
         min_filter:         filter_set.filter,
-        mipmap_filter:      filter_set.mipmap_filter, // TODO: figure out if should be fixed to linear
+        mipmap_filter:      filter_set.mipmap_filter,
         ..Default::default()
     })
 }
@@ -81,6 +90,9 @@ impl Samplers {
             linear_clamp:           create_sampler(device, SamplerMode::LinearClamp),
             linear_wrap:            create_sampler(device, SamplerMode::LinearWrap),
             linear_wrap_mirror:     create_sampler(device, SamplerMode::LinearWrapMirror),
+            trilinear_clamp:        create_sampler(device, SamplerMode::TrilinearClamp),
+            trilinear_wrap:         create_sampler(device, SamplerMode::TrilinearWrap),
+            trilinear_wrap_mirror:  create_sampler(device, SamplerMode::TrilinearWrapMirror),
         };
     }
 }
@@ -194,6 +206,15 @@ impl PartialEq for BindGroupChannel<'_> {
     }
 }
 
+impl CacheKey {
+    fn uses_identity(&self,id: BindGroupIdentity) -> bool {
+        match self {
+            CacheKey::SingleChannel { ch_0 } =>       ch_0.id == id,
+            CacheKey::DualChannel   { ch_0, ch_1 } => ch_0.id == id || ch_1.id == id,
+        }
+    }
+}
+
 impl From<&BindGroupChannelSet<'_>> for CacheKey {
     fn from(value: &BindGroupChannelSet<'_>) -> Self {
         return match value {
@@ -223,6 +244,16 @@ impl BindGroupCache {
         }
     }
 
+    /// Drops every bind group that uses the identity, they hold a reference to its texture view
+    pub fn remove_identity(&mut self,id: BindGroupIdentity) {
+        self.cache.retain(|key,_|!key.uses_identity(id));
+    }
+
+    /// Bind groups that are cached, each one keeps its texture views alive
+    pub fn get_live_count(&self) -> usize {
+        self.cache.len()
+    }
+
     pub fn get(&mut self,device: &Device,channel_set: &BindGroupChannelSet) -> &BindGroup {
         let entry = self.cache.entry(channel_set.into());
         return entry.or_insert_with(||match channel_set {
@@ -0,0 +1,749 @@
+const BLOCK_LENGTH: usize = 4;
+const BLOCK_PIXELS: usize = BLOCK_LENGTH * BLOCK_LENGTH;
+const BYTES_PER_PIXEL: usize = 4;
+/// The largest ASTC footprint, 12x12
+const MAX_BLOCK_PIXELS: usize = 144;
+/// 1.0 as an `Rgba8Snorm` byte
+const SNORM_ONE: u8 = 127;
+
+/// Intensity modifiers of the ETC1 subblock modes, the small and large step of each table
+const ETC_MODIFIERS: [[i32;2];8] = [[2,8],[5,17],[9,29],[13,42],[18,60],[24,80],[33,106],[47,183]];
+/// The distances of the ETC2 T and H modes
+const ETC_DISTANCES: [i32;8] = [3,6,11,16,23,32,41,64];
+const EAC_MODIFIERS: [[i32;8];16] = [
+    [-3,-6,-9,-15,2,5,8,14],
+    [-3,-7,-10,-13,2,6,9,12],
+    [-2,-5,-8,-13,1,4,7,12],
+    [-2,-4,-6,-13,1,3,5,12],
+    [-3,-6,-8,-12,2,5,7,11],
+    [-3,-7,-9,-11,2,6,8,10],
+    [-4,-7,-8,-11,3,6,7,10],
+    [-3,-5,-8,-11,2,4,7,10],
+    [-2,-6,-8,-10,1,5,7,9],
+    [-2,-5,-8,-10,1,4,7,9],
+    [-2,-4,-8,-10,1,3,7,9],
+    [-2,-5,-7,-10,1,4,6,9],
+    [-3,-4,-7,-10,2,3,6,9],
+    [-1,-2,-3,-10,0,1,2,9],
+    [-4,-6,-8,-9,3,5,7,8],
+    [-3,-5,-7,-9,2,4,6,8],
+];
+
+use wgpu::{AstcChannel, TextureFormat};
+
+use crate::UWimpyPoint;
+use super::{astc_decode, bptc_decode};
+
+/// CPU decoders for adapters that can't sample a compressed format. ASTC HDR has none.
+///
+/// Signed formats decode to `Rgba8Snorm` bytes, BC6H is clamped to the 0 to 1 range.
+#[derive(Copy,Clone)]
+pub enum BlockDecoder {
+    Rgba8,
+    Bc1,
+    Bc2,
+    Bc3,
+    Bc4,
+    Bc4Snorm,
+    Bc5,
+    Bc5Snorm,
+    Bc6h { signed: bool },
+    Bc7,
+    Etc2Rgb,
+    Etc2RgbA1,
+    Etc2Rgba,
+    EacR11,
+    EacR11Snorm,
+    EacRg11,
+    EacRg11Snorm,
+    Astc { width: usize, height: usize, srgb: bool },
+}
+
+pub type Block = [[u8;BYTES_PER_PIXEL]];
+
+/// A 16 byte block read from its lowest bit up, bits past the end read as zero
+pub struct BlockBits {
+    pub bits:       u128,
+    pub position:   u32,
+}
+
+impl BlockBits {
+    pub fn new(data: &[u8]) -> Self {
+        let mut bytes = [0;16];
+        bytes.copy_from_slice(&data[..16]);
+        return Self {
+            bits: u128::from_le_bytes(bytes),
+            position: 0
+        };
+    }
+
+    pub fn get(&self,position: u32,count: u32) -> u32 {
+        if count == 0 || position >= 128 {
+            return 0;
+        }
+        return (self.bits >> position) as u32 & (u32::MAX >> (32 - count));
+    }
+
+    pub fn read(&mut self,count: u32) -> u32 {
+        let value = self.get(self.position,count);
+        self.position += count;
+        return value;
+    }
+}
+
+pub fn get_decoder(format: TextureFormat) -> Option<BlockDecoder> {
+    use TextureFormat::*;
+    let decoder = match format {
+        Rgba8Unorm | Rgba8UnormSrgb =>              BlockDecoder::Rgba8,
+        Bc1RgbaUnorm | Bc1RgbaUnormSrgb =>          BlockDecoder::Bc1,
+        Bc2RgbaUnorm | Bc2RgbaUnormSrgb =>          BlockDecoder::Bc2,
+        Bc3RgbaUnorm | Bc3RgbaUnormSrgb =>          BlockDecoder::Bc3,
+        Bc4RUnorm =>                                BlockDecoder::Bc4,
+        Bc4RSnorm =>                                BlockDecoder::Bc4Snorm,
+        Bc5RgUnorm =>                               BlockDecoder::Bc5,
+        Bc5RgSnorm =>                               BlockDecoder::Bc5Snorm,
+        Bc6hRgbUfloat =>                            BlockDecoder::Bc6h { signed: false },
+        Bc6hRgbFloat =>                             BlockDecoder::Bc6h { signed: true },
+        Bc7RgbaUnorm | Bc7RgbaUnormSrgb =>          BlockDecoder::Bc7,
+        Etc2Rgb8Unorm | Etc2Rgb8UnormSrgb =>        BlockDecoder::Etc2Rgb,
+        Etc2Rgb8A1Unorm | Etc2Rgb8A1UnormSrgb =>    BlockDecoder::Etc2RgbA1,
+        Etc2Rgba8Unorm | Etc2Rgba8UnormSrgb =>      BlockDecoder::Etc2Rgba,
+        EacR11Unorm =>                              BlockDecoder::EacR11,
+        EacR11Snorm =>                              BlockDecoder::EacR11Snorm,
+        EacRg11Unorm =>                             BlockDecoder::EacRg11,
+        EacRg11Snorm =>                             BlockDecoder::EacRg11Snorm,
+        Astc { channel: AstcChannel::Unorm | AstcChannel::UnormSrgb, .. } => {
+            let (width,height) = format.block_dimensions();
+            BlockDecoder::Astc {
+                width: width as usize,
+                height: height as usize,
+                srgb: format.is_srgb()
+            }
+        },
+        _ => return None,
+    };
+    return Some(decoder);
+}
+
+impl BlockDecoder {
+    fn get_block_size(self) -> usize {
+        match self {
+            BlockDecoder::Rgba8 => BYTES_PER_PIXEL,
+            BlockDecoder::Bc1 | BlockDecoder::Bc4 | BlockDecoder::Bc4Snorm | BlockDecoder::Etc2Rgb |
+            BlockDecoder::Etc2RgbA1 | BlockDecoder::EacR11 | BlockDecoder::EacR11Snorm => 8,
+            _ => 16,
+        }
+    }
+
+    fn get_block_dimensions(self) -> (usize,usize) {
+        match self {
+            BlockDecoder::Rgba8 => (1,1),
+            BlockDecoder::Astc { width, height, .. } => (width,height),
+            _ => (BLOCK_LENGTH,BLOCK_LENGTH),
+        }
+    }
+
+    /// Pixels are in row order
+    fn decode_block(self,data: &[u8],block: &mut Block) {
+        match self {
+            BlockDecoder::Rgba8 => {},
+            BlockDecoder::Bc1 => decode_bc1_color(data,true,block),
+            BlockDecoder::Bc2 => {
+                decode_bc1_color(&data[8..],false,block);
+                let alpha = u64::from_le_bytes(get_half(data,0));
+                for (index,pixel) in block.iter_mut().enumerate() {
+                    pixel[3] = (alpha >> (index * 4) & 15) as u8 * 17;
+                }
+            },
+            BlockDecoder::Bc3 => {
+                decode_bc1_color(&data[8..],false,block);
+                for (pixel,alpha) in block.iter_mut().zip(decode_bc4(data)) {
+                    pixel[3] = alpha;
+                }
+            },
+            BlockDecoder::Bc4 => {
+                for (pixel,red) in block.iter_mut().zip(decode_bc4(data)) {
+                    *pixel = [red,0,0,255];
+                }
+            },
+            BlockDecoder::Bc4Snorm => {
+                for (pixel,red) in block.iter_mut().zip(decode_bc4_snorm(data)) {
+                    *pixel = [red,0,0,SNORM_ONE];
+                }
+            },
+            BlockDecoder::Bc5 => {
+                for ((pixel,red),green) in block.iter_mut().zip(decode_bc4(data)).zip(decode_bc4(&data[8..])) {
+                    *pixel = [red,green,0,255];
+                }
+            },
+            BlockDecoder::Bc5Snorm => {
+                for ((pixel,red),green) in block.iter_mut().zip(decode_bc4_snorm(data)).zip(decode_bc4_snorm(&data[8..])) {
+                    *pixel = [red,green,0,SNORM_ONE];
+                }
+            },
+            BlockDecoder::Bc6h { signed } => bptc_decode::decode_bc6h(data,signed,block),
+            BlockDecoder::Bc7 => bptc_decode::decode_bc7(data,block),
+            BlockDecoder::Etc2Rgb => decode_etc2_color(data,false,block),
+            BlockDecoder::Etc2RgbA1 => decode_etc2_color(data,true,block),
+            BlockDecoder::Etc2Rgba => {
+                decode_etc2_color(&data[8..],false,block);
+                for (pixel,alpha) in block.iter_mut().zip(decode_eac(data,false)) {
+                    pixel[3] = alpha;
+                }
+            },
+            BlockDecoder::EacR11 => {
+                for (pixel,red) in block.iter_mut().zip(decode_eac(data,true)) {
+                    *pixel = [red,0,0,255];
+                }
+            },
+            BlockDecoder::EacR11Snorm => {
+                for (pixel,red) in block.iter_mut().zip(decode_eac_snorm(data)) {
+                    *pixel = [red,0,0,SNORM_ONE];
+                }
+            },
+            BlockDecoder::EacRg11 => {
+                for ((pixel,red),green) in block.iter_mut().zip(decode_eac(data,true)).zip(decode_eac(&data[8..],true)) {
+                    *pixel = [red,green,0,255];
+                }
+            },
+            BlockDecoder::EacRg11Snorm => {
+                for ((pixel,red),green) in block.iter_mut().zip(decode_eac_snorm(data)).zip(decode_eac_snorm(&data[8..])) {
+                    *pixel = [red,green,0,SNORM_ONE];
+                }
+            },
+            BlockDecoder::Astc { width, height, srgb } => astc_decode::decode_block(data,width,height,srgb,block),
+        }
+    }
+}
+
+/// Decodes tightly packed blocks to RGBA8 rows, blocks that hang over the edge are clipped
+pub fn decode_image(decoder: BlockDecoder,data: &[u8],size: UWimpyPoint) -> Vec<u8> {
+    if let BlockDecoder::Rgba8 = decoder {
+        return data.to_vec();
+    }
+    let width = size.x as usize;
+    let height = size.y as usize;
+    let (block_width,block_height) = decoder.get_block_dimensions();
+    let columns = width.div_ceil(block_width);
+    let block_size = decoder.get_block_size();
+
+    let mut output = vec![0;width * height * BYTES_PER_PIXEL];
+    let mut pixels = [[0;BYTES_PER_PIXEL];MAX_BLOCK_PIXELS];
+    let block = &mut pixels[..block_width * block_height];
+
+    for (block_index,block_data) in data.chunks_exact(block_size).enumerate() {
+        decoder.decode_block(block_data,block);
+        let block_x = block_index % columns * block_width;
+        let block_y = block_index / columns * block_height;
+
+        for (index,pixel) in block.iter().enumerate() {
+            let x = block_x + index % block_width;
+            let y = block_y + index / block_width;
+            if x >= width || y >= height {
+                continue;
+            }
+            let start = (y * width + x) * BYTES_PER_PIXEL;
+            output[start..start + BYTES_PER_PIXEL].copy_from_slice(pixel);
+        }
+    }
+    return output;
+}
+
+fn get_half(data: &[u8],offset: usize) -> [u8;8] {
+    let mut value = [0;8];
+    value.copy_from_slice(&data[offset..offset + 8]);
+    return value;
+}
+
+fn expand_565(color: u16) -> [i32;3] {
+    let red = (color >> 11 & 31) as i32;
+    let green = (color >> 5 & 63) as i32;
+    let blue = (color & 31) as i32;
+    return [red << 3 | red >> 2,green << 2 | green >> 4,blue << 3 | blue >> 2];
+}
+
+fn mix(a: [i32;3],b: [i32;3],a_weight: i32,b_weight: i32) -> [u8;4] {
+    let total = a_weight + b_weight;
+    let channel = |index: usize|((a[index] * a_weight + b[index] * b_weight) / total) as u8;
+    return [channel(0),channel(1),channel(2),255];
+}
+
+/// BC2 and BC3 always use four colors, only BC1 has the punch through mode
+fn decode_bc1_color(data: &[u8],punch_through: bool,block: &mut Block) {
+    let color_0 = u16::from_le_bytes([data[0],data[1]]);
+    let color_1 = u16::from_le_bytes([data[2],data[3]]);
+    let indices = u32::from_le_bytes([data[4],data[5],data[6],data[7]]);
+
+    let a = expand_565(color_0);
+    let b = expand_565(color_1);
+    let palette = match !punch_through || color_0 > color_1 {
+        true => [mix(a,b,1,0),mix(a,b,0,1),mix(a,b,2,1),mix(a,b,1,2)],
+        false => [mix(a,b,1,0),mix(a,b,0,1),mix(a,b,1,1),[0,0,0,0]],
+    };
+    for (index,pixel) in block.iter_mut().enumerate() {
+        *pixel = palette[(indices >> (index * 2) & 3) as usize];
+    }
+}
+
+/// The interpolated channel of BC3 alpha, BC4 and BC5
+fn decode_bc4(data: &[u8]) -> [u8;BLOCK_PIXELS] {
+    let a = data[0] as u32;
+    let b = data[1] as u32;
+    let indices = u64::from_le_bytes(get_half(data,0)) >> 16;
+
+    let mut palette = [a,b,0,0,0,0,0,255];
+    if a > b {
+        for (step,value) in (1..).zip(&mut palette[2..8]) {
+            *value = ((7 - step) * a + step * b + 3) / 7;
+        }
+    } else {
+        for (step,value) in (1..).zip(&mut palette[2..6]) {
+            *value = ((5 - step) * a + step * b + 2) / 5;
+        }
+    }
+
+    let mut values = [0;BLOCK_PIXELS];
+    for (index,value) in values.iter_mut().enumerate() {
+        *value = palette[(indices >> (index * 3) & 7) as usize] as u8;
+    }
+    return values;
+}
+
+/// Signed BC4 and BC5 channels as `Rgba8Snorm` bytes. -128 is read as -127, both mean -1.
+fn decode_bc4_snorm(data: &[u8]) -> [u8;BLOCK_PIXELS] {
+    let a = (data[0] as i8).max(-127) as i32;
+    let b = (data[1] as i8).max(-127) as i32;
+    let indices = u64::from_le_bytes(get_half(data,0)) >> 16;
+
+    let mut palette = [a,b,0,0,0,0,-127,127];
+    if a > b {
+        for (step,value) in (1..).zip(&mut palette[2..8]) {
+            let sum = (7 - step) * a + step * b;
+            *value = (sum + sum.signum() * 3) / 7;
+        }
+    } else {
+        for (step,value) in (1..).zip(&mut palette[2..6]) {
+            let sum = (5 - step) * a + step * b;
+            *value = (sum + sum.signum() * 2) / 5;
+        }
+    }
+
+    let mut values = [0;BLOCK_PIXELS];
+    for (index,value) in values.iter_mut().enumerate() {
+        *value = palette[(indices >> (index * 3) & 7) as usize] as i8 as u8;
+    }
+    return values;
+}
+
+fn extend(value: u64,bits: u32) -> i32 {
+    let value = value as i32 & ((1 << bits) - 1);
+    return value << (8 - bits) | value >> (2 * bits - 8);
+}
+
+fn clamp_color(color: [i32;3],offset: i32) -> [u8;4] {
+    let channel = |index: usize|(color[index] + offset).clamp(0,255) as u8;
+    return [channel(0),channel(1),channel(2),255];
+}
+
+/// ETC and EAC index pixels by column
+fn get_column_index(row_index: usize) -> usize {
+    row_index % BLOCK_LENGTH * BLOCK_LENGTH + row_index / BLOCK_LENGTH
+}
+
+/// ETC1 subblocks plus the ETC2 T, H and planar modes. With punch through alpha, the differential bit is the opaque bit instead.
+fn decode_etc2_color(data: &[u8],punch_through: bool,block: &mut Block) {
+    let bits = u64::from_be_bytes(get_half(data,0));
+    let flag = bits >> 33 & 1 == 1;
+    let differential = punch_through || flag;
+    let opaque = !punch_through || flag;
+
+    let get_index = |row_index: usize| {
+        let pixel = get_column_index(row_index);
+        (bits >> (16 + pixel) & 1) << 1 | bits >> pixel & 1
+    };
+
+    /* Overflowing a differential channel selects one of the ETC2 modes */
+    let red = (bits >> 59 & 31) as i32 + ((bits >> 56 & 7) as i32 ^ 4) - 4;
+    let green = (bits >> 51 & 31) as i32 + ((bits >> 48 & 7) as i32 ^ 4) - 4;
+    let blue = (bits >> 43 & 31) as i32 + ((bits >> 40 & 7) as i32 ^ 4) - 4;
+
+    if differential && !(0..32).contains(&red) {
+        let color_1 = [extend((bits >> 59 & 3) << 2 | bits >> 56 & 3,4),extend(bits >> 52,4),extend(bits >> 48,4)];
+        let color_2 = [extend(bits >> 44,4),extend(bits >> 40,4),extend(bits >> 36,4)];
+        let distance = ETC_DISTANCES[((bits >> 34 & 3) << 1 | bits >> 32 & 1) as usize];
+        let palette = [clamp_color(color_1,0),clamp_color(color_2,distance),clamp_color(color_2,0),clamp_color(color_2,-distance)];
+        decode_etc2_palette(palette,opaque,get_index,block);
+    } else if differential && !(0..32).contains(&green) {
+        let red_1 = bits >> 59 & 15;
+        let green_1 = (bits >> 56 & 7) << 1 | bits >> 52 & 1;
+        let blue_1 = (bits >> 51 & 1) << 3 | bits >> 47 & 7;
+        let red_2 = bits >> 43 & 15;
+        let green_2 = bits >> 39 & 15;
+        let blue_2 = bits >> 35 & 15;
+        let order = (red_1 << 8 | green_1 << 4 | blue_1) >= (red_2 << 8 | green_2 << 4 | blue_2);
+        let distance = ETC_DISTANCES[((bits >> 34 & 1) << 2 | (bits >> 32 & 1) << 1 | order as u64) as usize];
+        let color_1 = [extend(red_1,4),extend(green_1,4),extend(blue_1,4)];
+        let color_2 = [extend(red_2,4),extend(green_2,4),extend(blue_2,4)];
+        let palette = [clamp_color(color_1,distance),clamp_color(color_1,-distance),clamp_color(color_2,distance),clamp_color(color_2,-distance)];
+        decode_etc2_palette(palette,opaque,get_index,block);
+    } else if differential && !(0..32).contains(&blue) {
+        let origin = [
+            extend(bits >> 57,6),
+            extend((bits >> 56 & 1) << 6 | bits >> 49 & 63,7),
+            extend((bits >> 48 & 1) << 5 | (bits >> 43 & 3) << 3 | bits >> 39 & 7,6)
+        ];
+        let horizontal = [extend((bits >> 34 & 31) << 1 | bits >> 32 & 1,6),extend(bits >> 25,7),extend(bits >> 19,6)];
+        let vertical = [extend(bits >> 13,6),extend(bits >> 6,7),extend(bits,6)];
+        for (index,pixel) in block.iter_mut().enumerate() {
+            let x = (index % BLOCK_LENGTH) as i32;
+            let y = (index / BLOCK_LENGTH) as i32;
+            let channel = |channel: usize|{
+                let value = x * (horizontal[channel] - origin[channel]) + y * (vertical[channel] - origin[channel]) + 4 * origin[channel] + 2;
+                (value >> 2).clamp(0,255) as u8
+            };
+            *pixel = [channel(0),channel(1),channel(2),255];
+        }
+    } else {
+        let (base_1,base_2) = match differential {
+            true => (
+                [extend(bits >> 59,5),extend(bits >> 51,5),extend(bits >> 43,5)],
+                [extend(red as u64,5),extend(green as u64,5),extend(blue as u64,5)]
+            ),
+            false => (
+                [extend(bits >> 60,4),extend(bits >> 52,4),extend(bits >> 44,4)],
+                [extend(bits >> 56,4),extend(bits >> 48,4),extend(bits >> 40,4)]
+            ),
+        };
+        let tables = [ETC_MODIFIERS[(bits >> 37 & 7) as usize],ETC_MODIFIERS[(bits >> 34 & 7) as usize]];
+        let flip = bits >> 32 & 1 == 1;
+        for (index,pixel) in block.iter_mut().enumerate() {
+            let x = index % BLOCK_LENGTH;
+            let y = index / BLOCK_LENGTH;
+            let second = match flip {
+                true => y >= 2,
+                false => x >= 2,
+            };
+            let (base,table) = match second {
+                true => (base_2,tables[1]),
+                false => (base_1,tables[0]),
+            };
+            *pixel = match (get_index(index),opaque) {
+                (0,false) => clamp_color(base,0),
+                (2,false) => [0,0,0,0],
+                (0,true) => clamp_color(base,table[0]),
+                (1,_) => clamp_color(base,table[1]),
+                (2,true) => clamp_color(base,-table[0]),
+                _ => clamp_color(base,-table[1]),
+            };
+        }
+    }
+}
+
+/// The T and H modes pick one of four colors. Without the opaque bit, the third is transparent.
+fn decode_etc2_palette(palette: [[u8;4];4],opaque: bool,get_index: impl Fn(usize) -> u64,block: &mut Block) {
+    for (index,pixel) in block.iter_mut().enumerate() {
+        *pixel = match (get_index(index),opaque) {
+            (2,false) => [0,0,0,0],
+            (palette_index,_) => palette[palette_index as usize],
+        };
+    }
+}
+
+/// ETC2 alpha, or an 11 bit EAC channel scaled down to 8 bits
+fn decode_eac(data: &[u8],eleven_bit: bool) -> [u8;BLOCK_PIXELS] {
+    let bits = u64::from_be_bytes(get_half(data,0));
+    let base = (bits >> 56) as i32;
+    let multiplier = (bits >> 52 & 15) as i32;
+    let table = EAC_MODIFIERS[(bits >> 48 & 15) as usize];
+
+    let mut values = [0;BLOCK_PIXELS];
+    for (index,value) in values.iter_mut().enumerate() {
+        let pixel = get_column_index(index);
+        let modifier = table[(bits >> (45 - pixel * 3) & 7) as usize];
+        *value = match eleven_bit {
+            true => {
+                let step = match multiplier {
+                    0 => modifier,
+                    _ => modifier * multiplier * 8,
+                };
+                let value = (base * 8 + 4 + step).clamp(0,2047);
+                ((value * 255 + 1023) / 2047) as u8
+            },
+            false => (base + modifier * multiplier).clamp(0,255) as u8,
+        };
+    }
+    return values;
+}
+
+/// A signed 11 bit EAC channel scaled down to an `Rgba8Snorm` byte
+fn decode_eac_snorm(data: &[u8]) -> [u8;BLOCK_PIXELS] {
+    let bits = u64::from_be_bytes(get_half(data,0));
+    let base = ((bits >> 56) as u8 as i8).max(-127) as i32;
+    let multiplier = (bits >> 52 & 15) as i32;
+    let table = EAC_MODIFIERS[(bits >> 48 & 15) as usize];
+
+    let mut values = [0;BLOCK_PIXELS];
+    for (index,value) in values.iter_mut().enumerate() {
+        let pixel = get_column_index(index);
+        let modifier = table[(bits >> (45 - pixel * 3) & 7) as usize];
+        let step = match multiplier {
+            0 => modifier,
+            _ => modifier * multiplier * 8,
+        };
+        let signed = (base * 8 + step).clamp(-1023,1023);
+        let rounding = match signed < 0 {
+            true => -511,
+            false => 511,
+        };
+        *value = ((signed * 127 + rounding) / 1023) as i8 as u8;
+    }
+    return values;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /* Pixel index `n` of the BC blocks below picks palette entry `n % 4` for color and `n % 8` for the interpolated channels */
+    const BC1_RED_BLUE: [u8;8] = [0x00,0xF8,0x1F,0x00,0xE4,0xE4,0xE4,0xE4];
+    const BC4_INDICES: [u8;6] = [0x88,0xC6,0xFA,0x88,0xC6,0xFA];
+
+    fn decode(decoder: BlockDecoder,data: &[u8]) -> Vec<[u8;4]> {
+        let (width,height) = decoder.get_block_dimensions();
+        let mut block = vec![[0;BYTES_PER_PIXEL];width * height];
+        decoder.decode_block(data,&mut block);
+        return block;
+    }
+
+    fn bc4_block(a: u8,b: u8) -> Vec<u8> {
+        return [[a,b].as_slice(),&BC4_INDICES].concat();
+    }
+
+    fn repeat<const N: usize>(palette: [u8;N]) -> Vec<u8> {
+        return (0..BLOCK_PIXELS).map(|index|palette[index % N]).collect();
+    }
+
+    fn red_green(red: &[u8],green: &[u8],alpha: u8) -> Vec<[u8;4]> {
+        return red.iter().zip(green).map(|(&red,&green)|[red,green,0,alpha]).collect();
+    }
+
+    fn snorm<const N: usize>(values: [i8;N]) -> [u8;N] {
+        return values.map(|value|value as u8);
+    }
+
+    #[test]
+    fn rgba8_is_copied() {
+        let data = [1,2,3,4,5,6,7,8];
+        let size = UWimpyPoint { x: 2, y: 1 };
+        assert_eq!(decode_image(BlockDecoder::Rgba8,&data,size),data);
+    }
+
+    #[test]
+    fn bc1() {
+        let expected = [[255,0,0,255],[0,0,255,255],[170,0,85,255],[85,0,170,255]];
+        assert_eq!(decode(BlockDecoder::Bc1,&BC1_RED_BLUE),[expected;4].concat());
+    }
+
+    #[test]
+    fn bc1_punch_through() {
+        /* Swapped endpoints select the three color mode, index 3 is transparent black */
+        let data = [0x1F,0x00,0x00,0xF8,0x1B,0x1B,0x1B,0x1B];
+        let expected = [[0,0,0,0],[127,0,127,255],[255,0,0,255],[0,0,255,255]];
+        assert_eq!(decode(BlockDecoder::Bc1,&data),[expected;4].concat());
+    }
+
+    #[test]
+    fn bc2() {
+        let data = [87,56,123,5,250,135,19,84,151,57,245,35,1,223,146,148];
+        let expected = [
+            [33,125,173,119],[57,48,189,85],[57,48,189,136],[57,48,189,51],
+            [41,99,178,187],[41,99,178,119],[33,125,173,85],[41,99,178,0],
+            [49,73,183,170],[57,48,189,255],[33,125,173,119],[49,73,183,136],
+            [57,48,189,51],[33,125,173,17],[33,125,173,68],[49,73,183,85]
+        ];
+        assert_eq!(decode(BlockDecoder::Bc2,&data),expected);
+    }
+
+    #[test]
+    fn bc3() {
+        let data = [bc4_block(210,0),BC1_RED_BLUE.to_vec()].concat();
+        let colors = [[255,0,0],[0,0,255],[170,0,85],[85,0,170]];
+        let alpha = repeat([210,0,180,150,120,90,60,30]);
+        let expected: Vec<[u8;4]> = (0..BLOCK_PIXELS).map(|index|{
+            let [red,green,blue] = colors[index % 4];
+            [red,green,blue,alpha[index]]
+        }).collect();
+        assert_eq!(decode(BlockDecoder::Bc3,&data),expected);
+    }
+
+    #[test]
+    fn bc4() {
+        let red = repeat([0,200,40,80,120,160,0,255]);
+        assert_eq!(decode(BlockDecoder::Bc4,&bc4_block(0,200)),red_green(&red,&[0;BLOCK_PIXELS],255));
+    }
+
+    #[test]
+    fn bc4_snorm() {
+        let red = repeat(snorm([70,-70,50,30,10,-10,-30,-50]));
+        let data = bc4_block(70,-70i8 as u8);
+        assert_eq!(decode(BlockDecoder::Bc4Snorm,&data),red_green(&red,&[0;BLOCK_PIXELS],SNORM_ONE));
+    }
+
+    #[test]
+    fn bc5() {
+        let data = [bc4_block(0,200),bc4_block(210,0)].concat();
+        let red = repeat([0,200,40,80,120,160,0,255]);
+        let green = repeat([210,0,180,150,120,90,60,30]);
+        assert_eq!(decode(BlockDecoder::Bc5,&data),red_green(&red,&green,255));
+    }
+
+    #[test]
+    fn bc5_snorm() {
+        /* -128 is read as -127 */
+        let data = [bc4_block(70,-70i8 as u8),bc4_block(-128i8 as u8,127)].concat();
+        let red = repeat(snorm([70,-70,50,30,10,-10,-30,-50]));
+        let green = repeat(snorm([-127,127,-76,-25,25,76,-127,127]));
+        assert_eq!(decode(BlockDecoder::Bc5Snorm,&data),red_green(&red,&green,SNORM_ONE));
+    }
+
+    #[test]
+    fn bc6h_unsigned() {
+        let data = [44,156,15,223,59,174,19,184,228,56,133,5,58,136,1,133];
+        let blue = [246,255,253,226,255,240,255,213,255,213,253,213,213,255,213,240];
+        let expected: Vec<[u8;4]> = blue.iter().map(|&blue|[1,255,blue,255]).collect();
+        assert_eq!(decode(BlockDecoder::Bc6h { signed: false },&data),expected);
+    }
+
+    #[test]
+    fn bc6h_signed() {
+        let data = [105,98,146,0,227,33,83,43,147,253,164,101,225,103,213,138];
+        let expected = [
+            [5,255,0,255],[4,255,0,255],[3,255,0,255],[5,255,0,255],
+            [3,255,0,255],[5,255,0,255],[3,255,1,255],[1,255,0,255],
+            [3,255,0,255],[2,255,5,255],[30,255,1,255],[4,255,0,255],
+            [2,255,5,255],[30,255,1,255],[59,255,5,255],[30,255,1,255]
+        ];
+        assert_eq!(decode(BlockDecoder::Bc6h { signed: true },&data),expected);
+    }
+
+    #[test]
+    fn bc7() {
+        let data = [112,245,92,43,32,144,211,150,12,45,134,6,35,242,35,40];
+        let expected = [
+            [173,189,97,16],[95,184,0,94],[95,184,65,94],[135,186,97,54],
+            [57,181,0,132],[95,184,199,94],[173,189,0,16],[135,186,32,54],
+            [95,184,65,94],[135,186,199,54],[173,189,231,16],[95,184,32,94],
+            [95,184,65,94],[135,186,0,54],[135,186,65,54],[173,189,32,16]
+        ];
+        assert_eq!(decode(BlockDecoder::Bc7,&data),expected);
+    }
+
+    #[test]
+    fn etc2_rgb() {
+        let data = [6,104,246,228,99,37,162,32];
+        let expected = [
+            [0,55,208,255],[47,149,255,255],[97,131,97,255],[107,141,107,255],
+            [47,149,255,255],[0,0,72,255],[85,119,85,255],[85,119,85,255],
+            [0,55,208,255],[47,149,255,255],[107,141,107,255],[97,131,97,255],
+            [47,149,255,255],[47,149,255,255],[107,141,107,255],[119,153,119,255]
+        ];
+        assert_eq!(decode(BlockDecoder::Etc2Rgb,&data),expected);
+    }
+
+    #[test]
+    fn etc2_rgb_a1() {
+        let data = [22,110,14,105,127,160,241,52];
+        let expected = [
+            [44,110,48,255],[87,114,89,255],[130,118,130,255],[172,122,170,255],
+            [40,117,89,255],[83,121,130,255],[126,125,170,255],[168,129,211,255],
+            [36,124,130,255],[79,128,170,255],[122,132,211,255],[164,136,252,255],
+            [32,130,170,255],[75,134,211,255],[118,138,252,255],[160,142,255,255]
+        ];
+        assert_eq!(decode(BlockDecoder::Etc2RgbA1,&data),expected);
+    }
+
+    #[test]
+    fn etc2_rgba() {
+        let data = [190,127,89,24,215,190,228,190,20,124,122,102,166,194,112,190];
+        let expected = [
+            [136,119,204,141],[130,181,113,204],[136,119,204,218],[130,181,113,141],
+            [108,159,91,232],[130,181,113,127],[119,170,102,246],[108,159,91,141],
+            [130,181,113,141],[119,170,102,141],[119,170,102,218],[130,181,113,246],
+            [130,181,113,155],[108,159,91,246],[136,119,204,232],[119,170,102,232]
+        ];
+        assert_eq!(decode(BlockDecoder::Etc2Rgba,&data),expected);
+    }
+
+    #[test]
+    fn eac_r11() {
+        let data = [40,146,252,22,166,55,36,221];
+        let red = [148,0,0,0,148,0,76,0,22,49,103,0,0,103,0,76];
+        assert_eq!(decode(BlockDecoder::EacR11,&data),red_green(&red,&[0;BLOCK_PIXELS],255));
+    }
+
+    #[test]
+    fn eac_r11_snorm() {
+        let data = [101,138,124,64,78,9,180,134];
+        let red = [21,84,84,37,127,69,37,37,84,69,21,84,108,127,21,127];
+        assert_eq!(decode(BlockDecoder::EacR11Snorm,&data),red_green(&red,&[0;BLOCK_PIXELS],SNORM_ONE));
+    }
+
+    #[test]
+    fn eac_rg11() {
+        let data = [37,104,127,251,197,169,173,162,105,164,96,149,1,26,236,254];
+        let red = [0,67,67,79,91,91,0,79,91,25,0,43,91,67,0,0];
+        let green = [0,25,75,175,75,125,175,0,45,75,155,215,45,45,175,175];
+        assert_eq!(decode(BlockDecoder::EacRg11,&data),red_green(&red,&green,255));
+    }
+
+    #[test]
+    fn eac_rg11_snorm() {
+        let data = [50,94,187,75,36,227,85,90,44,107,206,168,30,51,73,99];
+        let red = [74,74,89,10,84,65,30,74,84,65,84,5,65,65,74,10];
+        let green = [79,50,14,50,240,32,50,68,68,240,79,50,2,79,50,240];
+        assert_eq!(decode(BlockDecoder::EacRg11Snorm,&data),red_green(&red,&green,SNORM_ONE));
+    }
+
+    #[test]
+    fn astc_4x4() {
+        let decoder = BlockDecoder::Astc { width: 4, height: 4, srgb: false };
+        let data = [207,33,5,23,129,197,133,184,245,31,33,109,71,167,244,20];
+        let expected = [
+            [170,176,143,255],[173,183,151,255],[173,183,151,255],[170,176,143,255],
+            [177,192,161,255],[175,188,156,255],[175,188,156,255],[177,192,161,255],
+            [174,186,154,255],[171,179,146,255],[172,180,148,255],[176,189,158,255],
+            [173,182,150,255],[171,178,145,255],[171,179,146,255],[174,186,154,255]
+        ];
+        assert_eq!(decode(decoder,&data),expected);
+    }
+
+    #[test]
+    fn astc_6x5() {
+        let decoder = BlockDecoder::Astc { width: 6, height: 5, srgb: false };
+        let data = [97,249,81,76,39,91,212,13,82,2,45,199,46,22,156,28];
+        let expected = [
+            [0,10,2,255],[0,10,2,255],[27,20,30,255],[0,153,34,255],[0,153,34,255],[0,153,34,255],
+            [27,20,30,255],[27,20,30,255],[170,51,153,255],[13,67,94,255],[27,20,30,255],[34,170,238,255],
+            [136,102,153,255],[34,170,238,255],[146,44,132,255],[27,20,30,255],[27,20,30,255],[13,67,94,255],
+            [13,67,94,255],[34,170,238,255],[146,44,132,255],[136,102,153,255],[170,51,153,255],[13,67,94,255],
+            [0,10,2,255],[13,67,94,255],[136,102,153,255],[13,67,94,255],[170,51,153,255],[136,102,153,255]
+        ];
+        assert_eq!(decode(decoder,&data),expected);
+    }
+
+    #[test]
+    fn astc_void_extent() {
+        let decoder = BlockDecoder::Astc { width: 4, height: 4, srgb: false };
+        /* A constant LDR color without an extent, the channels are 16 bit */
+        let data = [0xFC,0xFD,0xFF,0xFF,0xFF,0xFF,0xFF,0xFF,0xFF,0xFF,0x00,0x80,0x00,0x00,0x00,0x40];
+        assert_eq!(decode(decoder,&data),[[255,128,0,64];BLOCK_PIXELS]);
+    }
+
+    #[test]
+    fn astc_hdr_is_magenta() {
+        let decoder = BlockDecoder::Astc { width: 4, height: 4, srgb: false };
+        /* A void extent with the HDR bit set */
+        let data = [0xFC,0xFF,0xFF,0xFF,0xFF,0xFF,0xFF,0xFF,0x00,0x3C,0x00,0x3C,0x00,0x3C,0x00,0x3C];
+        assert_eq!(decode(decoder,&data),[[255,0,255,255];BLOCK_PIXELS]);
+    }
+}
@@ -0,0 +1,398 @@
+const WEIGHTS_2: [u32;4] = [0,21,43,64];
+const WEIGHTS_3: [u32;8] = [0,9,18,27,37,46,55,64];
+const WEIGHTS_4: [u32;16] = [0,4,9,13,17,21,26,30,34,38,43,47,51,55,60,64];
+
+/// One bit per pixel, set for the second subset
+const PARTITIONS_2: [u16;64] = [
+    0xCCCC,0x8888,0xEEEE,0xECC8,0xC880,0xFEEC,0xFEC8,0xEC80,
+    0xC800,0xFFEC,0xFE80,0xE800,0xFFE8,0xFF00,0xFFF0,0xF000,
+    0xF710,0x008E,0x7100,0x08CE,0x008C,0x7310,0x3100,0x8CCE,
+    0x088C,0x3110,0x6666,0x366C,0x17E8,0x0FF0,0x718E,0x399C,
+    0xAAAA,0xF0F0,0x5A5A,0x33CC,0x3C3C,0x55AA,0x9696,0xA55A,
+    0x73CE,0x13C8,0x324C,0x3BDC,0x6996,0xC33C,0x9966,0x0660,
+    0x0272,0x04E4,0x4E40,0x2720,0xC936,0x936C,0x39C6,0x639C,
+    0x9336,0x9CC6,0x817E,0xE718,0xCCF0,0x0FCC,0x7744,0xEE22,
+];
+/// Two bits per pixel
+const PARTITIONS_3: [u32;64] = [
+    0xAA685050,0x6A5A5040,0x5A5A4200,0x5450A0A8,0xA5A50000,0xA0A05050,0x5555A0A0,0x5A5A5050,
+    0xAA550000,0xAA555500,0xAAAA5500,0x90909090,0x94949494,0xA4A4A4A4,0xA9A59450,0x2A0A4250,
+    0xA5945040,0x0A425054,0xA5A5A500,0x55A0A0A0,0xA8A85454,0x6A6A4040,0xA4A45000,0x1A1A0500,
+    0x0050A4A4,0xAAA59090,0x14696914,0x69691400,0xA08585A0,0xAA821414,0x50A4A450,0x6A5A0200,
+    0xA9A58000,0x5090A0A8,0xA8A09050,0x24242424,0x00AA5500,0x24924924,0x24499224,0x50A50A50,
+    0x500AA550,0xAAAA4444,0x66660000,0xA5A0A5A0,0x50A050A0,0x69286928,0x44AAAA44,0x66666600,
+    0xAA444444,0x54A854A8,0x95809580,0x96969600,0xA85454A8,0x80959580,0xAA141414,0x96960000,
+    0xAAAA1414,0xA05050A0,0xA0A5A5A0,0x96000000,0x40804080,0xA9A8A9A8,0xAAAAAA44,0x2A4A5254,
+];
+/// The anchor of the second subset in `PARTITIONS_2`, the first subset's anchor is always pixel 0
+const ANCHORS_2: [u8;64] = [
+    15,15,15,15,15,15,15,15,15,15,15,15,15,15,15,15,
+    15,2,8,2,2,8,8,15,2,8,2,2,8,8,2,2,
+    15,15,6,8,2,8,15,15,2,8,2,2,2,15,15,6,
+    6,2,6,8,15,15,2,2,15,15,15,15,15,2,2,15,
+];
+const ANCHORS_3_SECOND: [u8;64] = [
+    3,3,15,15,8,3,15,15,8,8,6,6,6,5,3,3,
+    3,3,8,15,3,3,6,10,5,8,8,6,8,5,15,15,
+    8,15,3,5,6,10,8,15,15,3,15,5,15,15,15,15,
+    3,15,5,5,5,8,5,10,5,10,8,13,15,12,3,3,
+];
+const ANCHORS_3_THIRD: [u8;64] = [
+    15,8,8,3,15,15,3,8,15,15,15,15,15,15,15,8,
+    15,8,15,3,15,8,15,8,3,15,6,10,15,15,10,8,
+    15,3,15,10,10,8,9,10,6,15,8,15,3,6,6,8,
+    15,3,15,15,15,15,15,15,15,15,15,15,3,15,15,8,
+];
+
+struct Bc7Mode {
+    subsets:            usize,
+    partition_bits:     u32,
+    rotation_bits:      u32,
+    selection_bits:     u32,
+    color_bits:         u32,
+    alpha_bits:         u32,
+    /// A P-bit for each endpoint
+    endpoint_p_bits:    bool,
+    /// A P-bit for each subset
+    shared_p_bits:      bool,
+    index_bits:         u32,
+    /// Modes 4 and 5 store alpha and color indices separately
+    index_bits_2:       u32,
+}
+
+const BC7_MODES: [Bc7Mode;8] = [
+    Bc7Mode { subsets: 3, partition_bits: 4, rotation_bits: 0, selection_bits: 0, color_bits: 4, alpha_bits: 0, endpoint_p_bits: true,  shared_p_bits: false, index_bits: 3, index_bits_2: 0 },
+    Bc7Mode { subsets: 2, partition_bits: 6, rotation_bits: 0, selection_bits: 0, color_bits: 6, alpha_bits: 0, endpoint_p_bits: false, shared_p_bits: true,  index_bits: 3, index_bits_2: 0 },
+    Bc7Mode { subsets: 3, partition_bits: 6, rotation_bits: 0, selection_bits: 0, color_bits: 5, alpha_bits: 0, endpoint_p_bits: false, shared_p_bits: false, index_bits: 2, index_bits_2: 0 },
+    Bc7Mode { subsets: 2, partition_bits: 6, rotation_bits: 0, selection_bits: 0, color_bits: 7, alpha_bits: 0, endpoint_p_bits: true,  shared_p_bits: false, index_bits: 2, index_bits_2: 0 },
+    Bc7Mode { subsets: 1, partition_bits: 0, rotation_bits: 2, selection_bits: 1, color_bits: 5, alpha_bits: 6, endpoint_p_bits: false, shared_p_bits: false, index_bits: 2, index_bits_2: 3 },
+    Bc7Mode { subsets: 1, partition_bits: 0, rotation_bits: 2, selection_bits: 0, color_bits: 7, alpha_bits: 8, endpoint_p_bits: false, shared_p_bits: false, index_bits: 2, index_bits_2: 2 },
+    Bc7Mode { subsets: 1, partition_bits: 0, rotation_bits: 0, selection_bits: 0, color_bits: 7, alpha_bits: 7, endpoint_p_bits: true,  shared_p_bits: false, index_bits: 4, index_bits_2: 0 },
+    Bc7Mode { subsets: 2, partition_bits: 6, rotation_bits: 0, selection_bits: 0, color_bits: 5, alpha_bits: 5, endpoint_p_bits: true,  shared_p_bits: false, index_bits: 2, index_bits_2: 0 },
+];
+
+/* BC6H fields are endpoint * 3 + channel, endpoints W and X are the first region and Y and Z the second */
+const RW: u8 = 0;
+const GW: u8 = 1;
+const BW: u8 = 2;
+const RX: u8 = 3;
+const GX: u8 = 4;
+const BX: u8 = 5;
+const RY: u8 = 6;
+const GY: u8 = 7;
+const BY: u8 = 8;
+const RZ: u8 = 9;
+const GZ: u8 = 10;
+const BZ: u8 = 11;
+/// The partition
+const D: u8 = 12;
+
+/// The 2 or 5 bit mode numbers, in the order of `BC6H_MODES`
+const BC6H_MODE_NUMBERS: [u32;14] = [0b00,0b01,0b00010,0b00110,0b01010,0b01110,0b10010,0b10110,0b11010,0b11110,0b00011,0b00111,0b01011,0b01111];
+
+struct Bc6hMode {
+    /// The other endpoints are deltas from the first
+    transformed:    bool,
+    endpoint_bits:  u32,
+    /// Red, green and blue
+    delta_bits:     [u32;3],
+    /// Fields in the order they are stored after the mode: field, first bit, last bit
+    layout:         &'static [(u8,u8,u8)],
+}
+
+const fn bc6h_mode(transformed: bool,endpoint_bits: u32,delta_bits: [u32;3],layout: &'static [(u8,u8,u8)]) -> Bc6hMode {
+    Bc6hMode { transformed, endpoint_bits, delta_bits, layout }
+}
+
+const BC6H_LAYOUTS: [&[(u8,u8,u8)];14] = [
+    &[(GY,4,4),(BY,4,4),(BZ,4,4),(RW,0,9),(GW,0,9),(BW,0,9),(RX,0,4),(GZ,4,4),(GY,0,3),(GX,0,4),(BZ,0,0),(GZ,0,3),(BX,0,4),(BZ,1,1),(BY,0,3),(RY,0,4),(BZ,2,2),(RZ,0,4),(BZ,3,3),(D,0,4)],
+    &[(GY,5,5),(GZ,4,4),(GZ,5,5),(RW,0,6),(BZ,0,0),(BZ,1,1),(BY,4,4),(GW,0,6),(BY,5,5),(BZ,2,2),(GY,4,4),(BW,0,6),(BZ,3,3),(BZ,5,5),(BZ,4,4),(RX,0,5),(GY,0,3),(GX,0,5),(GZ,0,3),(BX,0,5),(BY,0,3),(RY,0,5),(RZ,0,5),(D,0,4)],
+    &[(RW,0,9),(GW,0,9),(BW,0,9),(RX,0,4),(RW,10,10),(GY,0,3),(GX,0,3),(GW,10,10),(BZ,0,0),(GZ,0,3),(BX,0,3),(BW,10,10),(BZ,1,1),(BY,0,3),(RY,0,4),(BZ,2,2),(RZ,0,4),(BZ,3,3),(D,0,4)],
+    &[(RW,0,9),(GW,0,9),(BW,0,9),(RX,0,3),(RW,10,10),(GZ,4,4),(GY,0,3),(GX,0,4),(GW,10,10),(GZ,0,3),(BX,0,3),(BW,10,10),(BZ,1,1),(BY,0,3),(RY,0,3),(BZ,0,0),(BZ,2,2),(RZ,0,3),(GY,4,4),(BZ,3,3),(D,0,4)],
+    &[(RW,0,9),(GW,0,9),(BW,0,9),(RX,0,3),(RW,10,10),(BY,4,4),(GY,0,3),(GX,0,3),(GW,10,10),(BZ,0,0),(GZ,0,3),(BX,0,4),(BW,10,10),(BY,0,3),(RY,0,3),(BZ,1,1),(BZ,2,2),(RZ,0,3),(BZ,4,4),(BZ,3,3),(D,0,4)],
+    &[(RW,0,8),(BY,4,4),(GW,0,8),(GY,4,4),(BW,0,8),(BZ,4,4),(RX,0,4),(GZ,4,4),(GY,0,3),(GX,0,4),(BZ,0,0),(GZ,0,3),(BX,0,4),(BZ,1,1),(BY,0,3),(RY,0,4),(BZ,2,2),(RZ,0,4),(BZ,3,3),(D,0,4)],
+    &[(RW,0,7),(GZ,4,4),(BY,4,4),(GW,0,7),(BZ,2,2),(GY,4,4),(BW,0,7),(BZ,3,3),(BZ,4,4),(RX,0,5),(GY,0,3),(GX,0,4),(BZ,0,0),(GZ,0,3),(BX,0,4),(BZ,1,1),(BY,0,3),(RY,0,5),(RZ,0,5),(D,0,4)],
+    &[(RW,0,7),(BZ,0,0),(BY,4,4),(GW,0,7),(GY,5,5),(GY,4,4),(BW,0,7),(GZ,5,5),(BZ,4,4),(RX,0,4),(GZ,4,4),(GY,0,3),(GX,0,5),(GZ,0,3),(BX,0,4),(BZ,1,1),(BY,0,3),(RY,0,4),(BZ,2,2),(RZ,0,4),(BZ,3,3),(D,0,4)],
+    &[(RW,0,7),(BZ,1,1),(BY,4,4),(GW,0,7),(BY,5,5),(GY,4,4),(BW,0,7),(BZ,5,5),(BZ,4,4),(RX,0,4),(GZ,4,4),(GY,0,3),(GX,0,4),(BZ,0,0),(GZ,0,3),(BX,0,5),(BY,0,3),(RY,0,4),(BZ,2,2),(RZ,0,4),(BZ,3,3),(D,0,4)],
+    &[(RW,0,5),(GZ,4,4),(BZ,0,0),(BZ,1,1),(BY,4,4),(GW,0,5),(GY,5,5),(BY,5,5),(BZ,2,2),(GY,4,4),(BW,0,5),(GZ,5,5),(BZ,3,3),(BZ,5,5),(BZ,4,4),(RX,0,5),(GY,0,3),(GX,0,5),(GZ,0,3),(BX,0,5),(BY,0,3),(RY,0,5),(RZ,0,5),(D,0,4)],
+    &[(RW,0,9),(GW,0,9),(BW,0,9),(RX,0,9),(GX,0,9),(BX,0,9)],
+    &[(RW,0,9),(GW,0,9),(BW,0,9),(RX,0,8),(RW,10,10),(GX,0,8),(GW,10,10),(BX,0,8),(BW,10,10)],
+    &[(RW,0,9),(GW,0,9),(BW,0,9),(RX,0,7),(RW,11,10),(GX,0,7),(GW,11,10),(BX,0,7),(BW,11,10)],
+    &[(RW,0,9),(GW,0,9),(BW,0,9),(RX,0,3),(RW,15,10),(GX,0,3),(GW,15,10),(BX,0,3),(BW,15,10)],
+];
+
+const BC6H_MODES: [Bc6hMode;14] = [
+    bc6h_mode(true,10,[5,5,5],BC6H_LAYOUTS[0]),
+    bc6h_mode(true,7,[6,6,6],BC6H_LAYOUTS[1]),
+    bc6h_mode(true,11,[5,4,4],BC6H_LAYOUTS[2]),
+    bc6h_mode(true,11,[4,5,4],BC6H_LAYOUTS[3]),
+    bc6h_mode(true,11,[4,4,5],BC6H_LAYOUTS[4]),
+    bc6h_mode(true,9,[5,5,5],BC6H_LAYOUTS[5]),
+    bc6h_mode(true,8,[6,5,5],BC6H_LAYOUTS[6]),
+    bc6h_mode(true,8,[5,6,5],BC6H_LAYOUTS[7]),
+    bc6h_mode(true,8,[5,5,6],BC6H_LAYOUTS[8]),
+    bc6h_mode(false,6,[6,6,6],BC6H_LAYOUTS[9]),
+    bc6h_mode(false,10,[10,10,10],BC6H_LAYOUTS[10]),
+    bc6h_mode(true,11,[9,9,9],BC6H_LAYOUTS[11]),
+    bc6h_mode(true,12,[8,8,8],BC6H_LAYOUTS[12]),
+    bc6h_mode(true,16,[4,4,4],BC6H_LAYOUTS[13]),
+];
+
+use super::block_decode::{Block, BlockBits};
+
+fn get_weights(index_bits: u32) -> &'static [u32] {
+    match index_bits {
+        2 => &WEIGHTS_2,
+        3 => &WEIGHTS_3,
+        _ => &WEIGHTS_4,
+    }
+}
+
+fn get_subset(subsets: usize,partition: usize,pixel: usize) -> usize {
+    match subsets {
+        2 => (PARTITIONS_2[partition] >> pixel & 1) as usize,
+        3 => (PARTITIONS_3[partition] >> (pixel * 2) & 3) as usize,
+        _ => 0,
+    }
+}
+
+/// Anchor indices are stored with one bit less, their top bit is always zero
+fn is_anchor(subsets: usize,partition: usize,pixel: usize) -> bool {
+    let anchor = match (subsets,get_subset(subsets,partition,pixel)) {
+        (_,0) => 0,
+        (2,_) => ANCHORS_2[partition],
+        (_,1) => ANCHORS_3_SECOND[partition],
+        _ => ANCHORS_3_THIRD[partition],
+    };
+    return pixel == anchor as usize;
+}
+
+fn interpolate(a: u32,b: u32,weight: u32) -> u32 {
+    ((64 - weight) * a + weight * b + 32) >> 6
+}
+
+/// Bit replication up to 8 bits
+fn expand(value: u32,bits: u32) -> u32 {
+    let value = value << (8 - bits);
+    return value | value >> bits;
+}
+
+/// Reserved modes decode to transparent black
+pub fn decode_bc7(data: &[u8],block: &mut Block) {
+    let mode_number = data[0].trailing_zeros() as usize;
+    let Some(mode) = BC7_MODES.get(mode_number) else {
+        block.fill([0;4]);
+        return;
+    };
+    let mut bits = BlockBits::new(data);
+    bits.position = mode_number as u32 + 1;
+
+    let partition = bits.read(mode.partition_bits) as usize;
+    let rotation = bits.read(mode.rotation_bits);
+    let selection = bits.read(mode.selection_bits);
+
+    let endpoint_count = mode.subsets * 2;
+    let mut endpoints = [[0u32;4];6];
+    for channel in 0..3 {
+        for endpoint in endpoints.iter_mut().take(endpoint_count) {
+            endpoint[channel] = bits.read(mode.color_bits);
+        }
+    }
+    for endpoint in endpoints.iter_mut().take(endpoint_count) {
+        endpoint[3] = bits.read(mode.alpha_bits);
+    }
+
+    let mut color_bits = mode.color_bits;
+    let mut alpha_bits = mode.alpha_bits;
+    if mode.endpoint_p_bits || mode.shared_p_bits {
+        let mut p_bits = [0;6];
+        if mode.endpoint_p_bits {
+            for p_bit in p_bits.iter_mut().take(endpoint_count) {
+                *p_bit = bits.read(1);
+            }
+        } else {
+            for subset in 0..mode.subsets {
+                let p_bit = bits.read(1);
+                p_bits[subset * 2] = p_bit;
+                p_bits[subset * 2 + 1] = p_bit;
+            }
+        }
+        for (endpoint,p_bit) in endpoints.iter_mut().zip(p_bits).take(endpoint_count) {
+            for channel in endpoint.iter_mut() {
+                *channel = *channel << 1 | p_bit;
+            }
+        }
+        color_bits += 1;
+        if alpha_bits > 0 {
+            alpha_bits += 1;
+        }
+    }
+    for endpoint in endpoints.iter_mut().take(endpoint_count) {
+        for channel in endpoint.iter_mut().take(3) {
+            *channel = expand(*channel,color_bits);
+        }
+        endpoint[3] = match alpha_bits {
+            0 => 255,
+            _ => expand(endpoint[3],alpha_bits),
+        };
+    }
+
+    let mut indices = [0;16];
+    for (pixel,index) in indices.iter_mut().enumerate() {
+        let anchor = is_anchor(mode.subsets,partition,pixel) as u32;
+        *index = bits.read(mode.index_bits - anchor);
+    }
+    let mut indices_2 = [0;16];
+    if mode.index_bits_2 > 0 {
+        for (pixel,index) in indices_2.iter_mut().enumerate() {
+            *index = bits.read(mode.index_bits_2 - (pixel == 0) as u32);
+        }
+    }
+
+    for (pixel,output) in block.iter_mut().enumerate() {
+        let subset = get_subset(mode.subsets,partition,pixel);
+        let a = endpoints[subset * 2];
+        let b = endpoints[subset * 2 + 1];
+        /* The selection bit of mode 4 swaps which index set goes to color and alpha */
+        let (color_weight,alpha_weight) = match (mode.index_bits_2,selection) {
+            (0,_) => {
+                let weight = get_weights(mode.index_bits)[indices[pixel] as usize];
+                (weight,weight)
+            },
+            (_,0) => (get_weights(mode.index_bits)[indices[pixel] as usize],get_weights(mode.index_bits_2)[indices_2[pixel] as usize]),
+            _ => (get_weights(mode.index_bits_2)[indices_2[pixel] as usize],get_weights(mode.index_bits)[indices[pixel] as usize]),
+        };
+        let mut color = [
+            interpolate(a[0],b[0],color_weight) as u8,
+            interpolate(a[1],b[1],color_weight) as u8,
+            interpolate(a[2],b[2],color_weight) as u8,
+            interpolate(a[3],b[3],alpha_weight) as u8,
+        ];
+        if rotation > 0 {
+            color.swap(rotation as usize - 1,3);
+        }
+        *output = color;
+    }
+}
+
+fn sign_extend(value: i32,bits: u32) -> i32 {
+    let shift = 32 - bits;
+    return value << shift >> shift;
+}
+
+fn unquantize_bc6h(value: i32,bits: u32,signed: bool) -> i32 {
+    if !signed {
+        return match value {
+            _ if bits >= 15 => value,
+            0 => 0,
+            _ if value == (1 << bits) - 1 => 0xFFFF,
+            _ => ((value << 16) + 0x8000) >> bits,
+        };
+    }
+    if bits >= 16 {
+        return value;
+    }
+    let magnitude = value.abs();
+    let unquantized = match magnitude {
+        0 => 0,
+        _ if magnitude >= (1 << (bits - 1)) - 1 => 0x7FFF,
+        _ => ((magnitude << 15) + 0x4000) >> (bits - 1),
+    };
+    return match value < 0 {
+        true => -unquantized,
+        false => unquantized,
+    };
+}
+
+/// Scales the interpolated value to half float bits and converts it to a byte, values outside of 0 to 1 are clamped
+fn finish_bc6h(value: i32,signed: bool) -> u8 {
+    let half = match signed {
+        true if value < 0 => return 0,
+        true => (value * 31) >> 5,
+        false => (value * 31) >> 6,
+    };
+    let exponent = half >> 10 & 31;
+    let mantissa = (half & 1023) as f32;
+    let float = match exponent {
+        0 => mantissa / (1 << 24) as f32,
+        _ => (1.0 + mantissa / 1024.0) * 2f32.powi(exponent - 15),
+    };
+    return (float.min(1.0) * 255.0 + 0.5) as u8;
+}
+
+/// Reserved modes decode to opaque black
+pub fn decode_bc6h(data: &[u8],signed: bool,block: &mut Block) {
+    let mut bits = BlockBits::new(data);
+    let mut mode_number = bits.read(2);
+    if mode_number >= 2 {
+        mode_number |= bits.read(3) << 2;
+    }
+    let Some(mode_index) = BC6H_MODE_NUMBERS.iter().position(|number|*number == mode_number) else {
+        block.fill([0,0,0,255]);
+        return;
+    };
+    let mode = &BC6H_MODES[mode_index];
+
+    let mut fields = [0i32;13];
+    for &(field,first,last) in mode.layout {
+        let mut bit = first as i32;
+        loop {
+            fields[field as usize] |= (bits.read(1) as i32) << bit;
+            if bit == last as i32 {
+                break;
+            }
+            bit += (last as i32 - first as i32).signum();
+        }
+    }
+
+    let regions = match mode_index < 10 {
+        true => 2,
+        false => 1,
+    };
+    let endpoint_count = regions * 2;
+    let mut endpoints = [[0i32;3];4];
+    for (endpoint,values) in endpoints.iter_mut().enumerate().take(endpoint_count) {
+        values.copy_from_slice(&fields[endpoint * 3..endpoint * 3 + 3]);
+    }
+
+    let endpoint_bits = mode.endpoint_bits;
+    let mask = (1 << endpoint_bits) - 1;
+    if signed {
+        for channel in endpoints[0].iter_mut() {
+            *channel = sign_extend(*channel,endpoint_bits);
+        }
+    }
+    let base = endpoints[0];
+    for endpoint in endpoints.iter_mut().take(endpoint_count).skip(1) {
+        for channel in 0..3 {
+            if mode.transformed {
+                let delta = sign_extend(endpoint[channel],mode.delta_bits[channel]);
+                endpoint[channel] = (base[channel] + delta) & mask;
+            }
+            if signed {
+                endpoint[channel] = sign_extend(endpoint[channel],endpoint_bits);
+            }
+        }
+    }
+    for endpoint in endpoints.iter_mut().take(endpoint_count) {
+        for channel in endpoint.iter_mut() {
+            *channel = unquantize_bc6h(*channel,endpoint_bits,signed);
+        }
+    }
+
+    let partition = fields[D as usize] as usize;
+    let index_bits = match regions {
+        2 => 3,
+        _ => 4,
+    };
+    let weights = get_weights(index_bits);
+    for (pixel,output) in block.iter_mut().enumerate() {
+        let anchor = is_anchor(regions,partition,pixel) as u32;
+        let weight = weights[bits.read(index_bits - anchor) as usize] as i32;
+        let region = get_subset(regions,partition,pixel);
+        let a = endpoints[region * 2];
+        let b = endpoints[region * 2 + 1];
+        let channel = |channel: usize|finish_bc6h(((64 - weight) * a[channel] + weight * b[channel] + 32) >> 6,signed);
+        *output = [channel(0),channel(1),channel(2),255];
+    }
+}
@@ -0,0 +1,355 @@
+const KTX2_IDENTIFIER: [u8;12] = [0xAB,0x4B,0x54,0x58,0x20,0x32,0x30,0xBB,0x0D,0x0A,0x1A,0x0A];
+/// Images with this extension are read as KTX2 containers instead of being decoded by `WimpyIO`
+pub const KTX2_EXTENSION: &'static str = "ktx2";
+
+const HEADER_SIZE: usize =      80;
+const LEVEL_INDEX_ENTRY: usize = 24;
+
+const SUPERCOMPRESSION_NONE: u32 = 0;
+
+use wgpu::*;
+
+use crate::{UWimpyPoint, app::graphics::GraphicsProvider};
+use super::block_decode;
+
+/// A block compressed (or plain RGBA8) image with the mip levels stored in its container.
+///
+/// Uploaded as is when the adapter supports its format, otherwise the first level is decoded to RGBA8 on the CPU.
+pub struct CompressedImage {
+    format: TextureFormat,
+    size:   UWimpyPoint,
+    /// Tightly packed block rows, largest level first
+    levels: Vec<Vec<u8>>,
+}
+
+#[derive(Debug)]
+pub enum Ktx2Error {
+    InvalidIdentifier,
+    Truncated,
+    /// The Vulkan format number of the container
+    UnsupportedFormat(u32),
+    /// Basis and Zstandard payloads are not decoded, export without supercompression
+    Supercompressed(u32),
+    /// Only single layer, single face 2D textures
+    UnsupportedShape,
+    /// More levels than a full mip chain of the image has
+    InvalidLevelCount(u32),
+    /// The byte length of the level doesn't fit in a `usize`
+    LevelTooLarge(usize),
+    LevelOutOfBounds(usize),
+    LevelSizeMismatch { level: usize, expected: usize, found: usize },
+}
+
+fn read_u32(data: &[u8],offset: usize) -> Result<u32,Ktx2Error> {
+    match data.get(offset..offset + 4) {
+        Some(bytes) => Ok(u32::from_le_bytes([bytes[0],bytes[1],bytes[2],bytes[3]])),
+        None => Err(Ktx2Error::Truncated),
+    }
+}
+
+fn read_usize(data: &[u8],offset: usize) -> Result<usize,Ktx2Error> {
+    let Some(bytes) = data.get(offset..offset + 8) else {
+        return Err(Ktx2Error::Truncated);
+    };
+    let mut value = [0;8];
+    value.copy_from_slice(bytes);
+    match usize::try_from(u64::from_le_bytes(value)) {
+        Ok(value) => Ok(value),
+        Err(_) => Err(Ktx2Error::Truncated),
+    }
+}
+
+/// `VkFormat` numbers to their wgpu counterparts. Only the formats wgpu can sample are mapped.
+fn get_texture_format(vk_format: u32) -> Option<TextureFormat> {
+    use TextureFormat::*;
+
+    fn astc(block: AstcBlock,srgb: bool) -> TextureFormat {
+        let channel = match srgb {
+            true => AstcChannel::UnormSrgb,
+            false => AstcChannel::Unorm,
+        };
+        Astc { block, channel }
+    }
+
+    /* ASTC LDR formats come in unorm and sRGB pairs, in this order */
+    const ASTC_BLOCKS: [AstcBlock;14] = [
+        AstcBlock::B4x4,AstcBlock::B5x4,AstcBlock::B5x5,AstcBlock::B6x5,AstcBlock::B6x6,
+        AstcBlock::B8x5,AstcBlock::B8x6,AstcBlock::B8x8,AstcBlock::B10x5,AstcBlock::B10x6,
+        AstcBlock::B10x8,AstcBlock::B10x10,AstcBlock::B12x10,AstcBlock::B12x12
+    ];
+
+    let format = match vk_format {
+        37 =>           Rgba8Unorm,
+        43 =>           Rgba8UnormSrgb,
+        /* The RGB variants of BC1 are read as RGBA, they only differ in the meaning of the punch through index */
+        131 | 133 =>    Bc1RgbaUnorm,
+        132 | 134 =>    Bc1RgbaUnormSrgb,
+        135 =>          Bc2RgbaUnorm,
+        136 =>          Bc2RgbaUnormSrgb,
+        137 =>          Bc3RgbaUnorm,
+        138 =>          Bc3RgbaUnormSrgb,
+        139 =>          Bc4RUnorm,
+        140 =>          Bc4RSnorm,
+        141 =>          Bc5RgUnorm,
+        142 =>          Bc5RgSnorm,
+        143 =>          Bc6hRgbUfloat,
+        144 =>          Bc6hRgbFloat,
+        145 =>          Bc7RgbaUnorm,
+        146 =>          Bc7RgbaUnormSrgb,
+        147 =>          Etc2Rgb8Unorm,
+        148 =>          Etc2Rgb8UnormSrgb,
+        149 =>          Etc2Rgb8A1Unorm,
+        150 =>          Etc2Rgb8A1UnormSrgb,
+        151 =>          Etc2Rgba8Unorm,
+        152 =>          Etc2Rgba8UnormSrgb,
+        153 =>          EacR11Unorm,
+        154 =>          EacR11Snorm,
+        155 =>          EacRg11Unorm,
+        156 =>          EacRg11Snorm,
+        157..=184 => {
+            let index = (vk_format - 157) as usize;
+            astc(ASTC_BLOCKS[index / 2],index % 2 == 1)
+        },
+        _ => return None,
+    };
+    return Some(format);
+}
+
+/// The byte length of a level with tightly packed block rows, `None` if it overflows
+fn get_level_length(format: TextureFormat,size: UWimpyPoint) -> Option<usize> {
+    let (block_width,block_height) = format.block_dimensions();
+    let block_size = format.block_copy_size(None).unwrap_or(0) as usize;
+    let columns = size.x.div_ceil(block_width) as usize;
+    let rows = size.y.div_ceil(block_height) as usize;
+    return columns.checked_mul(rows)?.checked_mul(block_size);
+}
+
+/// Levels in a full mip chain, down to 1x1
+fn get_max_level_count(size: UWimpyPoint) -> u32 {
+    size.x.max(size.y).ilog2() + 1
+}
+
+fn get_level_size(size: UWimpyPoint,level: usize) -> UWimpyPoint {
+    UWimpyPoint {
+        x: (size.x >> level).max(1),
+        y: (size.y >> level).max(1),
+    }
+}
+
+impl CompressedImage {
+    /// Reads a KTX2 container. Level data is copied out, the container can be dropped afterwards.
+    pub fn from_ktx2(data: &[u8]) -> Result<Self,Ktx2Error> {
+        if data.len() < HEADER_SIZE {
+            return Err(Ktx2Error::Truncated);
+        }
+        if data[..KTX2_IDENTIFIER.len()] != KTX2_IDENTIFIER {
+            return Err(Ktx2Error::InvalidIdentifier);
+        }
+
+        let vk_format =         read_u32(data,12)?;
+        let width =             read_u32(data,20)?;
+        let height =            read_u32(data,24)?;
+        let depth =             read_u32(data,28)?;
+        let layer_count =       read_u32(data,32)?;
+        let face_count =        read_u32(data,36)?;
+        let level_count =       read_u32(data,40)?;
+        let supercompression =  read_u32(data,44)?;
+
+        let Some(format) = get_texture_format(vk_format) else {
+            return Err(Ktx2Error::UnsupportedFormat(vk_format));
+        };
+        if supercompression != SUPERCOMPRESSION_NONE {
+            return Err(Ktx2Error::Supercompressed(supercompression));
+        }
+        if width == 0 || height == 0 || depth > 0 || layer_count > 1 || face_count != 1 {
+            return Err(Ktx2Error::UnsupportedShape);
+        }
+
+        let size = UWimpyPoint { x: width, y: height };
+        /* The count is untrusted, it is bounded before anything is allocated for it */
+        if level_count > get_max_level_count(size) {
+            return Err(Ktx2Error::InvalidLevelCount(level_count));
+        }
+        /* Zero asks the loader to generate the chain, only the first level is stored */
+        let level_count = level_count.max(1) as usize;
+        let mut levels = Vec::with_capacity(level_count);
+
+        for level in 0..level_count {
+            let index = HEADER_SIZE + level * LEVEL_INDEX_ENTRY;
+            let offset = read_usize(data,index)?;
+            let length = read_usize(data,index + 8)?;
+
+            let Some(expected) = get_level_length(format,get_level_size(size,level)) else {
+                return Err(Ktx2Error::LevelTooLarge(level));
+            };
+            if length != expected {
+                return Err(Ktx2Error::LevelSizeMismatch { level, expected, found: length });
+            }
+            let Some(bytes) = offset.checked_add(length).and_then(|end|data.get(offset..end)) else {
+                return Err(Ktx2Error::LevelOutOfBounds(level));
+            };
+            levels.push(bytes.to_vec());
+        }
+
+        return Ok(Self {
+            format,
+            size,
+            levels
+        });
+    }
+
+    pub fn format(&self) -> TextureFormat {
+        self.format
+    }
+
+    pub fn size(&self) -> UWimpyPoint {
+        self.size
+    }
+
+    pub fn level_count(&self) -> u32 {
+        self.levels.len() as u32
+    }
+
+    /// The adapter can sample the format and a texture of `size` can hold the image as is.
+    ///
+    /// Block compressed textures need block aligned sizes, images that are clipped or padded are decoded instead.
+    pub fn can_upload(&self,graphics_provider: &GraphicsProvider,size: UWimpyPoint) -> bool {
+        let (block_width,block_height) = self.format.block_dimensions();
+        return graphics_provider.supports_texture_format(self.format) &&
+            size == self.size &&
+            size.x.is_multiple_of(block_width) &&
+            size.y.is_multiple_of(block_height);
+    }
+
+    /// A CPU decoder exists for the format, see `decode_rgba8`
+    pub fn can_decode(&self) -> bool {
+        block_decode::get_decoder(self.format).is_some()
+    }
+
+    /// The format of the data returned by `decode_rgba8`. Signed formats keep their sign with `Rgba8Snorm`.
+    pub fn get_decoded_format(&self) -> TextureFormat {
+        use TextureFormat::*;
+        match self.format {
+            Bc4RSnorm | Bc5RgSnorm | EacR11Snorm | EacRg11Snorm => Rgba8Snorm,
+            format if format.is_srgb() => Rgba8UnormSrgb,
+            _ => Rgba8Unorm,
+        }
+    }
+
+    /// The first level as RGBA8 rows. `None` if there is no decoder for the format, which is only the case for ASTC HDR.
+    ///
+    /// BC6H is clamped to the 0 to 1 range.
+    pub fn decode_rgba8(&self) -> Option<Vec<u8>> {
+        let decoder = block_decode::get_decoder(self.format)?;
+        return Some(block_decode::decode_image(decoder,&self.levels[0],self.size));
+    }
+
+    /// Writes every level to a texture that was created with this image's format, size and level count
+    pub fn write(&self,queue: &Queue,texture: &Texture) {
+        let (block_width,block_height) = self.format.block_dimensions();
+        let block_size = self.format.block_copy_size(None).unwrap_or(0);
+        let level_count = self.levels.len().min(texture.mip_level_count() as usize);
+
+        for (level,data) in self.levels.iter().enumerate().take(level_count) {
+            let size = get_level_size(self.size,level);
+            let columns = size.x.div_ceil(block_width);
+            let rows = size.y.div_ceil(block_height);
+            queue.write_texture(
+                TexelCopyTextureInfo {
+                    texture,
+                    mip_level: level as u32,
+                    origin: Origin3d::ZERO,
+                    aspect: TextureAspect::All,
+                },
+                data,
+                TexelCopyBufferLayout {
+                    offset: 0,
+                    bytes_per_row: Some(columns * block_size),
+                    rows_per_image: Some(rows),
+                },
+                /* Copies of compressed levels cover whole blocks, even past the edge of a small level */
+                Extent3d {
+                    width: columns * block_width,
+                    height: rows * block_height,
+                    depth_or_array_layers: 1,
+                },
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const VK_FORMAT_BC1_RGBA_UNORM: u32 = 133;
+    const BC1_BLOCK_SIZE: usize = 8;
+
+    /// A BC1 container with the given level index entries, `data` follows the index
+    fn make_ktx2(width: u32,height: u32,levels: &[(usize,usize)],data: &[u8]) -> Vec<u8> {
+        let mut bytes = vec![0;HEADER_SIZE];
+        bytes[..KTX2_IDENTIFIER.len()].copy_from_slice(&KTX2_IDENTIFIER);
+        let mut write_u32 = |offset: usize,value: u32|bytes[offset..offset + 4].copy_from_slice(&value.to_le_bytes());
+        write_u32(12,VK_FORMAT_BC1_RGBA_UNORM);
+        write_u32(20,width);
+        write_u32(24,height);
+        write_u32(36,1);
+        write_u32(40,levels.len() as u32);
+        for &(offset,length) in levels {
+            bytes.extend_from_slice(&(offset as u64).to_le_bytes());
+            bytes.extend_from_slice(&(length as u64).to_le_bytes());
+            /* The uncompressed length matches without supercompression */
+            bytes.extend_from_slice(&(length as u64).to_le_bytes());
+        }
+        bytes.extend_from_slice(data);
+        return bytes;
+    }
+
+    fn get_data_start(level_count: usize) -> usize {
+        return HEADER_SIZE + level_count * LEVEL_INDEX_ENTRY;
+    }
+
+    #[test]
+    fn reads_levels() {
+        let start = get_data_start(2);
+        let first = 4 * BC1_BLOCK_SIZE;
+        let data = vec![0;first + BC1_BLOCK_SIZE];
+        let container = make_ktx2(8,8,&[(start,first),(start + first,BC1_BLOCK_SIZE)],&data);
+
+        let image = CompressedImage::from_ktx2(&container).unwrap();
+        assert_eq!(image.format(),TextureFormat::Bc1RgbaUnorm);
+        assert_eq!(image.size(),UWimpyPoint { x: 8, y: 8 });
+        assert_eq!(image.level_count(),2);
+    }
+
+    #[test]
+    fn rejects_too_many_levels() {
+        /* An 8x8 image has 4 levels, the index isn't read */
+        let container = make_ktx2(8,8,&[(0,0);5],&[]);
+        assert!(matches!(CompressedImage::from_ktx2(&container),Err(Ktx2Error::InvalidLevelCount(5))));
+    }
+
+    #[test]
+    fn rejects_level_size_mismatch() {
+        let start = get_data_start(1);
+        let container = make_ktx2(8,8,&[(start,BC1_BLOCK_SIZE)],&[0;BC1_BLOCK_SIZE]);
+        assert!(matches!(
+            CompressedImage::from_ktx2(&container),
+            Err(Ktx2Error::LevelSizeMismatch { level: 0, expected: 32, found: 8 })
+        ));
+    }
+
+    #[test]
+    fn rejects_level_out_of_bounds() {
+        let start = get_data_start(2);
+        let first = 4 * BC1_BLOCK_SIZE;
+        /* The second level is missing its last byte */
+        let data = vec![0;first + BC1_BLOCK_SIZE - 1];
+        let container = make_ktx2(8,8,&[(start,first),(start + first,BC1_BLOCK_SIZE)],&data);
+        assert!(matches!(CompressedImage::from_ktx2(&container),Err(Ktx2Error::LevelOutOfBounds(1))));
+
+        /* An offset that overflows when the length is added */
+        let container = make_ktx2(4,4,&[(usize::MAX,BC1_BLOCK_SIZE)],&[]);
+        assert!(matches!(CompressedImage::from_ktx2(&container),Err(Ktx2Error::LevelOutOfBounds(0))));
+    }
+}
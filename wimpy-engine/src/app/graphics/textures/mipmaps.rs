@@ -0,0 +1,175 @@
+use wgpu::*;
+
+use crate::{UWimpyPoint, app::graphics::{GraphicsProvider, constants}};
+
+/// Fills the mip chain of a texture from its first level, each level is a linear downsample of the one before it.
+///
+/// Sampling and rendering go through the sRGB view, so the averaging happens in linear space.
+/// Textures without the sRGB suffix hold linear data already and are averaged as stored.
+pub struct MipmapGenerator {
+    pipeline:           RenderPipeline,
+    /// Renders to `INTERNAL_TEXTURE_FORMAT` without the sRGB suffix
+    linear_pipeline:    RenderPipeline,
+    layout:             BindGroupLayout,
+    sampler:            Sampler,
+}
+
+fn create_pipeline(device: &Device,layout: &PipelineLayout,shader: &ShaderModule,format: TextureFormat) -> RenderPipeline {
+    return device.create_render_pipeline(&RenderPipelineDescriptor {
+        label: Some("Mipmap Blit Pipeline"),
+        layout: Some(layout),
+        vertex: VertexState {
+            module: shader,
+            entry_point: Some("vs_main"),
+            compilation_options: PipelineCompilationOptions::default(),
+            buffers: &[],
+        },
+        fragment: Some(FragmentState {
+            module: shader,
+            entry_point: Some("fs_main"),
+            compilation_options: PipelineCompilationOptions::default(),
+            targets: &[Some(ColorTargetState {
+                format,
+                blend: None,
+                write_mask: ColorWrites::ALL,
+            })],
+        }),
+        primitive: PrimitiveState::default(),
+        depth_stencil: None,
+        multisample: MultisampleState::default(),
+        multiview_mask: None,
+        cache: None,
+    });
+}
+
+impl MipmapGenerator {
+    pub fn create(device: &Device) -> Self {
+        let shader = device.create_shader_module(ShaderModuleDescriptor {
+            label: Some("Mipmap Blit Shader"),
+            source: ShaderSource::Wgsl(include_str!("../pipelines/shaders/mipmap_blit.wgsl").into())
+        });
+
+        let layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            label: Some("Mipmap Blit Bind Group Layout"),
+            entries: &[
+                BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: BindingType::Texture {
+                        multisampled: false,
+                        view_dimension: TextureViewDimension::D2,
+                        sample_type: TextureSampleType::Float {
+                            filterable: true
+                        },
+                    },
+                    count: None,
+                },
+                BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: BindingType::Sampler(SamplerBindingType::Filtering),
+                    count: None,
+                },
+            ],
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+            label: Some("Mipmap Blit Pipeline Layout"),
+            bind_group_layouts: &[&layout],
+            immediate_size: 0,
+        });
+
+        let pipeline = create_pipeline(device,&pipeline_layout,&shader,constants::INTERNAL_TEXTURE_FORMAT);
+        let linear_pipeline = create_pipeline(device,&pipeline_layout,&shader,constants::INTERNAL_TEXTURE_FORMAT.remove_srgb_suffix());
+
+        let sampler = device.create_sampler(&SamplerDescriptor {
+            label: Some("Mipmap Blit Sampler"),
+            address_mode_u: AddressMode::ClampToEdge,
+            address_mode_v: AddressMode::ClampToEdge,
+            address_mode_w: AddressMode::ClampToEdge,
+            mag_filter: FilterMode::Linear,
+            min_filter: FilterMode::Linear,
+            ..Default::default()
+        });
+
+        return Self {
+            pipeline,
+            linear_pipeline,
+            layout,
+            sampler,
+        };
+    }
+
+    /// The full chain, down to one pixel
+    pub fn get_level_count(size: UWimpyPoint) -> u32 {
+        u32::BITS - size.largest().max(1).leading_zeros()
+    }
+
+    /// `INTERNAL_TEXTURE_FORMAT`, with or without the sRGB suffix
+    pub fn supports_format(format: TextureFormat) -> bool {
+        format.remove_srgb_suffix() == constants::INTERNAL_TEXTURE_FORMAT.remove_srgb_suffix()
+    }
+
+    /// The format must pass `supports_format` and the texture must have `RENDER_ATTACHMENT` usage.
+    /// Level 0 must already be written, queue writes land before this submission.
+    pub fn generate(&self,graphics_provider: &GraphicsProvider,texture: &Texture) {
+        let level_count = texture.mip_level_count();
+        if level_count < 2 {
+            return;
+        }
+
+        let pipeline = match texture.format() == constants::INTERNAL_TEXTURE_FORMAT {
+            true => &self.pipeline,
+            false => &self.linear_pipeline,
+        };
+        let device = graphics_provider.get_device();
+        let views: Vec<TextureView> = (0..level_count).map(|level|texture.create_view(&TextureViewDescriptor {
+            label: Some("Mipmap Level View"),
+            base_mip_level: level,
+            mip_level_count: Some(1),
+            ..Default::default()
+        })).collect();
+
+        let mut encoder = device.create_command_encoder(&CommandEncoderDescriptor {
+            label: Some("Mipmap Encoder")
+        });
+
+        for level in 1..level_count as usize {
+            let bind_group = device.create_bind_group(&BindGroupDescriptor {
+                label: Some("Mipmap Blit Bind Group"),
+                layout: &self.layout,
+                entries: &[
+                    BindGroupEntry {
+                        binding: 0,
+                        resource: BindingResource::TextureView(&views[level - 1]),
+                    },
+                    BindGroupEntry {
+                        binding: 1,
+                        resource: BindingResource::Sampler(&self.sampler),
+                    },
+                ],
+            });
+            let mut render_pass = encoder.begin_render_pass(&RenderPassDescriptor {
+                label: Some("Mipmap Blit Pass"),
+                color_attachments: &[Some(RenderPassColorAttachment {
+                    view: &views[level],
+                    depth_slice: None,
+                    resolve_target: None,
+                    ops: Operations {
+                        load: LoadOp::Clear(Color::TRANSPARENT),
+                        store: StoreOp::Store,
+                    },
+                })],
+                multiview_mask: None,
+                depth_stencil_attachment: None,
+                occlusion_query_set: None,
+                timestamp_writes: None,
+            });
+            render_pass.set_pipeline(pipeline);
+            render_pass.set_bind_group(0,&bind_group,&[]);
+            render_pass.draw(0..3,0..1);
+        }
+
+        graphics_provider.get_queue().submit(Some(encoder.finish()));
+    }
+}
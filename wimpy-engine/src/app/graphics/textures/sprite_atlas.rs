@@ -0,0 +1,420 @@
+use std::collections::HashMap;
+
+use wgpu::{Color, CommandEncoder, Extent3d, LoadOp, Operations, Origin3d, RenderPassColorAttachment, RenderPassDescriptor, StoreOp, TexelCopyTextureInfo, TextureAspect};
+use crate::{UWimpyPoint, WimpyRect, WimpyVec, app::graphics::{GraphicsProvider, constants}};
+use super::{BindGroupIdentity, WimpyTextureKey, TextureManager, TextureLoadState};
+
+pub struct SpriteAtlasConfig {
+    /// The width and height of a page, clamped to the largest texture the adapter allows
+    pub page_size:      u32,
+    /// Pages are created as they are needed, up to this many
+    pub max_pages:      usize,
+    /// Empty pixels to the right of and below every sprite, keeps linear filtering from bleeding into neighbours
+    pub padding:        u32,
+    /// Sprites that aren't set for this many flushes are evicted
+    pub idle_frames:    u32,
+}
+
+/// Where a texture was packed. UVs cover the texture's input size, use them like `WimpyRect::ONE` would be used on the texture itself.
+#[derive(Copy,Clone)]
+pub struct SpriteRegion {
+    pub page:       WimpyTextureKey,
+    pub uv_area:    WimpyRect,
+}
+
+/// Packs textures of any size into shared pages with a skyline packer, so draws of different textures can share a bind group.
+///
+/// Space is only reclaimed by repacking. `flush` evicts idle sprites, then repacks every page when an insert failed or too much space is wasted.
+/// Repacked sprites move, so regions are only valid until the next `flush`.
+pub struct SpriteAtlas {
+    page_size:      u32,
+    max_pages:      usize,
+    padding:        u32,
+    idle_frames:    u32,
+    pages:          Vec<SpritePage>,
+    entries:        HashMap<WimpyTextureKey,SpriteEntry>,
+    copy_commands:  Vec<SpriteCopyCommand>,
+    /// Counts flushes, the time base of `idle_frames`
+    frame:          u32,
+    /// An insert failed with every page full, the next `flush` repacks
+    needs_repack:   bool,
+}
+
+struct SpritePage {
+    key:         WimpyTextureKey,
+    skyline:     Skyline,
+    /// Repacked, the old sprites would show through the padding of the new ones. Cleared by the next `flush`.
+    needs_clear: bool,
+}
+
+#[derive(Copy,Clone)]
+struct SpriteEntry {
+    page:       usize,
+    origin:     UWimpyPoint,
+    size:       UWimpyPoint,
+    load_state: TextureLoadState,
+    /// The identity of the source view that was copied, a reload replaces the view without changing the load state
+    source_id:  BindGroupIdentity,
+    last_used:  u32,
+}
+
+struct SpriteCopyCommand {
+    source:     WimpyTextureKey,
+    page:       usize,
+    origin:     UWimpyPoint,
+    size:       UWimpyPoint,
+}
+
+#[derive(Copy,Clone)]
+struct SkylineSegment {
+    x:      u32,
+    y:      u32,
+    width:  u32,
+}
+
+/// The top edge of the packed area as a list of horizontal segments, left to right
+struct Skyline {
+    size:           u32,
+    segments:       Vec<SkylineSegment>,
+    /// Pixels handed out since the last clear
+    allocated_area: u64,
+}
+
+impl Skyline {
+    fn new(size: u32) -> Self {
+        Self {
+            size,
+            segments: vec![SkylineSegment { x: 0, y: 0, width: size }],
+            allocated_area: 0,
+        }
+    }
+
+    fn clear(&mut self) {
+        self.segments.clear();
+        self.segments.push(SkylineSegment { x: 0, y: 0, width: self.size });
+        self.allocated_area = 0;
+    }
+
+    /// The height a rectangle would rest at if its left edge starts on this segment
+    fn fit(&self,index: usize,size: UWimpyPoint) -> Option<u32> {
+        if self.segments[index].x + size.x > self.size {
+            return None;
+        }
+        let mut y = 0;
+        let mut remaining = size.x;
+        for segment in &self.segments[index..] {
+            y = y.max(segment.y);
+            if y + size.y > self.size {
+                return None;
+            }
+            if segment.width >= remaining {
+                return Some(y);
+            }
+            remaining -= segment.width;
+        }
+        return None;
+    }
+
+    /// Bottom left rule, the lowest resting height wins and ties go to the leftmost segment
+    fn insert(&mut self,size: UWimpyPoint) -> Option<UWimpyPoint> {
+        let mut best: Option<(usize,u32)> = None;
+        for index in 0..self.segments.len() {
+            let Some(y) = self.fit(index,size) else {
+                continue;
+            };
+            if best.is_none_or(|(_,best_y)|y < best_y) {
+                best = Some((index,y));
+            }
+        }
+        let (index,y) = best?;
+        let x = self.segments[index].x;
+
+        self.segments.insert(index,SkylineSegment { x, y: y + size.y, width: size.x });
+
+        /* Segments under the new one are cut back or removed */
+        let right = x + size.x;
+        let next = index + 1;
+        while next < self.segments.len() {
+            let segment = &mut self.segments[next];
+            if segment.x >= right {
+                break;
+            }
+            let overlap = right - segment.x;
+            if overlap < segment.width {
+                segment.x += overlap;
+                segment.width -= overlap;
+                break;
+            }
+            self.segments.remove(next);
+        }
+
+        self.segments.dedup_by(|next,previous|{
+            if next.y != previous.y {
+                return false;
+            }
+            previous.width += next.width;
+            return true;
+        });
+
+        self.allocated_area += size.x as u64 * size.y as u64;
+        return Some(UWimpyPoint { x, y });
+    }
+}
+
+impl SpriteAtlas {
+    pub fn new(graphics_provider: &GraphicsProvider,config: &SpriteAtlasConfig) -> Self {
+        Self {
+            page_size:      graphics_provider.get_safe_texture_dimension_value(config.page_size),
+            max_pages:      config.max_pages,
+            padding:        config.padding,
+            idle_frames:    config.idle_frames,
+            pages:          Vec::with_capacity(config.max_pages),
+            entries:        HashMap::new(),
+            copy_commands:  Vec::new(),
+            frame:          0,
+            needs_repack:   false,
+        }
+    }
+
+    /// Packs the texture if it isn't already, and marks it as used this frame.
+    ///
+    /// `None` if the texture is bigger than a page, isn't in `INTERNAL_TEXTURE_FORMAT` (compressed textures can't be copied in),
+    /// or every page is full. Draw it from its own texture instead.
+    pub fn set_texture(
+        &mut self,
+        graphics_provider: &GraphicsProvider,
+        texture_manager: &mut TextureManager,
+        key: WimpyTextureKey
+    ) -> Option<SpriteRegion> {
+        let (size,load_state,source_id,compatible) = {
+            let source = texture_manager.get_or_default(key);
+            let format = source.view.texture().format();
+            (source.input_size,source.load_state,source.bind_group_id,format.remove_srgb_suffix() == constants::INTERNAL_TEXTURE_FORMAT.remove_srgb_suffix())
+        };
+        if !compatible {
+            return None;
+        }
+
+        if let Some(entry) = self.entries.get_mut(&key) {
+            entry.last_used = self.frame;
+            if entry.load_state == load_state && entry.source_id == source_id {
+                let entry = *entry;
+                return Some(self.get_region(entry));
+            }
+            /* A streamed texture finished loading, failed, or was reloaded. The new view might not be the same size */
+            entry.load_state = load_state;
+            entry.source_id = source_id;
+            if entry.size == size {
+                let entry = *entry;
+                self.queue_copy(key,entry);
+                return Some(self.get_region(entry));
+            }
+            self.entries.remove(&key);
+        }
+
+        let (page,origin) = self.allocate(graphics_provider,texture_manager,size)?;
+        let entry = SpriteEntry {
+            page,
+            origin,
+            size,
+            load_state,
+            source_id,
+            last_used: self.frame,
+        };
+        self.entries.insert(key,entry);
+        self.queue_copy(key,entry);
+        return Some(self.get_region(entry));
+    }
+
+    fn get_region(&self,entry: SpriteEntry) -> SpriteRegion {
+        let page_size = WimpyVec::from(self.page_size);
+        SpriteRegion {
+            page: self.pages[entry.page].key,
+            uv_area: WimpyRect {
+                position: WimpyVec::from(entry.origin) / page_size,
+                size: WimpyVec::from(entry.size) / page_size,
+            },
+        }
+    }
+
+    fn queue_copy(&mut self,source: WimpyTextureKey,entry: SpriteEntry) {
+        self.copy_commands.push(SpriteCopyCommand {
+            source,
+            page: entry.page,
+            origin: entry.origin,
+            size: entry.size,
+        });
+    }
+
+    fn get_padded_size(&self,size: UWimpyPoint) -> UWimpyPoint {
+        size + UWimpyPoint { x: self.padding, y: self.padding }
+    }
+
+    fn allocate(
+        &mut self,
+        graphics_provider: &GraphicsProvider,
+        texture_manager: &mut TextureManager,
+        size: UWimpyPoint
+    ) -> Option<(usize,UWimpyPoint)> {
+        let padded_size = self.get_padded_size(size);
+        if padded_size.x > self.page_size || padded_size.y > self.page_size {
+            return None;
+        }
+        for (index,page) in self.pages.iter_mut().enumerate() {
+            if let Some(origin) = page.skyline.insert(padded_size) {
+                return Some((index,origin));
+            }
+        }
+        if self.pages.len() >= self.max_pages {
+            self.needs_repack = true;
+            return None;
+        }
+        let (key,_) = texture_manager.create_atlas_texture(graphics_provider,self.page_size.into(),constants::INTERNAL_TEXTURE_FORMAT,true);
+        let mut skyline = Skyline::new(self.page_size);
+        let origin = skyline.insert(padded_size)?;
+        self.pages.push(SpritePage { key, skyline, needs_clear: false });
+        return Some((self.pages.len() - 1,origin));
+    }
+
+    /// Encodes the copies of newly packed sprites, then evicts and repacks for the next frame. Call once per frame before drawing from the atlas.
+    pub fn flush(
+        &mut self,
+        texture_manager: &mut TextureManager,
+        encoder: &mut CommandEncoder
+    ) {
+        for page in self.pages.iter_mut().filter(|page|page.needs_clear) {
+            page.needs_clear = false;
+            let view = match texture_manager.get_no_touch(page.key) {
+                Ok(entry) => entry.view,
+                Err(error) => {
+                    log::warn!("Failure to retrieve sprite atlas page: {:?}",error);
+                    continue;
+                },
+            };
+            /* Clearing doesn't need a draw, the pass ends as soon as it is dropped */
+            encoder.begin_render_pass(&RenderPassDescriptor {
+                label: Some("Sprite Atlas Clear Pass"),
+                color_attachments: &[Some(RenderPassColorAttachment {
+                    view,
+                    depth_slice: None,
+                    resolve_target: None,
+                    ops: Operations {
+                        load: LoadOp::Clear(Color::TRANSPARENT),
+                        store: StoreOp::Store,
+                    },
+                })],
+                multiview_mask: None,
+                depth_stencil_attachment: None,
+                occlusion_query_set: None,
+                timestamp_writes: None,
+            });
+        }
+
+        for command in self.copy_commands.drain(..) {
+            let destination = match texture_manager.get_no_touch(self.pages[command.page].key) {
+                Ok(entry) => entry.view.texture(),
+                Err(error) => {
+                    log::warn!("Failure to retrieve sprite atlas page: {:?}",error);
+                    continue;
+                },
+            };
+            let source = match texture_manager.get_no_touch(command.source) {
+                Ok(entry) => entry,
+                Err(_) => texture_manager.get_fallback_texture(command.source),
+            }.view.texture();
+            encoder.copy_texture_to_texture(
+                TexelCopyTextureInfo {
+                    texture:    source,
+                    origin:     Origin3d::ZERO,
+                    aspect:     TextureAspect::All,
+                    mip_level:  0,
+                },
+                TexelCopyTextureInfo {
+                    texture:    destination,
+                    origin:     command.origin.into(),
+                    aspect:     TextureAspect::All,
+                    mip_level:  0,
+                },
+                Extent3d {
+                    width: command.size.x.min(source.width()),
+                    height: command.size.y.min(source.height()),
+                    depth_or_array_layers: 1,
+                }
+            );
+        }
+
+        self.frame = self.frame.wrapping_add(1);
+        self.evict_idle();
+        if self.needs_repack || self.is_fragmented() {
+            self.repack();
+        }
+    }
+
+    fn evict_idle(&mut self) {
+        let frame = self.frame;
+        let idle_frames = self.idle_frames;
+        self.entries.retain(|_,entry|frame.wrapping_sub(entry.last_used) <= idle_frames);
+    }
+
+    /// More than half of the pages is handed out, but less than half of that still holds a sprite
+    fn is_fragmented(&self) -> bool {
+        let page_area = self.page_size as u64 * self.page_size as u64;
+        let allocated: u64 = self.pages.iter().map(|page|page.skyline.allocated_area).sum();
+        let live: u64 = self.entries.values().map(|entry|{
+            let size = self.get_padded_size(entry.size);
+            size.x as u64 * size.y as u64
+        }).sum();
+        return allocated * 2 > page_area * self.pages.len() as u64 && live * 2 < allocated;
+    }
+
+    /// Packs every sprite again, tallest first. Sprites that don't fit anymore are evicted and come back on their next `set_texture`.
+    ///
+    /// The pages are cleared and every sprite copied again by the next `flush`, this frame's draws still see the old layout.
+    fn repack(&mut self) {
+        self.needs_repack = false;
+        for page in &mut self.pages {
+            page.skyline.clear();
+            page.needs_clear = true;
+        }
+
+        let mut order: Vec<(WimpyTextureKey,UWimpyPoint)> = self.entries.iter().map(|(key,entry)|(*key,entry.size)).collect();
+        order.sort_by(|a,b|b.1.y.cmp(&a.1.y).then(b.1.x.cmp(&a.1.x)));
+
+        for (key,size) in order {
+            let padded_size = self.get_padded_size(size);
+            let placement = self.pages.iter_mut().enumerate().find_map(|(index,page)|{
+                page.skyline.insert(padded_size).map(|origin|(index,origin))
+            });
+            let Some((page,origin)) = placement else {
+                self.entries.remove(&key);
+                continue;
+            };
+            if let Some(entry) = self.entries.get_mut(&key) {
+                entry.page = page;
+                entry.origin = origin;
+                let entry = *entry;
+                self.queue_copy(key,entry);
+            }
+        }
+    }
+
+    /// Removes the pages from the texture manager. The atlas is empty afterwards and creates new pages as they are needed.
+    pub fn destroy(&mut self,texture_manager: &mut TextureManager) {
+        for page in self.pages.drain(..) {
+            if let Err(error) = texture_manager.remove_texture(page.key) {
+                log::warn!("Failure to remove sprite atlas page: {:?}",error);
+            }
+        }
+        self.entries.clear();
+        self.copy_commands.clear();
+        self.needs_repack = false;
+    }
+
+    pub fn get_page_count(&self) -> usize {
+        self.pages.len()
+    }
+
+    pub fn get_sprite_count(&self) -> usize {
+        self.entries.len()
+    }
+}
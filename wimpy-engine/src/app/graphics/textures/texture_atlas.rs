@@ -86,6 +86,12 @@ impl TextureAtlas {
                 Ok(entry) => entry,
                 Err(_) => texture_manager.get_fallback_texture(command.key),
             };
+            /* Copies can't convert, a compressed atlas only takes textures of its own format (sRGB or not) */
+            let src_format = src_texture.view.texture().format();
+            if src_format.remove_srgb_suffix() != dst_texture.format().remove_srgb_suffix() {
+                log::warn!("Texture format {:?} can't be copied into a {:?} atlas",src_format,dst_texture.format());
+                continue;
+            }
             let src = TexelCopyTextureInfo {
                 texture:    src_texture.view.texture(),
                 origin:     Origin3d::ZERO,
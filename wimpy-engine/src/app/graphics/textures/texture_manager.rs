@@ -1,11 +1,14 @@
 const UPDATE_OPERATIONS_BUFFER_DEFAULT_SIZE: usize =    16;
+const STREAM_REQUESTS_BUFFER_DEFAULT_SIZE: usize =      16;
+const DEFAULT_TIME_TO_LIVE_SECONDS: f32 =               30.0;
 
 use gltf::Image;
+use slotmap::SparseSecondaryMap;
 use wgpu::*;
 use std::num::NonZeroU32;
 
 use super::{*,bind_group_cache::{BindGroupCache, BindGroupChannelSet, BindGroupChannel}};
-use crate::{UWimpyPoint, WimpyPointRect, app::{WimpyIO, wam::HardAsset, WimpyImageData, EngineTextures, graphics::{GraphicsProvider, constants}}};
+use crate::{UWimpyPoint, WimpyPointRect, app::{wam::{HardAsset, HardAssetKey}, WimpyImageData, EngineTextures, graphics::{GraphicsProvider, constants}}};
 
 #[derive(Default,Clone,Copy)]
 pub enum StreamingPolicy {
@@ -37,6 +40,8 @@ pub enum StreamingHint {
     Atlas,
     /// Tells the streaming policy this texture should always behave as `StaticGPU`.
     Static,
+    /// No particular stream policy tuning, and the texture gets a full mip chain. Sample it with a trilinear `SamplerMode`.
+    Mipmapped,
 }
 
 pub struct TextureCopyParameters {
@@ -49,12 +54,16 @@ pub struct TextureCopyParameters {
 
 pub struct TextureCreationParameters {
     pub wam_id:         HardAsset,
+    /// Streaming loads are requested with this key, see `TextureStreamRequest`
+    pub wam_key:        HardAssetKey,
     /// A verified size or a hint provided by WAM.
     /// 
     /// If provided by WAM, the asset's real size in storage may be in disagreement with the manifest.
     pub size_hint:      UWimpyPoint,
     pub policy_hint:    StreamingHint,
-    pub slice:          Option<WimpyPointRect>
+    pub slice:          Option<WimpyPointRect>,
+    /// Requested by WAM metadata, `StreamingHint::Mipmapped` also turns it on
+    pub mipmaps:        bool,
 }
 
 #[derive(Copy,Clone,PartialEq,Eq,Hash)]
@@ -70,12 +79,19 @@ pub struct WimpyTextureInternal {
     /// The size of the texture according to WAM metadata or similiar.
     size_hint:          UWimpyPoint,
     wam_id:             Option<HardAsset>,
+    wam_key:            Option<HardAssetKey>,
     pub bind_group_id:  BindGroupIdentity,
     pub view:           Option<TextureView>,
     /// RGBA8 Representation of texture information. TODO: Determine if this is linear or gamma space.
     local_data:         Option<Vec<u8>>,
     policy_hint:        StreamingHint,
-    pub load_state:     TextureLoadState
+    pub load_state:     TextureLoadState,
+    /// The streaming clock at the last touch, in seconds
+    last_touch:         f64,
+    /// Counts up every time the texture is streamed, loads that finish for an older generation are dropped
+    stream_generation:  u8,
+    /// Uploads fill a full mip chain, see `MipmapGenerator`
+    mipmaps:            bool,
 }
 
 struct FallbackTexture {
@@ -92,15 +108,21 @@ impl WimpyTextureInternal {
         Self {
             size_hint: size,
             wam_id: None,
+            wam_key: None,
             bind_group_id,
             view: Some(create_texture_view(graphics_provider,TextureViewConfig {
                 size,
                 render_attachment: true,
+                format: constants::INTERNAL_TEXTURE_FORMAT,
                 image_data: None,
+                mipmaps: None,
             })),
             local_data: None,
             policy_hint: StreamingHint::Static,
-            load_state: TextureLoadState::Loaded
+            load_state: TextureLoadState::Loaded,
+            last_touch: 0.0,
+            stream_generation: 0,
+            mipmaps: false,
         }
     }
 }
@@ -134,32 +156,52 @@ pub struct TextureViewConfig<'a> {
     size:               UWimpyPoint,
     /// Specify if this texture resource will ever be used as a render pass attachment.
     render_attachment:  bool,
-    image_data:         Option<WimpyImageData<'a>>
+    /// Compressed image data brings its own format, see `create_compressed_texture_view`
+    format:             TextureFormat,
+    image_data:         Option<WimpyImageData<'a>>,
+    /// Creates the full mip chain and fills it after the image data is written, if `MipmapGenerator::supports_format`.
+    mipmaps:            Option<&'a MipmapGenerator>,
 }
 
 fn create_texture_view(
     graphics_provider: &GraphicsProvider,
     config: TextureViewConfig,
 ) -> TextureView {
+    let TextureViewConfig { size: max_size, render_attachment, format, image_data, mipmaps } = config;
+
+    if let Some(WimpyImageData::Compressed { image }) = image_data {
+        return create_compressed_texture_view(graphics_provider,max_size,image,mipmaps);
+    }
+
+    let mipmaps = match mipmaps {
+        Some(_) if !MipmapGenerator::supports_format(format) => {
+            log::warn!("Mipmaps can't be generated for texture format {:?}, only the first level is created",format);
+            None
+        },
+        mipmaps => mipmaps,
+    };
 
     let mut usage_flags =
         TextureUsages::TEXTURE_BINDING |
         TextureUsages::COPY_DST |
         TextureUsages::COPY_SRC;
 
-    if config.render_attachment {
+    if render_attachment || mipmaps.is_some() {
         usage_flags |= TextureUsages::RENDER_ATTACHMENT;
     };
 
-    let max_size: UWimpyPoint = config.size;
+    let mip_level_count = match mipmaps {
+        Some(_) => MipmapGenerator::get_level_count(max_size),
+        None => 1,
+    };
 
     let texture = graphics_provider.get_device().create_texture(&wgpu::TextureDescriptor {
         size: max_size.into(),
-        mip_level_count: 1,
+        mip_level_count,
         sample_count: 1,
         dimension: wgpu::TextureDimension::D2,
 
-        format: constants::INTERNAL_TEXTURE_FORMAT,
+        format,
 
         usage: usage_flags,
         label: Some("Texture"),
@@ -168,7 +210,7 @@ fn create_texture_view(
 
     let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
 
-    if let Some(data) = config.image_data {
+    if let Some(data) = image_data {
         match data {
             WimpyImageData::Buffer { size, data } => {
 
@@ -195,12 +237,74 @@ fn create_texture_view(
             WimpyImageData::Custom { data } => {
                 data.write(graphics_provider.get_queue(),&texture,max_size);
             },
+            WimpyImageData::Compressed { .. } => unreachable!("compressed data is handled by create_compressed_texture_view"),
+        }
+        if let Some(generator) = mipmaps {
+            generator.generate(graphics_provider,&texture);
         }
     }
 
     view
 }
 
+/// Uploads the image with its own format and levels when the adapter allows it. Otherwise the first level is decoded to RGBA8,
+/// which can then get a generated mip chain like any other image. Signed formats decode to `Rgba8Snorm` and get no chain.
+///
+/// Compressed formats can't be rendered to, an uploaded image only has the levels stored in its container.
+///
+/// An image that can't be decoded makes an empty texture, `upload_wam_texture` checks for this ahead of time.
+fn create_compressed_texture_view(
+    graphics_provider: &GraphicsProvider,
+    size: UWimpyPoint,
+    image: CompressedImage,
+    mipmaps: Option<&MipmapGenerator>
+) -> TextureView {
+    if !image.can_upload(graphics_provider,size) {
+        let data = image.decode_rgba8();
+        if data.is_none() {
+            log::error!("No CPU decoder for texture format {:?}",image.format());
+        }
+        return create_texture_view(graphics_provider,TextureViewConfig {
+            size,
+            render_attachment: false,
+            format: image.get_decoded_format(),
+            image_data: data.as_deref().map(|data|WimpyImageData::Buffer { size: image.size(), data }),
+            mipmaps,
+        });
+    }
+
+    if mipmaps.is_some() && image.level_count() == 1 {
+        log::warn!("Compressed texture of format {:?} has no stored mip levels, store them in the container",image.format());
+    }
+
+    let texture = graphics_provider.get_device().create_texture(&wgpu::TextureDescriptor {
+        size: size.into(),
+        mip_level_count: image.level_count(),
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format: image.format(),
+        usage: TextureUsages::TEXTURE_BINDING | TextureUsages::COPY_DST | TextureUsages::COPY_SRC,
+        label: Some("Compressed Texture"),
+        view_formats: &[],
+    });
+    image.write(graphics_provider.get_queue(),&texture);
+
+    texture.create_view(&wgpu::TextureViewDescriptor::default())
+}
+
+/// Every level of the texture, in bytes
+fn get_texture_bytes(texture: &Texture) -> u64 {
+    let format = texture.format();
+    let (block_width,block_height) = format.block_dimensions();
+    let block_size = format.block_copy_size(None).unwrap_or(4) as u64;
+    let size = texture.size();
+    (0..texture.mip_level_count()).map(|level|{
+        let width = (size.width >> level).max(1).div_ceil(block_width) as u64;
+        let height = (size.height >> level).max(1).div_ceil(block_height) as u64;
+        width * height * block_size
+    }).sum()
+}
+
 pub struct TextureManager {
     pub cache:              TextureCache,
     /// Textures that are generated at runtime
@@ -217,12 +321,32 @@ pub struct TextureManager {
     id_generator:           BindGroupIdentityGenerator,
     update_queue:           Vec<UpdateOperation>,
     streaming_policy:       StreamingPolicy,
+    mipmap_generator:       MipmapGenerator,
+    stream_requests:        Vec<TextureStreamRequest>,
+    /// Seconds of `update` calls, the time base of texture time to live
+    stream_clock:           f64,
+    time_to_live:           f32,
+    /// In bytes, zero is unlimited
+    memory_budget:          u64,
+    /// Streamed textures that have a GPU view and can be evicted, with their size in bytes
+    resident:               SparseSecondaryMap<WimpyTextureKey,u64>,
+    resident_bytes:         u64,
+}
+
+/// A texture that was touched while unloaded. `WimpyAppContext::take_preload_requests` hands these to the platform as file loads.
+#[derive(Copy,Clone)]
+pub struct TextureStreamRequest {
+    pub texture:    WimpyTextureKey,
+    pub wam_key:    HardAssetKey,
+    pub generation: u8,
 }
 
 #[derive(Debug)]
 pub enum TextureManagerError {
     CacheFault(CacheArenaError<u32,WimpyTextureKey>),
-    NoAvailableView
+    NoAvailableView,
+    /// Compressed data the adapter can't sample and there is no CPU decoder for
+    UnsupportedFormat(TextureFormat),
 }
 
 enum UpdateOperation {
@@ -235,7 +359,9 @@ pub struct TextureAtlasConfig {
     /// How big a slot is (e.g., 16 pixels)
     pub slot_size: u32,
     /// How many slots are in the horizontal and vertical dimension (`number of slots` == `slot_length * slot_length`)
-    pub slot_length: u32
+    pub slot_length: u32,
+    /// Usually `INTERNAL_TEXTURE_FORMAT`. A compressed atlas only takes textures of the same format, and its slot size is rounded up to whole blocks.
+    pub format: TextureFormat,
 }
 
 /// A set of built in, always available texture assets. Note, however, they still require unwrapping.
@@ -309,16 +435,22 @@ impl RuntimeTextureGenerator<'_> {
         let view = create_texture_view(self.graphics_provider,TextureViewConfig {
             size,
             render_attachment: false,
+            format: constants::INTERNAL_TEXTURE_FORMAT,
             image_data: Some(WimpyImageData::Buffer { size, data }),
+            mipmaps: None,
         });
         let texture = WimpyTextureInternal {
             size_hint: size,
             wam_id: None,
+            wam_key: None,
             bind_group_id: self.id_generator.next(),
             view: Some(view),
             local_data: None,
             policy_hint: StreamingHint::Static,
-            load_state: TextureLoadState::Loaded
+            load_state: TextureLoadState::Loaded,
+            last_touch: 0.0,
+            stream_generation: 0,
+            mipmaps: false,
         };
         let key = self.texture_cache.insert_keyless(texture);
         WimpyTexture {
@@ -333,7 +465,8 @@ impl TextureManager {
     pub fn new(
         graphics_provider:  &GraphicsProvider,
         texture_layout:     BindGroupLayout,
-        streaming_policy:   StreamingPolicy
+        streaming_policy:   StreamingPolicy,
+        memory_budget:      u64
     ) -> Self {
         let mut cache = TextureCache::default();
         let mut id_generator = BindGroupIdentityGenerator::default();
@@ -345,7 +478,9 @@ impl TextureManager {
             let view = create_texture_view(graphics_provider,TextureViewConfig {
                 size,
                 render_attachment: false, //should probably be false copy to copy doesn't
+                format: constants::INTERNAL_TEXTURE_FORMAT,
                 image_data: Some(WimpyImageData::Buffer { size, data: missing_texture_data }),
+                mipmaps: None,
             });
             FallbackTexture {
                 id: id_generator.next(),
@@ -379,18 +514,29 @@ impl TextureManager {
             streaming_policy,
             bind_groups:        BindGroupCache::create(graphics_provider.get_device(),texture_layout),
             update_queue:       Vec::with_capacity(UPDATE_OPERATIONS_BUFFER_DEFAULT_SIZE),
+            mipmap_generator:   MipmapGenerator::create(graphics_provider.get_device()),
+            stream_requests:    Vec::with_capacity(STREAM_REQUESTS_BUFFER_DEFAULT_SIZE),
+            stream_clock:       0.0,
+            time_to_live:       DEFAULT_TIME_TO_LIVE_SECONDS,
+            memory_budget,
+            resident:           SparseSecondaryMap::new(),
+            resident_bytes:     0,
         }
     }
 
     pub fn bind_wam_asset(&mut self,parameters: TextureCreationParameters) -> WimpyTexture {
         let texture = WimpyTextureInternal {
-            size_hint:      parameters.size_hint,
-            wam_id:         Some(parameters.wam_id),
-            bind_group_id:  self.id_generator.next(),
-            view:           None,
-            local_data:     None,
-            policy_hint:    parameters.policy_hint,
-            load_state:     TextureLoadState::Unloaded,
+            size_hint:          parameters.size_hint,
+            wam_id:             Some(parameters.wam_id),
+            wam_key:            Some(parameters.wam_key),
+            bind_group_id:      self.id_generator.next(),
+            view:               None,
+            local_data:         None,
+            policy_hint:        parameters.policy_hint,
+            load_state:         TextureLoadState::Unloaded,
+            last_touch:         self.stream_clock,
+            stream_generation:  0,
+            mipmaps:            parameters.mipmaps || matches!(parameters.policy_hint,StreamingHint::Mipmapped),
         };
         let texture_key = self.cache.insert_keyless(texture);
         WimpyTexture {
@@ -400,21 +546,129 @@ impl TextureManager {
         }
     }
 
+    /// Creates the GPU resource for a texture from `bind_wam_asset` using image data loaded by the caller.
+    ///
+    /// The view is created at the texture's size hint, image data that disagrees with the hint is clipped.
+    /// Uploading to a texture that already has a view replaces it, the key stays valid.
+    pub fn upload_wam_texture(
+        &mut self,
+        graphics_provider: &GraphicsProvider,
+        texture_key: WimpyTextureKey,
+        image_data: WimpyImageData
+    ) -> Result<(),TextureManagerError> {
+        let texture = match self.cache.get_mut(texture_key) {
+            Ok(value) => value,
+            Err(error) => return Err(TextureManagerError::CacheFault(error)),
+        };
+        if let WimpyImageData::Compressed { image } = &image_data
+            && !image.can_upload(graphics_provider,texture.size_hint)
+            && !image.can_decode()
+        {
+            texture.load_state = TextureLoadState::Fallback;
+            return Err(TextureManagerError::UnsupportedFormat(image.format()));
+        }
+        let size = image_data.size();
+        if size != texture.size_hint {
+            log::warn!(
+                "Texture data size ({}x{}) does not match its size hint ({}x{})",
+                size.x,size.y,texture.size_hint.x,texture.size_hint.y
+            );
+        }
+        /* Bind groups are cached by identity, the old view stays bound unless the identity changes */
+        if texture.view.is_some() {
+            self.bind_groups.remove_identity(texture.bind_group_id);
+            texture.bind_group_id = self.id_generator.next();
+        }
+        texture.local_data = match (self.streaming_policy,&image_data) {
+            (StreamingPolicy::Retained,WimpyImageData::Buffer { size, data }) if *size == texture.size_hint => Some(data.to_vec()),
+            _ => None,
+        };
+        texture.view = Some(create_texture_view(graphics_provider,TextureViewConfig {
+            size: texture.size_hint,
+            render_attachment: false,
+            format: constants::INTERNAL_TEXTURE_FORMAT,
+            image_data: Some(image_data),
+            mipmaps: texture.mipmaps.then_some(&self.mipmap_generator),
+        }));
+        texture.load_state = TextureLoadState::Loaded;
+        texture.last_touch = self.stream_clock;
+        self.add_resident(texture_key);
+        Ok(())
+    }
+
+    /// `upload_wam_texture` for the result of a `TextureStreamRequest`. Returns `false` if the texture no longer wants this generation, such as when it was evicted and streamed again.
+    pub fn upload_streamed_texture(
+        &mut self,
+        graphics_provider: &GraphicsProvider,
+        texture_key: WimpyTextureKey,
+        generation: u8,
+        image_data: WimpyImageData
+    ) -> Result<bool,TextureManagerError> {
+        if !self.is_streaming(texture_key,generation) {
+            return Ok(false);
+        }
+        self.upload_wam_texture(graphics_provider,texture_key,image_data)?;
+        return Ok(true);
+    }
+
+    /// The load of a `TextureStreamRequest` failed. The texture draws as the missing texture and isn't streamed again until it is reloaded.
+    pub fn fail_stream(&mut self,texture_key: WimpyTextureKey,generation: u8) {
+        if !self.is_streaming(texture_key,generation) {
+            return;
+        }
+        if let Ok(texture) = self.cache.get_mut(texture_key) {
+            texture.load_state = TextureLoadState::Fallback;
+        }
+    }
+
+    fn is_streaming(&self,texture_key: WimpyTextureKey,generation: u8) -> bool {
+        matches!(
+            self.cache.get(texture_key),
+            Ok(WimpyTextureInternal { load_state: TextureLoadState::Loading { generation: current }, .. }) if *current == generation
+        )
+    }
+
+    /// Drops a texture from `bind_wam_asset` along with its GPU resource and the bind groups that use it. The key becomes stale.
+    pub fn remove_wam_texture(&mut self,texture_key: WimpyTextureKey) -> Result<(),TextureManagerError> {
+        match self.remove_texture(texture_key) {
+            Ok(()) => Ok(()),
+            Err(error) => Err(TextureManagerError::CacheFault(error)),
+        }
+    }
+
+    /// Drops any texture from the cache along with the bind groups that use it. The key becomes stale.
+    ///
+    /// Use this instead of removing from `cache` directly, cached bind groups hold a reference to the texture view and would keep it alive.
+    pub fn remove_texture(&mut self,texture_key: WimpyTextureKey) -> Result<(),TextureCacheError> {
+        let texture = self.cache.remove(texture_key)?;
+        self.bind_groups.remove_identity(texture.bind_group_id);
+        if let Some(bytes) = self.resident.remove(texture_key) {
+            self.resident_bytes -= bytes;
+        }
+        return Ok(());
+    }
+
     pub fn create_static_gpu_texture(&mut self,graphics_provider: &GraphicsProvider,image_data: WimpyImageData) -> WimpyTexture {
         let size = image_data.size();
         let texture_view = create_texture_view(graphics_provider,TextureViewConfig {
             size,
             render_attachment: false,
-            image_data: Some(image_data)
+            format: constants::INTERNAL_TEXTURE_FORMAT,
+            image_data: Some(image_data),
+            mipmaps: None
         });
         let texture = WimpyTextureInternal {
-            size_hint:      size,
-            wam_id:         None,
-            bind_group_id:  self.id_generator.next(),
-            view:           Some(texture_view),
-            local_data:     None,
-            policy_hint:    StreamingHint::Static,
-            load_state:     TextureLoadState::Loaded
+            size_hint:          size,
+            wam_id:             None,
+            wam_key:            None,
+            bind_group_id:      self.id_generator.next(),
+            view:               Some(texture_view),
+            local_data:         None,
+            policy_hint:        StreamingHint::Static,
+            load_state:         TextureLoadState::Loaded,
+            last_touch:         0.0,
+            stream_generation:  0,
+            mipmaps:            false,
         };
         let texture_key = self.cache.insert_keyless(texture);
         WimpyTexture {
@@ -437,12 +691,152 @@ impl TextureManager {
         self.update_queue.len() > 0
     }
 
-    pub fn update<IO: WimpyIO>(&mut self) {
-        for update in self.update_queue.drain(..) {
-            todo!();
+    /// Called once per frame. Applies touches, queues stream requests for touched textures that are unloaded,
+    /// then evicts streamed textures that outlived their time to live or don't fit the memory budget.
+    ///
+    /// Evicted textures go back to `TextureLoadState::Unloaded` and stream again on their next touch.
+    pub fn update(&mut self,graphics_provider: &GraphicsProvider,delta_seconds: f32) {
+        self.stream_clock += delta_seconds as f64;
+        let mut update_queue = std::mem::take(&mut self.update_queue);
+        for update in update_queue.drain(..) {
+            match update {
+                UpdateOperation::Touch(texture_key) => self.apply_touch(graphics_provider,texture_key),
+                /* Not queued yet, see `copy_texture_to_texture` */
+                UpdateOperation::CopyTextureToTexture(_) => {},
+            }
+        }
+        self.update_queue = update_queue;
+        self.evict_expired();
+        self.evict_over_budget();
+    }
+
+    fn apply_touch(&mut self,graphics_provider: &GraphicsProvider,texture_key: WimpyTextureKey) {
+        let Ok(texture) = self.cache.get_mut(texture_key) else {
+            return;
+        };
+        texture.last_touch = self.stream_clock;
+        let (TextureLoadState::Unloaded,Some(wam_key)) = (texture.load_state,texture.wam_key) else {
+            return;
+        };
+        if let Some(data) = &texture.local_data {
+            texture.view = Some(create_texture_view(graphics_provider,TextureViewConfig {
+                size: texture.size_hint,
+                render_attachment: false,
+                format: constants::INTERNAL_TEXTURE_FORMAT,
+                image_data: Some(WimpyImageData::Buffer { size: texture.size_hint, data }),
+                mipmaps: texture.mipmaps.then_some(&self.mipmap_generator),
+            }));
+            texture.load_state = TextureLoadState::Loaded;
+            self.add_resident(texture_key);
+            return;
+        }
+        let generation = texture.stream_generation.wrapping_add(1);
+        texture.stream_generation = generation;
+        texture.load_state = TextureLoadState::Loading { generation };
+        self.stream_requests.push(TextureStreamRequest {
+            texture: texture_key,
+            wam_key,
+            generation
+        });
+    }
+
+    /// Textures made with `StreamingHint::Static` or under `StreamingPolicy::StaticGPU` are never evicted.
+    ///
+    /// A texture that is already resident had its view replaced, its old size is swapped for the new one.
+    fn add_resident(&mut self,texture_key: WimpyTextureKey) {
+        let Ok(texture) = self.cache.get(texture_key) else {
+            return;
+        };
+        let evictable = texture.wam_key.is_some() &&
+            !matches!(texture.policy_hint,StreamingHint::Static) &&
+            !matches!(self.streaming_policy,StreamingPolicy::StaticGPU);
+        if !evictable {
+            return;
+        }
+        let Some(view) = &texture.view else {
+            return;
+        };
+        let bytes = get_texture_bytes(view.texture());
+        if let Some(old_bytes) = self.resident.insert(texture_key,bytes) {
+            self.resident_bytes -= old_bytes;
+        }
+        self.resident_bytes += bytes;
+    }
+
+    fn evict(&mut self,texture_key: WimpyTextureKey) {
+        if let Some(bytes) = self.resident.remove(texture_key) {
+            self.resident_bytes -= bytes;
+        }
+        let Ok(texture) = self.cache.get_mut(texture_key) else {
+            return;
+        };
+        self.bind_groups.remove_identity(texture.bind_group_id);
+        texture.view = None;
+        texture.load_state = TextureLoadState::Unloaded;
+    }
+
+    fn evict_expired(&mut self) {
+        let oldest_touch = self.stream_clock - self.time_to_live as f64;
+        let expired: Vec<WimpyTextureKey> = self.resident.keys().filter(|key|{
+            self.cache.get(*key).is_ok_and(|texture|texture.last_touch < oldest_touch)
+        }).collect();
+        for texture_key in expired {
+            self.evict(texture_key);
+        }
+    }
+
+    /// Least recently touched first. Textures touched this frame are kept even if that breaks the budget.
+    fn evict_over_budget(&mut self) {
+        if self.memory_budget == 0 || self.resident_bytes <= self.memory_budget {
+            return;
+        }
+        let mut candidates: Vec<(f64,WimpyTextureKey)> = self.resident.keys().filter_map(|key|{
+            let texture = self.cache.get(key).ok()?;
+            (texture.last_touch < self.stream_clock).then_some((texture.last_touch,key))
+        }).collect();
+        candidates.sort_by(|a,b|a.0.total_cmp(&b.0));
+        for (_,texture_key) in candidates {
+            if self.resident_bytes <= self.memory_budget {
+                break;
+            }
+            self.evict(texture_key);
+        }
+    }
+
+    /// Requests are handed out in the order they were touched
+    pub fn take_stream_requests(&mut self,limit: usize) -> Vec<TextureStreamRequest> {
+        let count = limit.min(self.stream_requests.len());
+        self.stream_requests.drain(..count).collect()
+    }
+
+    /// `Fallback` if the key is stale
+    pub fn get_load_state(&self,texture_key: WimpyTextureKey) -> TextureLoadState {
+        match self.cache.get(texture_key) {
+            Ok(texture) => texture.load_state,
+            Err(_) => TextureLoadState::Fallback,
         }
     }
 
+    /// Bind groups in the cache. Should stay close to the number of textures drawn recently, growth points to a leak.
+    pub fn get_live_bind_group_count(&self) -> usize {
+        self.bind_groups.get_live_count()
+    }
+
+    /// GPU memory held by textures that can be evicted, in bytes
+    pub fn get_resident_bytes(&self) -> u64 {
+        self.resident_bytes
+    }
+
+    /// Zero is unlimited, takes effect on the next `update`
+    pub fn set_memory_budget(&mut self,bytes: u64) {
+        self.memory_budget = bytes;
+    }
+
+    /// Streamed textures that go this long without a touch are evicted
+    pub fn set_time_to_live(&mut self,seconds: f32) {
+        self.time_to_live = seconds;
+    }
+
     pub fn create_atlas(
         &mut self,
         graphics_provider: &GraphicsProvider,
@@ -451,32 +845,24 @@ impl TextureManager {
 
         //TODO: validate size with graphics provider
 
-        let length = config.slot_size * config.slot_length;
-        let size: UWimpyPoint = length.into();
-
-        let texture_view = create_texture_view(graphics_provider,TextureViewConfig {
-            size,
-            render_attachment: false,
-            image_data: None // should we create a blank texture first ?
-        });
-
-        let bind_group_id: BindGroupIdentity = self.id_generator.next();
+        /* Copies into a compressed atlas have to start on a block */
+        let (block_width,block_height) = config.format.block_dimensions();
+        let mut block_length = block_width;
+        while block_length % block_height != 0 {
+            block_length += block_width;
+        }
+        let slot_size = config.slot_size.next_multiple_of(block_length);
+        if slot_size != config.slot_size {
+            log::warn!("Atlas slot size '{}' rounded up to '{}' to fit the blocks of {:?}",config.slot_size,slot_size,config.format);
+        }
 
-        let texture = WimpyTextureInternal {
-            size_hint:      size,
-            wam_id:         None,
-            bind_group_id:  bind_group_id,
-            view:           Some(texture_view),
-            local_data:     None,
-            policy_hint:    StreamingHint::Static,
-            load_state:     TextureLoadState::Loaded
-        };
+        let length = slot_size * config.slot_length;
+        let (texture_key,bind_group_id) = self.create_atlas_texture(graphics_provider,length.into(),config.format,false);
 
-        let texture_key: WimpyTextureKey = self.cache.insert_keyless(texture);
         //todo: validate size with graphics provider
         let atlas = TextureAtlas::new(
             config.slot_length,
-            config.slot_size,
+            slot_size,
             texture_key,
             bind_group_id
         );
@@ -484,6 +870,41 @@ impl TextureManager {
         return atlas;
     }
 
+    /// A static texture without contents that atlases copy into. `render_attachment` lets it be cleared by a render pass.
+    pub(super) fn create_atlas_texture(
+        &mut self,
+        graphics_provider: &GraphicsProvider,
+        size: UWimpyPoint,
+        format: TextureFormat,
+        render_attachment: bool
+    ) -> (WimpyTextureKey,BindGroupIdentity) {
+        let texture_view = create_texture_view(graphics_provider,TextureViewConfig {
+            size,
+            render_attachment,
+            format,
+            image_data: None, // should we create a blank texture first ?
+            mipmaps: None
+        });
+
+        let bind_group_id: BindGroupIdentity = self.id_generator.next();
+
+        let texture = WimpyTextureInternal {
+            size_hint:          size,
+            wam_id:             None,
+            wam_key:            None,
+            bind_group_id:      bind_group_id,
+            view:               Some(texture_view),
+            local_data:         None,
+            policy_hint:        StreamingHint::Static,
+            load_state:         TextureLoadState::Loaded,
+            last_touch:         0.0,
+            stream_generation:  0,
+            mipmaps:            false,
+        };
+
+        return (self.cache.insert_keyless(texture),bind_group_id);
+    }
+
     pub fn create_keyless_render_target(
         &mut self,
         graphics_provider: &GraphicsProvider,
@@ -535,11 +956,15 @@ impl TextureManager {
         let texture = WimpyTextureInternal {
             size_hint: surface.texture.size().into(),
             wam_id: None,
+            wam_key: None,
             bind_group_id: BindGroupIdentity::Anonymous,
             view: Some(texture_view),
             local_data: None,
             policy_hint: StreamingHint::Static,
-            load_state:  TextureLoadState::Loaded
+            load_state:  TextureLoadState::Loaded,
+            last_touch: 0.0,
+            stream_generation: 0,
+            mipmaps: false,
         };
         self.cache.insert_keyless(texture)
     }
@@ -551,6 +976,7 @@ impl TextureManager {
             key: key, 
             view: &self.fallback_texture.view,
             load_state: TextureLoadState::Fallback,
+            bind_group_id: self.fallback_texture.id,
         }
     }
 
@@ -559,12 +985,13 @@ impl TextureManager {
     /// Note: Multiple disjoint texture entries can be obtained because `self` is not `mut`.
     pub fn get_no_touch<'a>(&'a self,key: WimpyTextureKey) -> Result<TextureCacheEntry<'a>,TextureManagerError> {
         match self.cache.get(key) {
-            Ok(WimpyTextureInternal { view: Some(view), size_hint, load_state, .. }) => {
+            Ok(WimpyTextureInternal { view: Some(view), size_hint, load_state, bind_group_id, .. }) => {
                 Ok(TextureCacheEntry {
                     input_size: *size_hint,
                     key,
                     view,
-                    load_state: *load_state
+                    load_state: *load_state,
+                    bind_group_id: *bind_group_id
                 })
             },
             Ok(WimpyTextureInternal { view: None, .. }) => {
@@ -592,7 +1019,7 @@ impl TextureManager {
         atlas.set_texture(self,key)
     }
 
-    // TODO: Bind group cache entries much be attached to their GPU textures otherwise they will leak GPU resources when dropping textures is intended
+    /// Cached until the texture is removed or evicted, see `remove_texture`
     pub fn get_bind_group_single_channel<'a>(&'a mut self,device: &Device,channel: BindGroupChannelConfig) -> &'a BindGroup {
         let (texture_view, id): (&TextureView, BindGroupIdentity) = match self.cache.get(channel.texture_key) {
             Ok(WimpyTextureInternal { view: Some(view), bind_group_id, .. }) => (view,*bind_group_id),
@@ -7,7 +7,7 @@ mod constants {
 }
 
 mod input_manager;
-pub use input_manager::InputManager;
+pub use input_manager::{InputManager, KeyBindImportError};
 
 mod gamepad;
 pub use gamepad::*;
@@ -21,7 +21,7 @@ pub use keyboard::*;
 mod mouse;
 pub use mouse::*;
 
-// These enum integer values need to freeze once key binds are saved into a file. We aren't there yet.
+// These enum integer values are written into saved key binds. They are frozen: never reorder, renumber, or reuse them.
 #[derive(Clone,Copy,PartialEq,Eq,Debug)]
 pub enum Impulse {
     Up = 0,
@@ -38,6 +38,7 @@ pub enum Impulse {
     Menu = 11
 }
 
+/// Every impulse, indexed by its integer value
 pub const IMPULSES: [Impulse;12] = [
     Impulse::Up,
     Impulse::Down,
@@ -53,7 +54,19 @@ pub const IMPULSES: [Impulse;12] = [
     Impulse::Menu
 ];
 
+const _: () = {
+    let mut i = 0;
+    while i < IMPULSES.len() {
+        assert!(IMPULSES[i] as usize == i);
+        i += 1;
+    }
+};
+
 impl Impulse {
+    pub fn from_id(id: u8) -> Option<Impulse> {
+        IMPULSES.get(id as usize).copied()
+    }
+
     pub fn direction(&self) -> Direction {
         match self {
             Impulse::Up =>      Direction::Up,
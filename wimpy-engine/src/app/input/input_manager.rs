@@ -81,6 +81,14 @@ pub use app_shell_controller::*;
 
 mod key_rebind_controller {
     use super::*;
+    use crate::app::kvs::{KeyValueStore, KeyValueStoreError};
+
+    #[derive(Debug)]
+    pub enum KeyBindImportError {
+        StoreError(KeyValueStoreError),
+        DecodeError(KeyBindDecodeError),
+    }
+
     impl InputManager {
         pub fn clear_captured_key_code(&mut self) {
             self.captured_key_code = None;
@@ -109,6 +117,35 @@ mod key_rebind_controller {
             self.keyboard_translator.clear_all_key_binds();
             self.keyboard_state.release_all();
         }
+
+        /// Restores the binds that `KeyboardTranslator::default()` sets up
+        pub fn reset_key_binds(&mut self) {
+            self.keyboard_translator = KeyboardTranslator::default();
+            self.keyboard_state.release_all();
+        }
+
+        pub fn iter_key_binds(&self) -> impl Iterator<Item = (KeyCode,Impulse)> {
+            self.keyboard_translator.iter_binds()
+        }
+
+        /// Writes the current binds into the store. The store still needs to be exported to reach storage.
+        pub fn export_key_binds(&self,store: &mut KeyValueStore) {
+            store.set_bytes(KEY_BINDS_STORAGE_KEY,&self.keyboard_translator.serialize());
+        }
+
+        /// Replaces the current binds with the binds in the store. The current binds are kept if the stored binds are missing or invalid.
+        pub fn import_key_binds(&mut self,store: &KeyValueStore) -> Result<(),KeyBindImportError> {
+            let data = match store.get_bytes(KEY_BINDS_STORAGE_KEY) {
+                Ok(value) => value,
+                Err(error) => return Err(KeyBindImportError::StoreError(error)),
+            };
+            self.keyboard_translator = match KeyboardTranslator::deserialize(data) {
+                Ok(value) => value,
+                Err(error) => return Err(KeyBindImportError::DecodeError(error)),
+            };
+            self.keyboard_state.release_all();
+            Ok(())
+        }
     }
 }
 
@@ -1,137 +1,278 @@
+/// Integer values are written into saved key binds. They are frozen: never reorder, renumber, or reuse them. Only append.
 #[repr(u8)]
 #[derive(PartialEq,Eq,Copy,Clone,Hash,Debug)]
 pub enum KeyCode{
     Unknown = 0,
 
     // Letters
-    KeyA,
-    KeyB,
-    KeyC,
-    KeyD,
-    KeyE,
-    KeyF,
-    KeyG,
-    KeyH,
-    KeyI,
-    KeyJ,
-    KeyK,
-    KeyL,
-    KeyM,
-    KeyN,
-    KeyO,
-    KeyP,
-    KeyQ,
-    KeyR,
-    KeyS,
-    KeyT,
-    KeyU,
-    KeyV,
-    KeyW,
-    KeyX,
-    KeyY,
-    KeyZ,
+    KeyA = 1,
+    KeyB = 2,
+    KeyC = 3,
+    KeyD = 4,
+    KeyE = 5,
+    KeyF = 6,
+    KeyG = 7,
+    KeyH = 8,
+    KeyI = 9,
+    KeyJ = 10,
+    KeyK = 11,
+    KeyL = 12,
+    KeyM = 13,
+    KeyN = 14,
+    KeyO = 15,
+    KeyP = 16,
+    KeyQ = 17,
+    KeyR = 18,
+    KeyS = 19,
+    KeyT = 20,
+    KeyU = 21,
+    KeyV = 22,
+    KeyW = 23,
+    KeyX = 24,
+    KeyY = 25,
+    KeyZ = 26,
 
     // Digits
-    Digit0,
-    Digit1,
-    Digit2,
-    Digit3,
-    Digit4,
-    Digit5,
-    Digit6,
-    Digit7,
-    Digit8,
-    Digit9,
+    Digit0 = 27,
+    Digit1 = 28,
+    Digit2 = 29,
+    Digit3 = 30,
+    Digit4 = 31,
+    Digit5 = 32,
+    Digit6 = 33,
+    Digit7 = 34,
+    Digit8 = 35,
+    Digit9 = 36,
 
     // Arrows
-    ArrowDown,
-    ArrowLeft,
-    ArrowRight,
-    ArrowUp,
+    ArrowDown = 37,
+    ArrowLeft = 38,
+    ArrowRight = 39,
+    ArrowUp = 40,
 
     // Other
-    Escape,
-    Space,
-    Tab,
-    Minus,
-    Equal,
-    BracketLeft,
-    BracketRight,
-    Backslash,
-    Backquote,
-    Semicolon,
-    Quote,
-    Comma,
-    Period,
-    Slash,
-    AltLeft,
-    AltRight,
-    ControlLeft,
-    ControlRight,
-    ShiftLeft,
-    ShiftRight,
-    Enter,
-    Backspace,
-    MetaLeft,
-    MetaRight,
-    ContextMenu,
-    CapsLock,
-    ScrollLock,
-    NumLock,
-    Pause,
-    PrintScreen,
-    Delete,
-    End,
-    Help,
-    Home,
-    Insert,
-    PageDown,
-    PageUp,
+    Escape = 41,
+    Space = 42,
+    Tab = 43,
+    Minus = 44,
+    Equal = 45,
+    BracketLeft = 46,
+    BracketRight = 47,
+    Backslash = 48,
+    Backquote = 49,
+    Semicolon = 50,
+    Quote = 51,
+    Comma = 52,
+    Period = 53,
+    Slash = 54,
+    AltLeft = 55,
+    AltRight = 56,
+    ControlLeft = 57,
+    ControlRight = 58,
+    ShiftLeft = 59,
+    ShiftRight = 60,
+    Enter = 61,
+    Backspace = 62,
+    MetaLeft = 63,
+    MetaRight = 64,
+    ContextMenu = 65,
+    CapsLock = 66,
+    ScrollLock = 67,
+    NumLock = 68,
+    Pause = 69,
+    PrintScreen = 70,
+    Delete = 71,
+    End = 72,
+    Help = 73,
+    Home = 74,
+    Insert = 75,
+    PageDown = 76,
+    PageUp = 77,
 
     // Function
-    F1,
-    F2,
-    F3,
-    F4,
-    F5,
-    F6,
-    F7,
-    F8,
-    F9,
-    F10,
-    F11,
-    F12,
+    F1 = 78,
+    F2 = 79,
+    F3 = 80,
+    F4 = 81,
+    F5 = 82,
+    F6 = 83,
+    F7 = 84,
+    F8 = 85,
+    F9 = 86,
+    F10 = 87,
+    F11 = 88,
+    F12 = 89,
 
     // Numpad numbers
-    Numpad0,
-    Numpad1,
-    Numpad2,
-    Numpad3,
-    Numpad4,
-    Numpad5,
-    Numpad6,
-    Numpad7,
-    Numpad8,
-    Numpad9,
+    Numpad0 = 90,
+    Numpad1 = 91,
+    Numpad2 = 92,
+    Numpad3 = 93,
+    Numpad4 = 94,
+    Numpad5 = 95,
+    Numpad6 = 96,
+    Numpad7 = 97,
+    Numpad8 = 98,
+    Numpad9 = 99,
 
     // Numpad others
-    NumpadAdd,
-    NumpadSubtract,
-    NumpadMultiply,
-    NumpadDivide,
-    NumpadDecimal,
-    NumpadComma,
-    NumpadBackspace,
-    NumpadClear,
-    NumpadClearEntry,
-    NumpadEnter,
-    NumpadEqual,
-    NumpadHash,
-    NumpadParenLeft,
-    NumpadParenRight,
-    NumpadMemoryAdd,
-    NumpadMemorySubtract,
-    NumpadMemoryClear,
-    NumpadMemoryStore,
-    NumpadMemoryRecall,
+    NumpadAdd = 100,
+    NumpadSubtract = 101,
+    NumpadMultiply = 102,
+    NumpadDivide = 103,
+    NumpadDecimal = 104,
+    NumpadComma = 105,
+    NumpadBackspace = 106,
+    NumpadClear = 107,
+    NumpadClearEntry = 108,
+    NumpadEnter = 109,
+    NumpadEqual = 110,
+    NumpadHash = 111,
+    NumpadParenLeft = 112,
+    NumpadParenRight = 113,
+    NumpadMemoryAdd = 114,
+    NumpadMemorySubtract = 115,
+    NumpadMemoryClear = 116,
+    NumpadMemoryStore = 117,
+    NumpadMemoryRecall = 118,
+}
+
+pub const KEY_CODE_COUNT: usize = 119;
+
+/// Every key code, indexed by its integer value
+pub const ALL_KEY_CODES: [KeyCode;KEY_CODE_COUNT] = [
+    KeyCode::Unknown,
+    KeyCode::KeyA,
+    KeyCode::KeyB,
+    KeyCode::KeyC,
+    KeyCode::KeyD,
+    KeyCode::KeyE,
+    KeyCode::KeyF,
+    KeyCode::KeyG,
+    KeyCode::KeyH,
+    KeyCode::KeyI,
+    KeyCode::KeyJ,
+    KeyCode::KeyK,
+    KeyCode::KeyL,
+    KeyCode::KeyM,
+    KeyCode::KeyN,
+    KeyCode::KeyO,
+    KeyCode::KeyP,
+    KeyCode::KeyQ,
+    KeyCode::KeyR,
+    KeyCode::KeyS,
+    KeyCode::KeyT,
+    KeyCode::KeyU,
+    KeyCode::KeyV,
+    KeyCode::KeyW,
+    KeyCode::KeyX,
+    KeyCode::KeyY,
+    KeyCode::KeyZ,
+    KeyCode::Digit0,
+    KeyCode::Digit1,
+    KeyCode::Digit2,
+    KeyCode::Digit3,
+    KeyCode::Digit4,
+    KeyCode::Digit5,
+    KeyCode::Digit6,
+    KeyCode::Digit7,
+    KeyCode::Digit8,
+    KeyCode::Digit9,
+    KeyCode::ArrowDown,
+    KeyCode::ArrowLeft,
+    KeyCode::ArrowRight,
+    KeyCode::ArrowUp,
+    KeyCode::Escape,
+    KeyCode::Space,
+    KeyCode::Tab,
+    KeyCode::Minus,
+    KeyCode::Equal,
+    KeyCode::BracketLeft,
+    KeyCode::BracketRight,
+    KeyCode::Backslash,
+    KeyCode::Backquote,
+    KeyCode::Semicolon,
+    KeyCode::Quote,
+    KeyCode::Comma,
+    KeyCode::Period,
+    KeyCode::Slash,
+    KeyCode::AltLeft,
+    KeyCode::AltRight,
+    KeyCode::ControlLeft,
+    KeyCode::ControlRight,
+    KeyCode::ShiftLeft,
+    KeyCode::ShiftRight,
+    KeyCode::Enter,
+    KeyCode::Backspace,
+    KeyCode::MetaLeft,
+    KeyCode::MetaRight,
+    KeyCode::ContextMenu,
+    KeyCode::CapsLock,
+    KeyCode::ScrollLock,
+    KeyCode::NumLock,
+    KeyCode::Pause,
+    KeyCode::PrintScreen,
+    KeyCode::Delete,
+    KeyCode::End,
+    KeyCode::Help,
+    KeyCode::Home,
+    KeyCode::Insert,
+    KeyCode::PageDown,
+    KeyCode::PageUp,
+    KeyCode::F1,
+    KeyCode::F2,
+    KeyCode::F3,
+    KeyCode::F4,
+    KeyCode::F5,
+    KeyCode::F6,
+    KeyCode::F7,
+    KeyCode::F8,
+    KeyCode::F9,
+    KeyCode::F10,
+    KeyCode::F11,
+    KeyCode::F12,
+    KeyCode::Numpad0,
+    KeyCode::Numpad1,
+    KeyCode::Numpad2,
+    KeyCode::Numpad3,
+    KeyCode::Numpad4,
+    KeyCode::Numpad5,
+    KeyCode::Numpad6,
+    KeyCode::Numpad7,
+    KeyCode::Numpad8,
+    KeyCode::Numpad9,
+    KeyCode::NumpadAdd,
+    KeyCode::NumpadSubtract,
+    KeyCode::NumpadMultiply,
+    KeyCode::NumpadDivide,
+    KeyCode::NumpadDecimal,
+    KeyCode::NumpadComma,
+    KeyCode::NumpadBackspace,
+    KeyCode::NumpadClear,
+    KeyCode::NumpadClearEntry,
+    KeyCode::NumpadEnter,
+    KeyCode::NumpadEqual,
+    KeyCode::NumpadHash,
+    KeyCode::NumpadParenLeft,
+    KeyCode::NumpadParenRight,
+    KeyCode::NumpadMemoryAdd,
+    KeyCode::NumpadMemorySubtract,
+    KeyCode::NumpadMemoryClear,
+    KeyCode::NumpadMemoryStore,
+    KeyCode::NumpadMemoryRecall,
+];
+
+/* The table must stay in sync with the enum, otherwise saved key binds resolve to the wrong keys */
+const _: () = {
+    let mut i = 0;
+    while i < KEY_CODE_COUNT {
+        assert!(ALL_KEY_CODES[i] as usize == i);
+        i += 1;
+    }
+};
+
+impl KeyCode {
+    pub fn from_id(id: u8) -> Option<KeyCode> {
+        ALL_KEY_CODES.get(id as usize).copied()
+    }
 }
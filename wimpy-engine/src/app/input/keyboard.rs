@@ -1,6 +1,27 @@
+/// The key value store entry that holds the player's key binds
+pub const KEY_BINDS_STORAGE_KEY: &'static str = "wimpy/input/key-binds";
+
+/*
+    Serialized key binds
+
+    version         u8
+    binds           [key code id: u8, impulse id: u8] (repeated until the end of the data)
+*/
+const KEY_BINDS_FORMAT_VERSION: u8 = 1;
+const KEY_BIND_SIZE: usize = 2;
+
 use std::collections::{HashMap, HashSet};
 use super::*;
 
+#[derive(Debug)]
+pub enum KeyBindDecodeError {
+    Empty,
+    UnsupportedVersion(u8),
+    InvalidLength(usize),
+    InvalidKeyCode(u8),
+    InvalidImpulse(u8),
+}
+
 pub struct KeyboardTranslator {
     binds: HashMap<KeyCode,Impulse>,
     reverse_lookup: [HashSet<KeyCode>;super::constants::IMPULSE_COUNT]
@@ -8,7 +29,12 @@ pub struct KeyboardTranslator {
 
 impl KeyboardTranslator {
     pub fn add_key_bind(&mut self,key_code: KeyCode,impulse: Impulse) {
-        self.binds.insert(key_code,impulse);
+        if
+            let Some(old_impulse) = self.binds.insert(key_code,impulse) &&
+            let Some(bind_set) = self.reverse_lookup.get_mut(old_impulse as usize)
+        {
+            bind_set.remove(&key_code);
+        }
         if let Some(bind_set) = self.reverse_lookup.get_mut(impulse as usize) {
             bind_set.insert(key_code);
         } else {
@@ -28,9 +54,9 @@ impl KeyboardTranslator {
     }
 
     pub fn remove_binds_for_impulse(&mut self,impulse: Impulse) {
-        if let Some(bind_set) = self.reverse_lookup.get(impulse as usize) {
-            for key_code in bind_set {
-                self.binds.remove(key_code);
+        if let Some(bind_set) = self.reverse_lookup.get_mut(impulse as usize) {
+            for key_code in bind_set.drain() {
+                self.binds.remove(&key_code);
             }
         } else {
             log::warn!("Missing reverse lookup set for impulse!");
@@ -44,6 +70,55 @@ impl KeyboardTranslator {
         }
     }
 
+    fn empty() -> Self {
+        Self {
+            binds: HashMap::<KeyCode,Impulse>::with_capacity(super::constants::IMPULSE_COUNT * 2),
+            reverse_lookup: std::array::from_fn(|_|HashSet::with_capacity(4))
+        }
+    }
+
+    /// Binds are sorted by key code so the same binds always produce the same bytes
+    pub fn serialize(&self) -> Vec<u8> {
+        let mut binds: Vec<(KeyCode,Impulse)> = self.binds.iter().map(|(key_code,impulse)|(*key_code,*impulse)).collect();
+        binds.sort_unstable_by_key(|(key_code,_)|*key_code as u8);
+
+        let mut data = Vec::with_capacity(1 + binds.len() * KEY_BIND_SIZE);
+        data.push(KEY_BINDS_FORMAT_VERSION);
+        for (key_code,impulse) in binds {
+            data.push(key_code as u8);
+            data.push(impulse as u8);
+        }
+        data
+    }
+
+    pub fn deserialize(data: &[u8]) -> Result<Self,KeyBindDecodeError> {
+        let Some((version,binds)) = data.split_first() else {
+            return Err(KeyBindDecodeError::Empty);
+        };
+        if *version != KEY_BINDS_FORMAT_VERSION {
+            return Err(KeyBindDecodeError::UnsupportedVersion(*version));
+        }
+        if binds.len() % KEY_BIND_SIZE != 0 {
+            return Err(KeyBindDecodeError::InvalidLength(data.len()));
+        }
+
+        let mut translator = Self::empty();
+        for bind in binds.chunks_exact(KEY_BIND_SIZE) {
+            let Some(key_code) = KeyCode::from_id(bind[0]) else {
+                return Err(KeyBindDecodeError::InvalidKeyCode(bind[0]));
+            };
+            let Some(impulse) = Impulse::from_id(bind[1]) else {
+                return Err(KeyBindDecodeError::InvalidImpulse(bind[1]));
+            };
+            translator.add_key_bind(key_code,impulse);
+        }
+        Ok(translator)
+    }
+
+    pub fn iter_binds(&self) -> impl Iterator<Item = (KeyCode,Impulse)> {
+        self.binds.iter().map(|(key_code,impulse)|(*key_code,*impulse))
+    }
+
     pub fn translate(&self,keyboard_state: &KeyboardState) -> ImpulseSet {
         let mut impulse_set = ImpulseSet::default();
         for (key_code,impulse) in self.binds.iter() {
@@ -58,10 +133,7 @@ impl KeyboardTranslator {
 /* Default key binds defined by the engine */
 impl Default for KeyboardTranslator {
     fn default() -> Self {
-        let mut translator = Self {
-            binds: HashMap::<KeyCode,Impulse>::with_capacity(super::constants::IMPULSE_COUNT * 2),
-            reverse_lookup: std::array::from_fn(|_|HashSet::with_capacity(4))
-        };
+        let mut translator = Self::empty();
 
         translator.add_key_bind(KeyCode::KeyW,Impulse::Up);
         translator.add_key_bind(KeyCode::KeyS,Impulse::Down);
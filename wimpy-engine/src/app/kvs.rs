@@ -1,58 +1,445 @@
-use crate::app::WimpyIO;
+use std::collections::HashMap;
+use crate::app::{WimpyIO, FileError};
+
+mod binary_format;
+
+mod save_slots;
+pub use save_slots::*;
+
+mod migrations;
+pub use migrations::*;
+
+mod autosave;
+pub use autosave::AutosavePolicy;
+use autosave::AutosaveState;
+
+const DEFAULT_STORE_CAPACITY: usize = 32;
 
-#[derive(Default)]
 pub struct KeyValueStore {
-    //todo... dictionary structure
+    values:         HashMap<String,StorageValue>,
+    schema_version: u32,
+    migrations:     KeyValueStoreMigrations,
+    /// Incremented on every change, see `KeyValueStore::changed_since`
+    revision:       u64,
+    /// The revision of the last change to each key, including keys deleted since the last flush
+    changes:        HashMap<String,u64>,
+    autosave:       AutosaveState,
 }
 
-pub enum StorageValueType {
-    String,
-    Integer,
+impl Default for KeyValueStore {
+    fn default() -> Self {
+        Self::with_migrations(Default::default())
+    }
+}
+
+#[derive(Debug,Clone,PartialEq)]
+pub enum StorageValue {
+    String(String),
+    Integer(u32),
     Flag,
+    Float(f32),
+    Bytes(Vec<u8>),
 }
 
-pub enum KeyValueStoreError {
+/// Type tags are written into the binary format. Existing values must never be changed.
+#[repr(u8)]
+#[derive(Debug,Copy,Clone,PartialEq,Eq)]
+pub enum StorageValueType {
+    String = 0,
+    Integer = 1,
+    Flag = 2,
+    Float = 3,
+    Bytes = 4,
+}
+
+impl StorageValue {
+    pub fn value_type(&self) -> StorageValueType {
+        match self {
+            StorageValue::String(_) =>  StorageValueType::String,
+            StorageValue::Integer(_) => StorageValueType::Integer,
+            StorageValue::Flag =>       StorageValueType::Flag,
+            StorageValue::Float(_) =>   StorageValueType::Float,
+            StorageValue::Bytes(_) =>   StorageValueType::Bytes,
+        }
+    }
+}
 
+impl TryFrom<u8> for StorageValueType {
+    type Error = KeyValueStoreError;
+
+    fn try_from(value: u8) -> Result<Self,Self::Error> {
+        Ok(match value {
+            0 => StorageValueType::String,
+            1 => StorageValueType::Integer,
+            2 => StorageValueType::Flag,
+            3 => StorageValueType::Float,
+            4 => StorageValueType::Bytes,
+            _ => return Err(KeyValueStoreError::InvalidValueType(value))
+        })
+    }
+}
+
+#[derive(Debug)]
+pub enum KeyValueStoreError {
+    KeyNotFound(String),
+    MismatchedType {
+        key: String,
+        expected: StorageValueType,
+        found: StorageValueType
+    },
+    /// The data does not start with the key value store magic number
+    InvalidHeader,
+    UnsupportedVersion(u16),
+    /// The data ended before the format said it would (e.g., a partial write)
+    UnexpectedEndOfData,
+    InvalidValueType(u8),
+    InvalidKey,
+    InvalidString {
+        key: String
+    },
+    DuplicateKey(String),
+    TooManyEntries(usize),
+    KeyTooLong(usize),
+    ValueTooLong {
+        key: String,
+        length: usize
+    },
+    ChecksumMismatch {
+        expected: u32,
+        found: u32
+    },
+    TrailingData(usize),
+    FileError(FileError),
+    /// The data was written by a newer version of the app than the registered migrations know about
+    SchemaTooNew {
+        found: u32,
+        latest: u32
+    },
+    /// The migration that upgrades the data to `version` failed
+    MigrationFailed {
+        version: u32,
+        error: Box<KeyValueStoreError>
+    },
 }
 
 impl KeyValueStore {
+    /// An empty store at the latest schema version. `migrations` are run on all data imported into the store.
+    pub fn with_migrations(migrations: KeyValueStoreMigrations) -> Self {
+        Self {
+            values: HashMap::with_capacity(DEFAULT_STORE_CAPACITY),
+            schema_version: migrations.latest_version(),
+            migrations,
+            revision: 0,
+            changes: HashMap::with_capacity(DEFAULT_STORE_CAPACITY),
+            autosave: Default::default(),
+        }
+    }
+
+    /// The schema version of the data in the store. This is written alongside the data on export.
+    pub fn schema_version(&self) -> u32 {
+        self.schema_version
+    }
+
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str,&StorageValue)> {
+        self.values.iter().map(|(key,value)|(key.as_str(),value))
+    }
+
+    /// An empty store has nothing to migrate, so it is at the latest schema version afterwards
     pub fn delete_all(&mut self) {
-        todo!()
+        let keys: Vec<String> = self.values.drain().map(|(key,_)|key).collect();
+        for key in keys {
+            self.record_change(&key);
+        }
+        self.schema_version = self.migrations.latest_version();
+    }
+
+    fn record_change(&mut self,key: &str) {
+        self.revision += 1;
+        match self.changes.get_mut(key) {
+            Some(revision) => *revision = self.revision,
+            None => {
+                self.changes.insert(key.to_string(),self.revision);
+            },
+        }
+        self.autosave.mark_dirty();
+    }
+
+    /// Setting a key to the value it already holds is not a change
+    pub fn set(&mut self,key: &str,value: StorageValue) {
+        match self.values.get_mut(key) {
+            Some(existing) if *existing == value => return,
+            Some(existing) => *existing = value,
+            None => {
+                self.values.insert(key.to_string(),value);
+            },
+        }
+        self.record_change(key);
+    }
+
+    pub fn set_string(&mut self,key: &str,value: &str) {
+        if let Some(StorageValue::String(existing)) = self.values.get_mut(key) {
+            if existing != value {
+                existing.clear();
+                existing.push_str(value);
+                self.record_change(key);
+            }
+            return;
+        }
+        self.set(key,StorageValue::String(value.to_string()));
+    }
+
+    pub fn set_u32(&mut self,key: &str,value: u32) {
+        self.set(key,StorageValue::Integer(value));
+    }
+
+    pub fn set_f32(&mut self,key: &str,value: f32) {
+        self.set(key,StorageValue::Float(value));
     }
 
-    pub fn set_string(&mut self,_key: &'static str,_value: &'static str) {
-        todo!()
+    pub fn set_bytes(&mut self,key: &str,value: &[u8]) {
+        if let Some(StorageValue::Bytes(existing)) = self.values.get_mut(key) {
+            if existing != value {
+                existing.clear();
+                existing.extend_from_slice(value);
+                self.record_change(key);
+            }
+            return;
+        }
+        self.set(key,StorageValue::Bytes(value.to_vec()));
     }
 
-    pub fn set_u32(&mut self,_key: &'static str,_value: u32) {
-        todo!()
+    pub fn set_flag(&mut self,key: &str) {
+        self.set(key,StorageValue::Flag);
     }
 
-    pub fn set_flag(&mut self,_key: &'static str) {
-        todo!()
+    pub fn delete(&mut self,key: &str) -> Result<(),KeyValueStoreError> {
+        match self.values.remove(key) {
+            Some(_) => {
+                self.record_change(key);
+                Ok(())
+            },
+            None => Err(KeyValueStoreError::KeyNotFound(key.to_string())),
+        }
     }
 
-    pub fn delete(&mut self,_key: &'static str) -> Result<(),KeyValueStoreError> {
-        todo!()
+    /// Moves the value at `from` to `to`, replacing any value already at `to`. Intended for migrations.
+    pub fn rename(&mut self,from: &str,to: &str) -> Result<(),KeyValueStoreError> {
+        let Some(value) = self.values.remove(from) else {
+            return Err(KeyValueStoreError::KeyNotFound(from.to_string()));
+        };
+        self.record_change(from);
+        self.set(to,value);
+        Ok(())
     }
 
-    pub fn get_string(&self,_key: &'static str) -> Result<(),&'static str> {
-        todo!()
+    /// The current revision. Keep it around and pass it to `changed_since` or `changes_since` later on.
+    pub fn revision(&self) -> u64 {
+        self.revision
     }
 
-    pub fn get_u32(&self,_key: &'static str) -> Result<u32,KeyValueStoreError> {
-        todo!()
+    /// If `key` was set, deleted, or replaced by an import after `revision`. Deletions are forgotten on the next flush.
+    pub fn changed_since(&self,key: &str,revision: u64) -> bool {
+        match self.changes.get(key) {
+            Some(changed) => *changed > revision,
+            None => false,
+        }
     }
 
-    pub fn has_key(&self,_key: &'static str) -> bool {
-        todo!()
+    /// Every key changed after `revision`, including deleted keys.
+    ///
+    /// Deleted keys are only reported until the next flush, see `prune_deleted_changes`. Live keys are always reported.
+    pub fn changes_since(&self,revision: u64) -> impl Iterator<Item = &str> {
+        self.changes.iter().filter_map(move |(key,changed)|match *changed > revision {
+            true => Some(key.as_str()),
+            false => None,
+        })
+    }
+
+    /// Forgets the changes of deleted keys, otherwise every key ever deleted is tracked. Done on every autosave.
+    ///
+    /// Stores that are saved by hand with `AutosavePolicy::Manual` should call this after exporting.
+    pub fn prune_deleted_changes(&mut self) {
+        let values = &self.values;
+        self.changes.retain(|key,_|values.contains_key(key));
+    }
+
+    /// If there are changes that have not been exported
+    pub fn is_dirty(&self) -> bool {
+        self.autosave.dirty
+    }
+
+    /// For when the data from `poll_autosave` could not be written
+    pub fn mark_dirty(&mut self) {
+        self.autosave.mark_dirty();
+    }
+
+    pub fn autosave_policy(&self) -> AutosavePolicy {
+        self.autosave.policy
+    }
+
+    pub fn set_autosave_policy(&mut self,policy: AutosavePolicy) {
+        self.autosave.policy = policy;
+    }
+
+    /// Saves on the next `poll_autosave` if the store is dirty, ignoring the debounce. Has no effect with `AutosavePolicy::Manual`.
+    pub fn request_flush(&mut self) {
+        self.autosave.flush_requested = true;
+    }
+
+    /// Advances the autosave timers. When a save is due, the store is encoded and marked clean.
+    ///
+    /// The data is meant for `WimpyIO::save_key_value_store`. If the write fails, call `mark_dirty` so it is tried again.
+    pub fn poll_autosave(&mut self,delta_seconds: f32) -> Option<Vec<u8>> {
+        if !self.autosave.update(delta_seconds) {
+            return None;
+        }
+        match self.to_bytes() {
+            Ok(data) => {
+                self.autosave.mark_clean();
+                self.prune_deleted_changes();
+                Some(data)
+            },
+            Err(error) => {
+                log::error!("Key value store autosave encode failure: {:?}",error);
+                // Don't try again every frame, wait for the next change
+                self.autosave.mark_clean();
+                None
+            },
+        }
+    }
+
+    pub fn get(&self,key: &str) -> Option<&StorageValue> {
+        self.values.get(key)
+    }
+
+    pub fn get_type(&self,key: &str) -> Option<StorageValueType> {
+        self.values.get(key).map(StorageValue::value_type)
+    }
+
+    fn get_typed(&self,key: &str,expected: StorageValueType) -> Result<&StorageValue,KeyValueStoreError> {
+        let Some(value) = self.values.get(key) else {
+            return Err(KeyValueStoreError::KeyNotFound(key.to_string()));
+        };
+        let found = value.value_type();
+        if found != expected {
+            return Err(KeyValueStoreError::MismatchedType {
+                key: key.to_string(),
+                expected,
+                found
+            });
+        }
+        Ok(value)
+    }
+
+    pub fn get_string(&self,key: &str) -> Result<&str,KeyValueStoreError> {
+        match self.get_typed(key,StorageValueType::String)? {
+            StorageValue::String(value) => Ok(value),
+            _ => unreachable!(),
+        }
+    }
+
+    pub fn get_u32(&self,key: &str) -> Result<u32,KeyValueStoreError> {
+        match self.get_typed(key,StorageValueType::Integer)? {
+            StorageValue::Integer(value) => Ok(*value),
+            _ => unreachable!(),
+        }
+    }
+
+    pub fn get_f32(&self,key: &str) -> Result<f32,KeyValueStoreError> {
+        match self.get_typed(key,StorageValueType::Float)? {
+            StorageValue::Float(value) => Ok(*value),
+            _ => unreachable!(),
+        }
+    }
+
+    pub fn get_bytes(&self,key: &str) -> Result<&[u8],KeyValueStoreError> {
+        match self.get_typed(key,StorageValueType::Bytes)? {
+            StorageValue::Bytes(value) => Ok(value),
+            _ => unreachable!(),
+        }
+    }
+
+    /// A flag is either set or it isn't. A key holding a different value type is not a flag.
+    pub fn has_flag(&self,key: &str) -> bool {
+        matches!(self.values.get(key),Some(StorageValue::Flag))
+    }
+
+    pub fn has_key(&self,key: &str) -> bool {
+        self.values.contains_key(key)
+    }
+
+    /// Encode the store with the versioned binary format. Keys are written in sorted order so identical stores produce identical bytes.
+    pub fn to_bytes(&self) -> Result<Vec<u8>,KeyValueStoreError> {
+        binary_format::encode(&self.values,self.schema_version)
+    }
+
+    /// Decode a store from the versioned binary format. Empty input decodes to an empty store.
+    ///
+    /// No migrations are run, the store keeps the schema version of the data.
+    pub fn from_bytes(data: &[u8]) -> Result<Self,KeyValueStoreError> {
+        let decoded = binary_format::decode(data)?;
+        Ok(Self {
+            values: decoded.values,
+            schema_version: decoded.schema_version,
+            ..Self::default()
+        })
+    }
+
+    /// Replaces the contents of the store with `data`, running any migrations the data needs.
+    ///
+    /// The store is left untouched if the data fails to decode or migrate.
+    pub fn import_bytes(&mut self,data: &[u8]) -> Result<(),KeyValueStoreError> {
+        let mut imported = Self::from_bytes(data)?;
+        if imported.is_empty() {
+            imported.schema_version = self.migrations.latest_version();
+        }
+        let loaded_version = imported.schema_version;
+        self.migrations.apply(&mut imported)?;
+
+        let old_values = std::mem::replace(&mut self.values,imported.values);
+        for key in old_values.keys() {
+            if self.values.get(key) != old_values.get(key) {
+                self.record_change(key);
+            }
+        }
+        let new_keys: Vec<String> = self.values.keys().filter(|key|!old_values.contains_key(*key)).cloned().collect();
+        for key in new_keys {
+            self.record_change(&key);
+        }
+
+        self.schema_version = imported.schema_version;
+        // Freshly loaded data matches what is stored, unless a migration changed it
+        if loaded_version == self.schema_version {
+            self.autosave.mark_clean();
+        } else {
+            self.autosave.mark_dirty();
+        }
+        Ok(())
     }
 
-    pub async fn import<IO: WimpyIO>(&mut self) {
-        todo!();
+    /// Replaces the contents of the store with the data provided by `IO`. The store is left untouched if the data fails to decode or migrate.
+    pub async fn import<IO: WimpyIO>(&mut self) -> Result<(),KeyValueStoreError> {
+        let data = match IO::load_key_value_store().await {
+            Ok(value) => value,
+            Err(error) => return Err(KeyValueStoreError::FileError(error)),
+        };
+        self.import_bytes(&data)
     }
 
-    pub async fn export<IO: WimpyIO>(&self) {
-        todo!();
+    pub async fn export<IO: WimpyIO>(&mut self) -> Result<(),KeyValueStoreError> {
+        let data = self.to_bytes()?;
+        if let Err(error) = IO::save_key_value_store(&data).await {
+            return Err(KeyValueStoreError::FileError(error));
+        }
+        self.autosave.mark_clean();
+        Ok(())
     }
 }
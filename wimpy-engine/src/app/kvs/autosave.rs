@@ -0,0 +1,64 @@
+const DEFAULT_DEBOUNCE_SECONDS: f32 = 2.0;
+const DEFAULT_MAX_DELAY_SECONDS: f32 = 30.0;
+
+#[derive(Debug,Clone,Copy,PartialEq,Default)]
+pub enum AutosavePolicy {
+    /// The app calls `KeyValueStore::export` itself
+    #[default]
+    Manual,
+    /// Save once the store has gone `debounce` seconds without a change, or `max_delay` seconds after the first unsaved change, whichever comes first.
+    ///
+    /// Lifecycle flushes (`KeyValueStore::request_flush`) save immediately.
+    Debounced {
+        debounce: f32,
+        max_delay: f32
+    },
+}
+
+impl AutosavePolicy {
+    pub const DEFAULT_DEBOUNCED: Self = Self::Debounced {
+        debounce: DEFAULT_DEBOUNCE_SECONDS,
+        max_delay: DEFAULT_MAX_DELAY_SECONDS
+    };
+}
+
+#[derive(Default)]
+pub(super) struct AutosaveState {
+    pub policy:             AutosavePolicy,
+    pub dirty:              bool,
+    pub flush_requested:    bool,
+    since_change:           f32,
+    since_dirty:            f32,
+}
+
+impl AutosaveState {
+    pub fn mark_dirty(&mut self) {
+        if !self.dirty {
+            self.since_dirty = 0.0;
+        }
+        self.dirty = true;
+        self.since_change = 0.0;
+    }
+
+    pub fn mark_clean(&mut self) {
+        self.dirty = false;
+        self.flush_requested = false;
+        self.since_change = 0.0;
+        self.since_dirty = 0.0;
+    }
+
+    /// Advances the timers, returns true if the store should be saved now
+    pub fn update(&mut self,delta_seconds: f32) -> bool {
+        let AutosavePolicy::Debounced { debounce, max_delay } = self.policy else {
+            self.flush_requested = false;
+            return false;
+        };
+        if !self.dirty {
+            self.flush_requested = false;
+            return false;
+        }
+        self.since_change += delta_seconds;
+        self.since_dirty += delta_seconds;
+        self.flush_requested || self.since_change >= debounce || self.since_dirty >= max_delay
+    }
+}
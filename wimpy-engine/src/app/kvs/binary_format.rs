@@ -0,0 +1,222 @@
+/*
+    Key value store binary format, all integers are little endian
+
+    Header
+        magic           [u8;4]      "WKVS"
+        version         u16
+        schema version  u32         Version 2 and up. Owned by the app, see `KeyValueStoreMigrations`.
+        entry count     u32
+
+    Entry (repeated 'entry count' times)
+        key length      u16
+        key             [u8]        UTF-8
+        value type      u8          `StorageValueType`
+        value           ...         String: u32 length + UTF-8 | Integer: u32 | Flag: nothing | Float: f32 | Bytes: u32 length + [u8]
+
+    Footer
+        checksum        u32         FNV-1a of every byte that comes before it
+*/
+
+const MAGIC: [u8;4] = *b"WKVS";
+const FORMAT_VERSION: u16 = 2;
+
+/* Version 1 has no schema version, it is read as schema 0 */
+const FORMAT_VERSION_NO_SCHEMA: u16 = 1;
+
+const HEADER_SIZE: usize = MAGIC.len() + size_of::<u16>() + size_of::<u32>() * 2;
+const MIN_HEADER_SIZE: usize = MAGIC.len() + size_of::<u16>() + size_of::<u32>();
+const FOOTER_SIZE: usize = size_of::<u32>();
+
+const FNV_OFFSET_BASIS: u32 = 0x811C9DC5;
+const FNV_PRIME: u32 = 0x01000193;
+
+use std::collections::HashMap;
+use super::{KeyValueStoreError, StorageValue, StorageValueType};
+
+fn checksum(data: &[u8]) -> u32 {
+    let mut hash = FNV_OFFSET_BASIS;
+    for byte in data {
+        hash ^= *byte as u32;
+        hash = hash.wrapping_mul(FNV_PRIME);
+    }
+    hash
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+    position: usize
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self,length: usize) -> Result<&'a [u8],KeyValueStoreError> {
+        let end = match self.position.checked_add(length) {
+            Some(value) if value <= self.data.len() => value,
+            _ => return Err(KeyValueStoreError::UnexpectedEndOfData),
+        };
+        let slice = &self.data[self.position..end];
+        self.position = end;
+        Ok(slice)
+    }
+
+    fn read_array<const SIZE: usize>(&mut self) -> Result<[u8;SIZE],KeyValueStoreError> {
+        let mut array = [0;SIZE];
+        array.copy_from_slice(self.take(SIZE)?);
+        Ok(array)
+    }
+
+    fn read_u8(&mut self) -> Result<u8,KeyValueStoreError> {
+        Ok(self.read_array::<1>()?[0])
+    }
+
+    fn read_u16(&mut self) -> Result<u16,KeyValueStoreError> {
+        Ok(u16::from_le_bytes(self.read_array()?))
+    }
+
+    fn read_u32(&mut self) -> Result<u32,KeyValueStoreError> {
+        Ok(u32::from_le_bytes(self.read_array()?))
+    }
+
+    fn remaining(&self) -> usize {
+        self.data.len() - self.position
+    }
+}
+
+fn write_length_prefixed(buffer: &mut Vec<u8>,key: &str,data: &[u8]) -> Result<(),KeyValueStoreError> {
+    let Ok(length) = u32::try_from(data.len()) else {
+        return Err(KeyValueStoreError::ValueTooLong {
+            key: key.to_string(),
+            length: data.len()
+        });
+    };
+    buffer.extend_from_slice(&length.to_le_bytes());
+    buffer.extend_from_slice(data);
+    Ok(())
+}
+
+pub fn encode(values: &HashMap<String,StorageValue>,schema_version: u32) -> Result<Vec<u8>,KeyValueStoreError> {
+    let mut keys: Vec<&String> = values.keys().collect();
+    keys.sort_unstable();
+
+    let Ok(entry_count) = u32::try_from(keys.len()) else {
+        return Err(KeyValueStoreError::TooManyEntries(keys.len()));
+    };
+
+    let mut buffer = Vec::with_capacity(HEADER_SIZE + FOOTER_SIZE + keys.len() * 16);
+    buffer.extend_from_slice(&MAGIC);
+    buffer.extend_from_slice(&FORMAT_VERSION.to_le_bytes());
+    buffer.extend_from_slice(&schema_version.to_le_bytes());
+    buffer.extend_from_slice(&entry_count.to_le_bytes());
+
+    for key in keys {
+        let Ok(key_length) = u16::try_from(key.len()) else {
+            return Err(KeyValueStoreError::KeyTooLong(key.len()));
+        };
+        buffer.extend_from_slice(&key_length.to_le_bytes());
+        buffer.extend_from_slice(key.as_bytes());
+
+        let value = &values[key];
+        buffer.push(value.value_type() as u8);
+
+        match value {
+            StorageValue::String(text) =>   write_length_prefixed(&mut buffer,key,text.as_bytes())?,
+            StorageValue::Integer(value) => buffer.extend_from_slice(&value.to_le_bytes()),
+            StorageValue::Flag =>           {},
+            StorageValue::Float(value) =>   buffer.extend_from_slice(&value.to_le_bytes()),
+            StorageValue::Bytes(data) =>    write_length_prefixed(&mut buffer,key,data)?,
+        }
+    }
+
+    let checksum = checksum(&buffer);
+    buffer.extend_from_slice(&checksum.to_le_bytes());
+
+    Ok(buffer)
+}
+
+pub struct DecodedStore {
+    pub schema_version: u32,
+    pub values:         HashMap<String,StorageValue>,
+}
+
+pub fn decode(data: &[u8]) -> Result<DecodedStore,KeyValueStoreError> {
+    if data.is_empty() {
+        return Ok(DecodedStore {
+            schema_version: 0,
+            values: HashMap::with_capacity(super::DEFAULT_STORE_CAPACITY)
+        });
+    }
+
+    if data.len() < MAGIC.len() || data[..MAGIC.len()] != MAGIC {
+        return Err(KeyValueStoreError::InvalidHeader);
+    }
+
+    if data.len() < MIN_HEADER_SIZE + FOOTER_SIZE {
+        return Err(KeyValueStoreError::UnexpectedEndOfData);
+    }
+
+    let (body,footer) = data.split_at(data.len() - FOOTER_SIZE);
+
+    let mut reader = Reader {
+        data: body,
+        position: MAGIC.len()
+    };
+
+    let version = reader.read_u16()?;
+    if version != FORMAT_VERSION && version != FORMAT_VERSION_NO_SCHEMA {
+        return Err(KeyValueStoreError::UnsupportedVersion(version));
+    }
+
+    let expected = u32::from_le_bytes([footer[0],footer[1],footer[2],footer[3]]);
+    let found = checksum(body);
+    if expected != found {
+        return Err(KeyValueStoreError::ChecksumMismatch { expected, found });
+    }
+
+    let schema_version = match version {
+        FORMAT_VERSION_NO_SCHEMA => 0,
+        _ => reader.read_u32()?
+    };
+
+    let entry_count = reader.read_u32()? as usize;
+
+    // Don't trust the entry count for preallocation, a corrupt count could be enormous
+    let mut values = HashMap::with_capacity(entry_count.min(reader.remaining()));
+
+    for _ in 0..entry_count {
+        let key_length = reader.read_u16()? as usize;
+        let key = match std::str::from_utf8(reader.take(key_length)?) {
+            Ok(value) => value.to_string(),
+            Err(_) => return Err(KeyValueStoreError::InvalidKey),
+        };
+
+        let value = match StorageValueType::try_from(reader.read_u8()?)? {
+            StorageValueType::String => {
+                let length = reader.read_u32()? as usize;
+                match std::str::from_utf8(reader.take(length)?) {
+                    Ok(value) => StorageValue::String(value.to_string()),
+                    Err(_) => return Err(KeyValueStoreError::InvalidString { key }),
+                }
+            },
+            StorageValueType::Integer => StorageValue::Integer(reader.read_u32()?),
+            StorageValueType::Flag => StorageValue::Flag,
+            StorageValueType::Float => StorageValue::Float(f32::from_le_bytes(reader.read_array()?)),
+            StorageValueType::Bytes => {
+                let length = reader.read_u32()? as usize;
+                StorageValue::Bytes(reader.take(length)?.to_vec())
+            },
+        };
+
+        if values.contains_key(&key) {
+            return Err(KeyValueStoreError::DuplicateKey(key));
+        }
+        values.insert(key,value);
+    }
+
+    if reader.remaining() > 0 {
+        return Err(KeyValueStoreError::TrailingData(reader.remaining()));
+    }
+
+    Ok(DecodedStore {
+        schema_version,
+        values
+    })
+}
@@ -0,0 +1,49 @@
+use super::{KeyValueStore, KeyValueStoreError};
+
+type Migration = Box<dyn Fn(&mut KeyValueStore) -> Result<(),KeyValueStoreError>>;
+
+/// An ordered list of schema upgrades for a `KeyValueStore`.
+///
+/// The first migration passed to `with_migration` upgrades schema 0 to 1, the second upgrades 1 to 2, and so on.
+/// The latest schema version is the number of migrations, so migrations must only ever be appended, never reordered or removed.
+#[derive(Default)]
+pub struct KeyValueStoreMigrations {
+    steps: Vec<Migration>,
+}
+
+impl KeyValueStoreMigrations {
+    /// Migrations operate on the decoded store before it is exposed. Any error aborts the import.
+    pub fn with_migration<F>(mut self,migration: F) -> Self
+    where
+        F: Fn(&mut KeyValueStore) -> Result<(),KeyValueStoreError> + 'static
+    {
+        self.steps.push(Box::new(migration));
+        self
+    }
+
+    pub fn latest_version(&self) -> u32 {
+        self.steps.len() as u32
+    }
+
+    /// Runs every migration after `store`'s schema version. On failure `store` is left partially migrated, callers should discard it.
+    pub(super) fn apply(&self,store: &mut KeyValueStore) -> Result<(),KeyValueStoreError> {
+        let latest = self.latest_version();
+        if store.schema_version > latest {
+            return Err(KeyValueStoreError::SchemaTooNew {
+                found: store.schema_version,
+                latest
+            });
+        }
+        for (index,migration) in self.steps.iter().enumerate().skip(store.schema_version as usize) {
+            let version = index as u32 + 1;
+            if let Err(error) = migration(store) {
+                return Err(KeyValueStoreError::MigrationFailed {
+                    version,
+                    error: Box::new(error)
+                });
+            }
+            store.schema_version = version;
+        }
+        Ok(())
+    }
+}
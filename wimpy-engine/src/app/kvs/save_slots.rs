@@ -0,0 +1,235 @@
+const AUTOSAVE_NAME: &'static str = "autosave";
+const QUICKSAVE_NAME: &'static str = "quicksave";
+const NUMBERED_PREFIX: &'static str = "slot-";
+
+const METADATA_TIMESTAMP: &'static str = "timestamp";
+const METADATA_PLAY_TIME: &'static str = "play-time";
+const METADATA_LABEL: &'static str = "label";
+const METADATA_THUMBNAIL: &'static str = "thumbnail";
+
+use std::fmt;
+use crate::app::{WimpyIO, FileError};
+use super::{KeyValueStore, KeyValueStoreError};
+
+#[derive(Debug,Clone,Copy,PartialEq,Eq,Hash,PartialOrd,Ord)]
+pub enum SaveSlot {
+    Numbered(u32),
+    Autosave,
+    Quicksave,
+}
+
+impl SaveSlot {
+    /// The platform storage name of the slot. Safe to use as a file name.
+    pub fn name(&self) -> String {
+        match self {
+            SaveSlot::Numbered(number) => format!("{NUMBERED_PREFIX}{number}"),
+            SaveSlot::Autosave =>         AUTOSAVE_NAME.to_string(),
+            SaveSlot::Quicksave =>        QUICKSAVE_NAME.to_string(),
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            AUTOSAVE_NAME => Some(SaveSlot::Autosave),
+            QUICKSAVE_NAME => Some(SaveSlot::Quicksave),
+            _ => match name.strip_prefix(NUMBERED_PREFIX)?.parse::<u32>() {
+                Ok(number) => Some(SaveSlot::Numbered(number)),
+                Err(_) => None,
+            }
+        }
+    }
+}
+
+impl fmt::Display for SaveSlot {
+    fn fmt(&self,f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.name())
+    }
+}
+
+#[derive(Debug,Clone,Default)]
+pub struct SaveSlotMetadata {
+    /// Seconds since the unix epoch, as reported by `WimpyIO::unix_timestamp` when the slot was written
+    pub timestamp:  u64,
+    /// Accumulated play time in seconds
+    pub play_time:  u32,
+    pub label:      String,
+    /// Opaque image data, the encoding is up to the app
+    pub thumbnail:  Option<Vec<u8>>,
+}
+
+#[derive(Debug,Clone)]
+pub struct SaveSlotInfo {
+    pub slot:       SaveSlot,
+    pub metadata:   SaveSlotMetadata,
+}
+
+#[derive(Debug)]
+pub enum SaveSlotError {
+    FileError(SaveSlot,FileError),
+    StoreError(SaveSlot,KeyValueStoreError),
+    MetadataError(SaveSlot,KeyValueStoreError),
+    ListError(FileError),
+}
+
+impl SaveSlotMetadata {
+    fn to_store(&self) -> KeyValueStore {
+        let mut store = KeyValueStore::default();
+        store.set_bytes(METADATA_TIMESTAMP,&self.timestamp.to_le_bytes());
+        store.set_u32(METADATA_PLAY_TIME,self.play_time);
+        store.set_string(METADATA_LABEL,&self.label);
+        if let Some(thumbnail) = &self.thumbnail {
+            store.set_bytes(METADATA_THUMBNAIL,thumbnail);
+        }
+        store
+    }
+
+    fn from_store(store: &KeyValueStore) -> Result<Self,KeyValueStoreError> {
+        let timestamp = match <[u8;8]>::try_from(store.get_bytes(METADATA_TIMESTAMP)?) {
+            Ok(bytes) => u64::from_le_bytes(bytes),
+            Err(_) => return Err(KeyValueStoreError::UnexpectedEndOfData),
+        };
+        Ok(Self {
+            timestamp,
+            play_time: store.get_u32(METADATA_PLAY_TIME)?,
+            label: store.get_string(METADATA_LABEL)?.to_string(),
+            thumbnail: match store.has_key(METADATA_THUMBNAIL) {
+                true => Some(store.get_bytes(METADATA_THUMBNAIL)?.to_vec()),
+                false => None,
+            },
+        })
+    }
+}
+
+/// Multiple save files on top of `KeyValueStore` and `WimpyIO`.
+///
+/// The slot data lives in `store`, separate from the app's settings store (`WimpyAppContext::key_value_store`).
+#[derive(Default)]
+pub struct SaveSlotManager {
+    /// The store of the active slot. Written out by `save` and replaced by `load`.
+    ///
+    /// Replace it with `KeyValueStore::with_migrations` before the first `load` to migrate old save data.
+    pub store:      KeyValueStore,
+    active_slot:    Option<SaveSlot>,
+    play_time:      f64,
+}
+
+impl SaveSlotManager {
+    pub fn active_slot(&self) -> Option<SaveSlot> {
+        self.active_slot
+    }
+
+    /// Play time in seconds of the active store, including time from the slot it was loaded from
+    pub fn play_time(&self) -> u32 {
+        self.play_time as u32
+    }
+
+    /// Call once per frame (or whenever the game considers time to be passing) to accumulate play time
+    pub fn advance_play_time(&mut self,delta_seconds: f32) {
+        self.play_time += delta_seconds as f64;
+    }
+
+    /// Clears the active store for a new game
+    pub fn reset(&mut self) {
+        self.store.delete_all();
+        self.active_slot = None;
+        self.play_time = 0.0;
+    }
+
+    /// Slots with missing or unreadable metadata are skipped. Sorted by slot (numbered first, then autosave, then quicksave).
+    pub async fn list<IO: WimpyIO>(&self) -> Result<Vec<SaveSlotInfo>,SaveSlotError> {
+        let names = match IO::list_slots().await {
+            Ok(value) => value,
+            Err(error) => return Err(SaveSlotError::ListError(error)),
+        };
+        let mut slots = Vec::with_capacity(names.len());
+        for name in names {
+            let Some(slot) = SaveSlot::from_name(&name) else {
+                log::warn!("Unrecognized save slot name '{name}'");
+                continue;
+            };
+            match Self::get_metadata::<IO>(slot).await {
+                Ok(metadata) => slots.push(SaveSlotInfo { slot, metadata }),
+                Err(error) => log::warn!("Save slot metadata failure: {:?}",error),
+            }
+        }
+        slots.sort_unstable_by_key(|info|info.slot);
+        Ok(slots)
+    }
+
+    pub async fn get_metadata<IO: WimpyIO>(slot: SaveSlot) -> Result<SaveSlotMetadata,SaveSlotError> {
+        let data = match IO::load_slot_metadata(&slot.name()).await {
+            Ok(value) => value,
+            Err(error) => return Err(SaveSlotError::FileError(slot,error)),
+        };
+        match KeyValueStore::from_bytes(&data).and_then(|store|SaveSlotMetadata::from_store(&store)) {
+            Ok(metadata) => Ok(metadata),
+            Err(error) => Err(SaveSlotError::MetadataError(slot,error)),
+        }
+    }
+
+    /// Writes the active store to `slot`, which becomes the active slot
+    pub async fn save<IO: WimpyIO>(&mut self,slot: SaveSlot,label: &str,thumbnail: Option<&[u8]>) -> Result<(),SaveSlotError> {
+        let metadata = SaveSlotMetadata {
+            timestamp: IO::unix_timestamp(),
+            play_time: self.play_time(),
+            label: label.to_string(),
+            thumbnail: thumbnail.map(<[u8]>::to_vec),
+        };
+        let data = match self.store.to_bytes() {
+            Ok(value) => value,
+            Err(error) => return Err(SaveSlotError::StoreError(slot,error)),
+        };
+        let metadata = match metadata.to_store().to_bytes() {
+            Ok(value) => value,
+            Err(error) => return Err(SaveSlotError::MetadataError(slot,error)),
+        };
+        if let Err(error) = IO::save_slot(&slot.name(),&data,&metadata).await {
+            return Err(SaveSlotError::FileError(slot,error));
+        }
+        self.active_slot = Some(slot);
+        Ok(())
+    }
+
+    /// Replaces the active store with the contents of `slot`. The active store is untouched on failure.
+    pub async fn load<IO: WimpyIO>(&mut self,slot: SaveSlot) -> Result<(),SaveSlotError> {
+        let metadata = Self::get_metadata::<IO>(slot).await?;
+        let data = match IO::load_slot(&slot.name()).await {
+            Ok(value) => value,
+            Err(error) => return Err(SaveSlotError::FileError(slot,error)),
+        };
+        if let Err(error) = self.store.import_bytes(&data) {
+            return Err(SaveSlotError::StoreError(slot,error));
+        }
+        self.active_slot = Some(slot);
+        self.play_time = metadata.play_time as f64;
+        Ok(())
+    }
+
+    /// Copies data and metadata as they are. The metadata timestamp is left alone.
+    pub async fn copy<IO: WimpyIO>(source: SaveSlot,destination: SaveSlot) -> Result<(),SaveSlotError> {
+        let source_name = source.name();
+        let data = match IO::load_slot(&source_name).await {
+            Ok(value) => value,
+            Err(error) => return Err(SaveSlotError::FileError(source,error)),
+        };
+        let metadata = match IO::load_slot_metadata(&source_name).await {
+            Ok(value) => value,
+            Err(error) => return Err(SaveSlotError::FileError(source,error)),
+        };
+        if let Err(error) = IO::save_slot(&destination.name(),&data,&metadata).await {
+            return Err(SaveSlotError::FileError(destination,error));
+        }
+        Ok(())
+    }
+
+    /// Deleting the active slot does not clear the active store, it can still be saved elsewhere
+    pub async fn delete<IO: WimpyIO>(&mut self,slot: SaveSlot) -> Result<(),SaveSlotError> {
+        if let Err(error) = IO::delete_slot(&slot.name()).await {
+            return Err(SaveSlotError::FileError(slot,error));
+        }
+        if self.active_slot == Some(slot) {
+            self.active_slot = None;
+        }
+        Ok(())
+    }
+}
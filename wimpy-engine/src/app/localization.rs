@@ -0,0 +1,191 @@
+use std::{collections::{HashMap, HashSet}, rc::Rc};
+
+use crate::app::{WimpyAppContext, WimpyIO, wam::{AssetId, AssetManager}};
+
+pub const DEFAULT_LOCALE: &'static str = "en";
+/// String tables are data assets named with this prefix and the locale, e.g., `locale/en`
+pub const DEFAULT_STRING_TABLE_PREFIX: &'static str = "locale/";
+
+/// The json of a string table, a flat object of keys to strings
+pub type StringTable = HashMap<Rc<str>,Rc<str>>;
+
+/// Loaded string tables for the active locale and the default locale
+pub struct Localization {
+    table_prefix:       String,
+    default_locale:     Rc<str>,
+    active_locale:      Rc<str>,
+    active:             StringTable,
+    /// Empty when the active locale is the default locale
+    fallback:           StringTable,
+    /// Incremented every time the tables change, see `Localization::get_revision`
+    revision:           u64,
+    /// Keys that were already logged as missing
+    reported:           HashSet<Rc<str>>,
+    /// The string table assets the loaded tables came from, released by the next `set_locale`
+    held_tables:        Vec<AssetId>,
+    /// Appends the key to the missing text, on by default in debug builds
+    pub show_missing_keys: bool,
+}
+
+impl Default for Localization {
+    fn default() -> Self {
+        Self {
+            table_prefix:       DEFAULT_STRING_TABLE_PREFIX.to_string(),
+            default_locale:     Rc::from(DEFAULT_LOCALE),
+            active_locale:      Rc::from(DEFAULT_LOCALE),
+            active:             StringTable::new(),
+            fallback:           StringTable::new(),
+            revision:           0,
+            reported:           HashSet::new(),
+            held_tables:        Vec::new(),
+            show_missing_keys:  cfg!(debug_assertions),
+        }
+    }
+}
+
+impl Localization {
+    /// Takes effect on the next `WimpyAppContext::set_locale`
+    pub fn set_table_prefix(&mut self,prefix: &str) {
+        self.table_prefix = prefix.to_string();
+    }
+
+    /// Takes effect on the next `WimpyAppContext::set_locale`
+    pub fn set_default_locale(&mut self,locale: &str) {
+        self.default_locale = Rc::from(locale);
+    }
+
+    pub fn get_locale(&self) -> &Rc<str> {
+        &self.active_locale
+    }
+
+    pub fn get_default_locale(&self) -> &Rc<str> {
+        &self.default_locale
+    }
+
+    /// Strings from an older revision were looked up in a different locale and should be fetched again
+    pub fn get_revision(&self) -> u64 {
+        self.revision
+    }
+
+    pub fn get_table_name(&self,locale: &str) -> String {
+        format!("{}{}",self.table_prefix,locale)
+    }
+
+    /// The active locale first, then the default locale
+    pub fn lookup(&self,key: &str) -> Option<&Rc<str>> {
+        self.active.get(key).or_else(||self.fallback.get(key))
+    }
+
+    fn set_tables(&mut self,locale: &str,active: StringTable,fallback: StringTable) {
+        self.active_locale = Rc::from(locale);
+        self.active = active;
+        self.fallback = fallback;
+        self.reported.clear();
+        self.revision += 1;
+    }
+
+    /// `true` the first time a key is reported for the current tables
+    fn report_missing(&mut self,key: &str) -> bool {
+        if self.reported.contains(key) {
+            return false;
+        }
+        self.reported.insert(Rc::from(key));
+        return true;
+    }
+}
+
+/// Replaces each `{name}` with its argument. `{{` and `}}` are literal braces, and names without an argument are left as they are.
+pub fn substitute(template: &str,arguments: &[(&str,&str)]) -> String {
+    let mut output = String::with_capacity(template.len());
+    let mut remaining = template;
+
+    while let Some(index) = remaining.find(['{','}']) {
+        output.push_str(&remaining[..index]);
+        let rest = &remaining[index..];
+
+        if let Some(after) = rest.strip_prefix("{{").or_else(||rest.strip_prefix("}}")) {
+            output.push_str(&rest[..1]);
+            remaining = after;
+            continue;
+        }
+        if let Some(after) = rest.strip_prefix('}') {
+            output.push('}');
+            remaining = after;
+            continue;
+        }
+
+        let Some(end) = rest.find('}') else {
+            remaining = rest;
+            break;
+        };
+        let name = &rest[1..end];
+        match arguments.iter().find(|(argument,_)|*argument == name) {
+            Some((_,value)) => output.push_str(value),
+            None => output.push_str(&rest[..=end]),
+        }
+        remaining = &rest[end + 1..];
+    }
+
+    output.push_str(remaining);
+    return output;
+}
+
+impl WimpyAppContext {
+    /// Loads the string tables of `locale` and the default locale, replacing the tables that were loaded before.
+    ///
+    /// Returns `false` if the locale has no table, lookups then only use the default locale.
+    pub async fn set_locale<IO: WimpyIO>(&mut self,locale: &str) -> bool {
+        let previous_tables = std::mem::take(&mut self.localization.held_tables);
+        let active = self.load_string_table::<IO>(locale).await;
+        let found = active.is_some();
+
+        let default_locale = self.localization.default_locale.clone();
+        let fallback = match *default_locale == *locale {
+            true => None,
+            false => self.load_string_table::<IO>(&default_locale).await,
+        };
+
+        self.localization.set_tables(locale,active.unwrap_or_default(),fallback.unwrap_or_default());
+
+        /* Released after loading, tables that stay in use come from the cache */
+        for id in previous_tables {
+            AssetManager::release_data_asset(id,self);
+        }
+        return found;
+    }
+
+    async fn load_string_table<IO: WimpyIO>(&mut self,locale: &str) -> Option<StringTable> {
+        let name = self.localization.get_table_name(locale);
+        let id = self.resolve_asset(&name);
+        match AssetManager::get_data_asset::<IO,HashMap<String,String>>(id,self).await {
+            Ok(table) => {
+                self.localization.held_tables.push(id);
+                Some(table.into_iter().map(|(key,value)|(Rc::from(key),Rc::from(value))).collect())
+            },
+            Err(error) => {
+                self.report_asset_failure(&format!("String table '{name}'"),&error);
+                None
+            },
+        }
+    }
+
+    /// The string for `key` in the active locale. Missing keys return `missing_text`.
+    pub fn localize(&mut self,key: &str) -> Rc<str> {
+        if let Some(value) = self.localization.lookup(key) {
+            return value.clone();
+        }
+        if self.localization.report_missing(key) {
+            log::warn!("Missing localized string '{key}' for locale '{}'",self.localization.active_locale);
+        }
+        match self.localization.show_missing_keys {
+            true => Rc::from(format!("{} ({key})",self.missing_text)),
+            false => self.missing_text.clone(),
+        }
+    }
+
+    /// `localize` with `{name}` arguments, e.g., `localize_with("score",&[("points","12")])`
+    pub fn localize_with(&mut self,key: &str,arguments: &[(&str,&str)]) -> Rc<str> {
+        let template = self.localize(key);
+        Rc::from(substitute(&template,arguments))
+    }
+}
@@ -6,10 +6,24 @@ mod virtual_asset_translator; /* Private */
 mod wam_manifest;
 pub use wam_manifest::*;
 
+mod asset_id;
+pub use asset_id::{AssetId, AssetNames};
+
 pub mod json_input;
 
 mod asset_manager;
-pub use asset_manager::{AssetManager, AssetManagerError};
+pub use asset_manager::{AssetManager, AssetManagerError, ManifestLoadMode};
+
+mod audio;
+pub use audio::{AudioAsset, AudioFormat};
+
+mod archive;
+pub use archive::{ARCHIVE_EXTENSION, ARCHIVE_MANIFEST_ENTRY, ArchiveError, WamArchive};
+
+mod asset_source; /* Private */
+
+mod preload;
+pub use preload::{PreloadGroup, PreloadGroupKey, PreloadProgress, PreloadRequest, PreloadResult};
 
 slotmap::new_key_type! {
     pub struct HardAssetKey;
@@ -21,6 +35,11 @@ pub enum HardAssetType {
     Text,
     Image,
     Model,
+    /// Encoded sound, see `AudioFormat`
+    Audio,
+    Binary,
+    /// JSON, deserialized by the caller
+    Data,
 }
 
 #[derive(Debug,Clone)]
@@ -46,7 +65,14 @@ pub mod reference_types {
         pub key: HardAssetKey
     }
 
-    #[derive(Debug)]
+    /// An audio, binary or data asset
+    #[derive(Debug,Clone)]
+    pub struct Binary {
+        pub name: Rc<str>,
+        pub key: HardAssetKey
+    }
+
+    #[derive(Debug,Clone)]
     pub struct Model {
         pub name: Rc<str>,
         /// The `.gltf` file that contains the mesh
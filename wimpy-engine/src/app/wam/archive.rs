@@ -0,0 +1,176 @@
+const ARCHIVE_MAGIC: [u8;4] = *b"WPAK";
+const ARCHIVE_VERSION: u32 = 1;
+
+/// The manifest is stored next to the hard assets under this name
+pub const ARCHIVE_MANIFEST_ENTRY: &'static str = "wam.json";
+/// A manifest path with this extension is loaded as an archive
+pub const ARCHIVE_EXTENSION: &'static str = "wpak";
+
+const COMPRESSION_NONE: u8 = 0;
+const COMPRESSION_ZLIB: u8 = 1;
+
+/// An entry with an empty name: name length, offset, stored length, length, compression and checksum
+const MIN_ENTRY_SIZE: usize = 2 + 8 + 8 + 8 + 1 + 4;
+
+use std::{collections::HashMap, rc::Rc};
+
+/// A packed asset archive made by the `wam pack-archive` command. The layout is documented in `wam-builder/src/archive.rs`.
+///
+/// The whole file is held in memory, entries are decompressed and checked when they are read.
+#[derive(Debug)]
+pub struct WamArchive {
+    data:       Vec<u8>,
+    entries:    HashMap<Rc<str>,ArchiveEntry>,
+}
+
+#[derive(Debug,Clone,Copy)]
+struct ArchiveEntry {
+    offset:         usize,
+    stored_length:  usize,
+    length:         usize,
+    compression:    u8,
+    checksum:       u32,
+}
+
+#[derive(Debug)]
+pub enum ArchiveError {
+    InvalidMagic,
+    UnsupportedVersion(u32),
+    Truncated,
+    InvalidEntryName,
+    EntryOutOfBounds(Rc<str>),
+    UnknownCompression { entry: Rc<str>, compression: u8 },
+    EntryNotFound(String),
+    DecompressFailure(Rc<str>),
+    ChecksumMismatch(Rc<str>),
+    InvalidText(Rc<str>),
+}
+
+struct ArchiveReader<'a> {
+    data: &'a [u8],
+    position: usize,
+}
+
+impl ArchiveReader<'_> {
+    fn take(&mut self,length: usize) -> Result<&[u8],ArchiveError> {
+        let end = self.position.checked_add(length).ok_or(ArchiveError::Truncated)?;
+        let Some(bytes) = self.data.get(self.position..end) else {
+            return Err(ArchiveError::Truncated);
+        };
+        self.position = end;
+        return Ok(bytes);
+    }
+
+    fn take_array<const N: usize>(&mut self) -> Result<[u8;N],ArchiveError> {
+        let mut value = [0;N];
+        value.copy_from_slice(self.take(N)?);
+        return Ok(value);
+    }
+
+    fn read_u8(&mut self) -> Result<u8,ArchiveError> {
+        Ok(self.take_array::<1>()?[0])
+    }
+
+    fn read_u16(&mut self) -> Result<u16,ArchiveError> {
+        Ok(u16::from_le_bytes(self.take_array()?))
+    }
+
+    fn read_u32(&mut self) -> Result<u32,ArchiveError> {
+        Ok(u32::from_le_bytes(self.take_array()?))
+    }
+
+    fn read_usize(&mut self) -> Result<usize,ArchiveError> {
+        match usize::try_from(u64::from_le_bytes(self.take_array()?)) {
+            Ok(value) => Ok(value),
+            Err(_) => Err(ArchiveError::Truncated),
+        }
+    }
+}
+
+impl WamArchive {
+    /// Reads the table of contents. Entry data is not touched until it is requested.
+    pub fn parse(data: Vec<u8>) -> Result<Self,ArchiveError> {
+        let mut reader = ArchiveReader {
+            data: &data,
+            position: 0
+        };
+
+        if reader.take_array::<4>()? != ARCHIVE_MAGIC {
+            return Err(ArchiveError::InvalidMagic);
+        }
+        let version = reader.read_u32()?;
+        if version != ARCHIVE_VERSION {
+            return Err(ArchiveError::UnsupportedVersion(version));
+        }
+
+        let entry_count = reader.read_u32()? as usize;
+        /* The count is untrusted, the data bounds how many entries can really follow */
+        let mut entries = HashMap::with_capacity(entry_count.min(data.len() / MIN_ENTRY_SIZE));
+
+        for _ in 0..entry_count {
+            let name_length = reader.read_u16()? as usize;
+            let name: Rc<str> = match str::from_utf8(reader.take(name_length)?) {
+                Ok(value) => Rc::from(value),
+                Err(_) => return Err(ArchiveError::InvalidEntryName),
+            };
+            let entry = ArchiveEntry {
+                offset:         reader.read_usize()?,
+                stored_length:  reader.read_usize()?,
+                length:         reader.read_usize()?,
+                compression:    reader.read_u8()?,
+                checksum:       reader.read_u32()?,
+            };
+            match entry.offset.checked_add(entry.stored_length) {
+                Some(end) if end <= data.len() => {},
+                _ => return Err(ArchiveError::EntryOutOfBounds(name)),
+            }
+            entries.insert(name,entry);
+        }
+
+        return Ok(Self {
+            data,
+            entries
+        });
+    }
+
+    pub fn contains(&self,name: &str) -> bool {
+        self.entries.contains_key(name)
+    }
+
+    pub fn entry_names(&self) -> impl Iterator<Item = &Rc<str>> {
+        self.entries.keys()
+    }
+
+    /// Decompresses the entry and verifies its checksum
+    pub fn read(&self,name: &str) -> Result<Vec<u8>,ArchiveError> {
+        let Some((name,entry)) = self.entries.get_key_value(name) else {
+            return Err(ArchiveError::EntryNotFound(name.to_string()));
+        };
+        let stored = &self.data[entry.offset..entry.offset + entry.stored_length];
+
+        let data = match entry.compression {
+            COMPRESSION_NONE => stored.to_vec(),
+            COMPRESSION_ZLIB => match miniz_oxide::inflate::decompress_to_vec_zlib_with_limit(stored,entry.length) {
+                Ok(value) => value,
+                Err(_) => return Err(ArchiveError::DecompressFailure(name.clone())),
+            },
+            compression => return Err(ArchiveError::UnknownCompression {
+                entry: name.clone(),
+                compression
+            }),
+        };
+
+        if data.len() != entry.length || crc32fast::hash(&data) != entry.checksum {
+            return Err(ArchiveError::ChecksumMismatch(name.clone()));
+        }
+        return Ok(data);
+    }
+
+    pub fn read_text(&self,name: &str) -> Result<String,ArchiveError> {
+        let data = self.read(name)?;
+        match String::from_utf8(data) {
+            Ok(value) => Ok(value),
+            Err(_) => Err(ArchiveError::InvalidText(Rc::from(name))),
+        }
+    }
+}
@@ -0,0 +1,55 @@
+use std::{collections::HashMap, rc::Rc};
+
+/// An interned virtual asset name (e.g., `wimpy/font/classic`)
+///
+/// Resolve a name once with `WimpyAppContext::resolve_asset` and keep the id, lookups by id never touch the name again.
+/// Any name can be resolved, including names that are not in the manifest. Those fail with `VirtualAssetNotFound` when used.
+#[derive(Debug,Copy,Clone,PartialEq,Eq,Hash,PartialOrd,Ord)]
+pub struct AssetId(u32);
+
+/// Ids are never removed, so an id stays valid for the life of the asset manager
+#[derive(Debug,Default)]
+pub struct AssetNames {
+    ids:    HashMap<Rc<str>,AssetId>,
+    names:  Vec<Rc<str>>,
+}
+
+impl AssetNames {
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            ids:    HashMap::with_capacity(capacity),
+            names:  Vec::with_capacity(capacity),
+        }
+    }
+
+    pub fn intern(&mut self,name: &str) -> AssetId {
+        if let Some(id) = self.ids.get(name) {
+            return *id;
+        }
+        self.insert(Rc::from(name))
+    }
+
+    /// Same as `intern`, but reuses the allocation of a name that is already shared
+    pub fn intern_rc(&mut self,name: &Rc<str>) -> AssetId {
+        if let Some(id) = self.ids.get(name) {
+            return *id;
+        }
+        self.insert(name.clone())
+    }
+
+    fn insert(&mut self,name: Rc<str>) -> AssetId {
+        let id = AssetId(self.names.len() as u32);
+        self.names.push(name.clone());
+        self.ids.insert(name,id);
+        return id;
+    }
+
+    /// Does not intern, `None` if the name was never resolved
+    pub fn find(&self,name: &str) -> Option<AssetId> {
+        self.ids.get(name).copied()
+    }
+
+    pub fn get_name(&self,id: AssetId) -> Option<&Rc<str>> {
+        self.names.get(id.0 as usize)
+    }
+}
@@ -1,38 +1,66 @@
 const START_CACHE_ENTRY_CAPACITY: usize = 8;
 
-use std::{path::{Path, PathBuf}, rc::Rc};
+use std::{collections::{HashMap, HashSet}, fmt, path::{Path, PathBuf}, rc::Rc};
 use slotmap::SparseSecondaryMap;
 
 use crate::{UWimpyPoint, WimpyPointRect};
-use crate::app::{WimpyIO, WimpyAppContext, FileError, graphics::{*, textures::*}};
-use super::{*, reference_types::MeshletTexture};
+use crate::app::{WimpyIO, WimpyAppContext, WimpyImageData, FileError, graphics::{*, textures::*}};
+use super::{*, reference_types::{MeshletTexture, MeshletTextureLayers}, preload::*, asset_source::{AssetSource, AssetLocation}};
 
 #[derive(Default)]
 pub struct AssetManager {
     pub manifest:   WamManifest,
-    root:           PathBuf,
+    /// Where the hard assets of each mounted overlay are read from
+    sources:        SparseSecondaryMap<OverlayKey,AssetSource>,
     text_cache:     SparseSecondaryMap<HardAssetKey,Rc<str>>,
     texture_keys:   SparseSecondaryMap<HardAssetKey,WimpyTexture>,
     model_cache:    SparseSecondaryMap<HardAssetKey,TexturedMesh>,
+    /// Audio, binary and data assets
+    binary_cache:   SparseSecondaryMap<HardAssetKey,Rc<[u8]>>,
+    /// Held references to each cached hard asset. Reaching zero evicts the asset from its cache and the GPU.
+    ref_counts:     SparseSecondaryMap<HardAssetKey,u32>,
+    /// The meshlet textures that are referenced along with a cached model
+    model_textures: SparseSecondaryMap<HardAssetKey,Vec<HardAssetKey>>,
+    /// The hard assets taken by each virtual asset getter. An overlay can move a name to a different hard asset between a get and its release.
+    held:           HashMap<AssetId,Vec<HardAssetKey>>,
+    preload:        PreloadQueue,
+    /// Changes every time an overlay is mounted or unmounted
+    overlay_revision: u32,
 }
 
 #[derive(Debug)]
 pub enum AssetManagerError {
-    VirtualAssetNotFound    (&'static str),
-    MissingHardAsset        (&'static str),
+    VirtualAssetNotFound    (Rc<str>),
+    MissingHardAsset        (Rc<str>),
     MismatchedType          { expected: HardAssetType, found: HardAssetType },
     FileError               (FileError),
+    ArchiveError            (ArchiveError),
+    ManifestError           (WamManifestError),
     ModelImportError        (ModelError),
     TextureImportError      (SizeValidationError),
+    TextureUploadFailure    (TextureManagerError),
+    AudioDecodeFailure      (Rc<str>),
+    DataDecodeFailure       { name: Rc<str>, error: serde_json::Error },
 }
 
-fn get_full_path(root: &PathBuf,hard_asset_path: &str) -> PathBuf {
-    let mut path_buffer = PathBuf::new();
-    path_buffer.push(root);
-    for component in hard_asset_path.split('/') {
-        path_buffer.push(component);
+impl fmt::Display for AssetManagerError {
+    fn fmt(&self,f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AssetManagerError::ManifestError(error) => write!(f,"invalid manifest, {error}"),
+            AssetManagerError::DataDecodeFailure { name, error } => write!(f,"could not parse data '{name}', {error}"),
+            error => fmt::Debug::fmt(error,f),
+        }
     }
-    return path_buffer;
+}
+
+/// What happens when the manifest given to `WimpyAppContext::create` fails to load
+#[derive(Debug,Default,Copy,Clone,PartialEq,Eq)]
+pub enum ManifestLoadMode {
+    /// Starts with no assets. The failure is logged and shown by the debug shell.
+    #[default]
+    Lenient,
+    /// Context creation returns the error
+    Strict,
 }
 
 fn validate_hard_asset_type(hard_asset: &HardAsset,expected_type: HardAssetType) -> Result<(),AssetManagerError> {
@@ -47,49 +75,125 @@ fn validate_hard_asset_type(hard_asset: &HardAsset,expected_type: HardAssetType)
 }
 
 impl AssetManager {
+    /// No manifest is mounted
+    pub fn new() -> Self {
+        return Self {
+            manifest: WamManifest::new(),
+            sources: SparseSecondaryMap::new(),
+            text_cache: SparseSecondaryMap::with_capacity(START_CACHE_ENTRY_CAPACITY),
+            texture_keys: SparseSecondaryMap::with_capacity(START_CACHE_ENTRY_CAPACITY),
+            model_cache: SparseSecondaryMap::with_capacity(START_CACHE_ENTRY_CAPACITY),
+            binary_cache: SparseSecondaryMap::with_capacity(START_CACHE_ENTRY_CAPACITY),
+            ref_counts: SparseSecondaryMap::with_capacity(START_CACHE_ENTRY_CAPACITY),
+            model_textures: SparseSecondaryMap::with_capacity(START_CACHE_ENTRY_CAPACITY),
+            held: HashMap::with_capacity(START_CACHE_ENTRY_CAPACITY),
+            preload: PreloadQueue::default(),
+            overlay_revision: 0,
+        };
+    }
+
+    /// `manifest_path` is a manifest file or, with the `wpak` extension, an archive made by `wam pack-archive`
+    pub async fn load<IO: WimpyIO>(manifest_path: &Path) -> Result<Self,AssetManagerError> {
+        let mut assets = Self::new();
+        assets.mount_manifest::<IO>(manifest_path,BASE_OVERLAY_LABEL).await?;
+        return Ok(assets);
+    }
+
+    /// Same as `load`, but a manifest that fails to load leaves the manager empty
     pub async fn load_or_default<IO: WimpyIO>(manifest_path: Option<&Path>) -> Self {
-        match manifest_path {
-            Some(path) => match IO::load_text_file(path).await {
-                Ok(json_text) => match WamManifest::create(&json_text) {
-                    Ok(manifest) => {
-                        let mut path_buffer = PathBuf::from(path);
-                        path_buffer.pop();
-                        Self {
-                            root: path_buffer,
-                            manifest: manifest,
-                            text_cache: SparseSecondaryMap::with_capacity(START_CACHE_ENTRY_CAPACITY),
-                            texture_keys: SparseSecondaryMap::with_capacity(START_CACHE_ENTRY_CAPACITY),
-                            model_cache: SparseSecondaryMap::with_capacity(START_CACHE_ENTRY_CAPACITY),
-                        }
-                    },
-                    Err(error) => {
-                        log::error!("Could not parse manifest data '{:?}': {:?}",path,error);
-                        Default::default()
-                    },
-                },
-                Err(error) => {
-                    log::error!("Could not load manifest file '{:?}': {:?}",path,error);
-                    Default::default()
+        let Some(path) = manifest_path else {
+            return Self::new();
+        };
+        match Self::load::<IO>(path).await {
+            Ok(assets) => assets,
+            Err(error) => {
+                log::error!("Could not load manifest '{:?}': {}",path,error);
+                Self::new()
+            },
+        }
+    }
+
+    /// Loads a manifest file or archive and mounts it over the current manifest. Its hard asset sources are relative to its own directory, or read from its own archive.
+    ///
+    /// Unmount it with `unmount_overlay`.
+    pub async fn mount_manifest<IO: WimpyIO>(&mut self,path: &Path,label: &str) -> Result<OverlayKey,AssetManagerError> {
+        let (json_text,source) = Self::load_manifest_source::<IO>(path).await?;
+        let key = match self.mount_overlay(&json_text,label) {
+            Ok(value) => value,
+            Err(error) => return Err(AssetManagerError::ManifestError(error)),
+        };
+        self.sources.insert(key,source);
+        return Ok(key);
+    }
+
+    async fn load_manifest_source<IO: WimpyIO>(path: &Path) -> Result<(String,AssetSource),AssetManagerError> {
+        if path.extension().is_some_and(|extension|extension == ARCHIVE_EXTENSION) {
+            let archive = match IO::load_binary_file(path).await {
+                Ok(data) => match WamArchive::parse(data) {
+                    Ok(value) => value,
+                    Err(error) => return Err(AssetManagerError::ArchiveError(error)),
                 },
+                Err(error) => return Err(AssetManagerError::FileError(error)),
+            };
+            return match archive.read_text(ARCHIVE_MANIFEST_ENTRY) {
+                Ok(json_text) => Ok((json_text,AssetSource::Archive(Rc::new(archive)))),
+                Err(error) => Err(AssetManagerError::ArchiveError(error)),
+            };
+        }
+        match IO::load_text_file(path).await {
+            Ok(json_text) => {
+                let mut path_buffer = PathBuf::from(path);
+                path_buffer.pop();
+                Ok((json_text,AssetSource::Files(path_buffer)))
             },
-            None => Default::default(),
+            Err(error) => Err(AssetManagerError::FileError(error)),
+        }
+    }
+
+    /// Hard asset sources are relative to the overlay that owns them
+    fn locate(&self,key: HardAssetKey,hard_asset: &HardAsset) -> AssetLocation {
+        let source = self.manifest.hard_asset_overlays.get(key).and_then(|overlay|self.sources.get(*overlay));
+        match source {
+            Some(source) => source.locate(&hard_asset.file_source),
+            None => AssetSource::default().locate(&hard_asset.file_source),
+        }
+    }
+
+    /// Empty if the hard asset was unmounted
+    fn get_hard_asset_source(&self,key: HardAssetKey) -> Rc<str> {
+        match self.manifest.hard_assets.get(key) {
+            Some(hard_asset) => hard_asset.file_source.clone(),
+            None => Rc::from(""),
         }
     }
 
-    async fn get_text_cached<IO: WimpyIO>(&mut self,key: HardAssetKey,name: &'static str) -> Result<Rc<str>,AssetManagerError> {
+    /// Interns the name, the id can be resolved ahead of time and reused
+    pub fn resolve(&mut self,name: &str) -> AssetId {
+        self.manifest.names.intern(name)
+    }
+
+    /// Empty if the id came from a different asset manager
+    pub fn get_name(&self,id: AssetId) -> Rc<str> {
+        match self.manifest.names.get_name(id) {
+            Some(name) => name.clone(),
+            None => Rc::from(""),
+        }
+    }
+
+    async fn get_text_cached<IO: WimpyIO>(&mut self,key: HardAssetKey,id: AssetId) -> Result<Rc<str>,AssetManagerError> {
         if let Some(text) = self.text_cache.get(key) {
             return Ok(text.clone());
         }
 
         let hard_asset = match self.manifest.hard_assets.get(key) {
             Some(value) => value,
-            None => return Err(AssetManagerError::MissingHardAsset(name)),
+            None => return Err(AssetManagerError::MissingHardAsset(self.get_name(id))),
         };
 
         validate_hard_asset_type(hard_asset,HardAssetType::Text)?;
 
-        let path = get_full_path(&self.root,&hard_asset.file_source);
-        let text_data: Rc<str> = Rc::from(match IO::load_text_file(path.as_path()).await {
+        let location = self.locate(key,hard_asset);
+        let text_data: Rc<str> = Rc::from(match location.load_text::<IO>().await {
             Ok(data) => data,
             Err(error) => return Err(AssetManagerError::FileError(error)),
         });
@@ -99,19 +203,103 @@ impl AssetManager {
         Ok(text_data)
     }
 
-    pub async fn get_text_asset<IO: WimpyIO>(name: &'static str,app: &mut WimpyAppContext) -> Result<Rc<str>,AssetManagerError> {
-        let Some(virtual_asset) = app.assets.manifest.text_assets.get(name) else {
-            return Err(AssetManagerError::VirtualAssetNotFound(name));
+    /// Takes a reference on the text, give it back with `release_text_asset`
+    pub async fn get_text_asset<IO: WimpyIO>(id: AssetId,app: &mut WimpyAppContext) -> Result<Rc<str>,AssetManagerError> {
+        let Some(virtual_asset) = app.assets.manifest.text_assets.get(&id) else {
+            return Err(AssetManagerError::VirtualAssetNotFound(app.assets.get_name(id)));
         };
-        Ok(app.assets.get_text_cached::<IO>(virtual_asset.key,name).await?)
+        let key = virtual_asset.key;
+        let text = app.assets.get_text_cached::<IO>(key,id).await?;
+        app.assets.acquire(key);
+        app.assets.hold(id,key);
+        Ok(text)
+    }
+
+    async fn get_binary_cached<IO: WimpyIO>(&mut self,key: HardAssetKey,id: AssetId,expected_type: HardAssetType) -> Result<Rc<[u8]>,AssetManagerError> {
+        let hard_asset = match self.manifest.hard_assets.get(key) {
+            Some(value) => value,
+            None => return Err(AssetManagerError::MissingHardAsset(self.get_name(id))),
+        };
+
+        validate_hard_asset_type(hard_asset,expected_type)?;
+
+        if let Some(data) = self.binary_cache.get(key) {
+            return Ok(data.clone());
+        }
+
+        let location = self.locate(key,hard_asset);
+        let data: Rc<[u8]> = Rc::from(match location.load_binary::<IO>().await {
+            Ok(data) => data,
+            Err(error) => return Err(AssetManagerError::FileError(error)),
+        });
+
+        self.binary_cache.insert(key,data.clone());
+
+        Ok(data)
+    }
+
+    fn get_binary_key(&self,id: AssetId) -> Result<HardAssetKey,AssetManagerError> {
+        match self.manifest.binary_assets.get(&id) {
+            Some(virtual_asset) => Ok(virtual_asset.key),
+            None => Err(AssetManagerError::VirtualAssetNotFound(self.get_name(id))),
+        }
+    }
+
+    /// Takes a reference on the bytes, give it back with `release_binary_asset`
+    pub async fn get_binary_asset<IO: WimpyIO>(id: AssetId,app: &mut WimpyAppContext) -> Result<Rc<[u8]>,AssetManagerError> {
+        let key = app.assets.get_binary_key(id)?;
+        let data = app.assets.get_binary_cached::<IO>(key,id,HardAssetType::Binary).await?;
+        app.assets.acquire(key);
+        app.assets.hold(id,key);
+        Ok(data)
     }
 
-    pub fn get_image_asset(name: &'static str,context: &mut WimpyAppContext,streaming_hint: StreamingHint) -> Result<WimpyTexture,AssetManagerError> {
-        let Some(virtual_asset) = context.assets.manifest.image_assets.get(name) else {
-            return Err(AssetManagerError::VirtualAssetNotFound(name));
+    /// Takes a reference on the encoded audio, give it back with `release_audio_asset`
+    pub async fn get_audio_asset<IO: WimpyIO>(id: AssetId,app: &mut WimpyAppContext) -> Result<AudioAsset,AssetManagerError> {
+        let key = app.assets.get_binary_key(id)?;
+        let data = app.assets.get_binary_cached::<IO>(key,id,HardAssetType::Audio).await?;
+        let Some(format) = AudioFormat::detect(&data) else {
+            /* Don't keep bytes that can't be played unless something else holds them */
+            if !app.assets.ref_counts.contains_key(key) {
+                app.assets.binary_cache.remove(key);
+            }
+            return Err(AssetManagerError::AudioDecodeFailure(app.assets.get_name(id)));
         };
-        let (key,size,area) = (
+        app.assets.acquire(key);
+        app.assets.hold(id,key);
+        Ok(AudioAsset { format, data })
+    }
+
+    /// Deserializes a data asset from its cached bytes. Takes a reference on the bytes, give it back with `release_data_asset`.
+    pub async fn get_data_asset<IO: WimpyIO,T: serde::de::DeserializeOwned>(id: AssetId,app: &mut WimpyAppContext) -> Result<T,AssetManagerError> {
+        let key = app.assets.get_binary_key(id)?;
+        let data = app.assets.get_binary_cached::<IO>(key,id,HardAssetType::Data).await?;
+        let value = match serde_json::from_slice(&data) {
+            Ok(value) => value,
+            Err(error) => {
+                /* Don't keep bytes that can't be parsed unless something else holds them */
+                if !app.assets.ref_counts.contains_key(key) {
+                    app.assets.binary_cache.remove(key);
+                }
+                return Err(AssetManagerError::DataDecodeFailure {
+                    name: app.assets.get_name(id),
+                    error
+                });
+            },
+        };
+        app.assets.acquire(key);
+        app.assets.hold(id,key);
+        Ok(value)
+    }
+
+    /// Takes a reference on the image, give it back with `release_image_asset`
+    pub fn get_image_asset(id: AssetId,context: &mut WimpyAppContext,streaming_hint: StreamingHint) -> Result<WimpyTexture,AssetManagerError> {
+        let Some(virtual_asset) = context.assets.manifest.image_assets.get(&id) else {
+            return Err(AssetManagerError::VirtualAssetNotFound(context.assets.get_name(id)));
+        };
+        let (key,name,size,area) = (
             virtual_asset.key,
+            virtual_asset.name.clone(),
             virtual_asset.size_hint,
             virtual_asset.slice
         );
@@ -119,22 +307,31 @@ impl AssetManager {
             streaming_hint,
             app: context,
         };
-        Ok(texture_key_creator.create_texture(key,name,size,area))
+        let texture = texture_key_creator.create_texture(key,&name,size,area);
+        /* Creation falls back to the missing texture, which has nothing to count */
+        if context.assets.texture_keys.contains_key(key) {
+            context.assets.acquire(key);
+            context.assets.hold(id,key);
+        }
+        Ok(texture)
     }
 
-    pub async fn get_model_asset<IO: WimpyIO>(name: &'static str,app: &mut WimpyAppContext) -> Result<TexturedMesh,AssetManagerError> {
+    /// Takes a reference on the model and its meshlet textures, give it back with `release_model_asset`
+    pub async fn get_model_asset<IO: WimpyIO>(id: AssetId,app: &mut WimpyAppContext) -> Result<TexturedMesh,AssetManagerError> {
 
-        let (hard_asset_key,meshlet_descriptors) = {
-            let Some(virtual_asset) = app.assets.manifest.model_assets.get(name) else {
-                return Err(AssetManagerError::VirtualAssetNotFound(name));
+        let (hard_asset_key,name,meshlet_descriptors) = {
+            let Some(virtual_asset) = app.assets.manifest.model_assets.get(&id) else {
+                return Err(AssetManagerError::VirtualAssetNotFound(app.assets.get_name(id)));
             };
             // I was so profoundly pissed off by the borrow checker that I threw in a clone here
-            (virtual_asset.key,virtual_asset.meshlet_layers.clone())
+            (virtual_asset.key,virtual_asset.name.clone(),virtual_asset.meshlet_layers.clone())
         };
 
         /* We can't use 'entry()' because we mutate the slotmap cache after this to get textures */
-        if let Some(mesh) = app.assets.model_cache.get(hard_asset_key) {
-            return Ok(mesh.clone());
+        if let Some(mesh) = app.assets.model_cache.get(hard_asset_key).cloned() {
+            app.assets.acquire_model(hard_asset_key);
+            app.assets.hold(id,hard_asset_key);
+            return Ok(mesh);
         }
 
         let hard_asset = match app.assets.manifest.hard_assets.get(hard_asset_key) {
@@ -143,14 +340,27 @@ impl AssetManager {
         };
         validate_hard_asset_type(hard_asset,HardAssetType::Model)?;
 
-        let path = get_full_path(&app.assets.root,&hard_asset.file_source);
-        let gltf_data = match IO::load_binary_file(path.as_path()).await {
+        let location = app.assets.locate(hard_asset_key,hard_asset);
+        let gltf_data = match location.load_binary::<IO>().await {
             Ok(data) => data,
             Err(error) => return Err(AssetManagerError::FileError(error)),
         };
 
+        let mesh = Self::insert_model(app,hard_asset_key,&name,&meshlet_descriptors,&gltf_data)?;
+        app.assets.acquire_model(hard_asset_key);
+        app.assets.hold(id,hard_asset_key);
+        Ok(mesh)
+    }
+
+    fn insert_model(
+        app: &mut WimpyAppContext,
+        hard_asset_key: HardAssetKey,
+        name: &str,
+        meshlet_descriptors: &[MeshletTextureLayers],
+        gltf_data: &[u8]
+    ) -> Result<TexturedMesh,AssetManagerError> {
         let queue = app.graphics.graphics_provider.get_queue();
-        let mesh = match app.graphics.mesh_cache.insert_geometry(queue,&gltf_data) {
+        let mesh = match app.graphics.mesh_cache.insert_geometry(queue,gltf_data) {
             Ok(value) => value,
             Err(error) => return Err(AssetManagerError::ModelImportError(error)),
         };
@@ -166,11 +376,17 @@ impl AssetManager {
         };
 
         for (i,meshlet) in mesh.into_iter().enumerate() {
-            let descriptor = &meshlet_descriptors[i];
+            let descriptor = match meshlet_descriptors.get(i) {
+                Some(value) => *value,
+                None => MeshletTextureLayers {
+                    diffuse: None,
+                    lightmap: None
+                },
+            };
 
             let [diffuse,lightmap] = [descriptor.diffuse,descriptor.lightmap].map(|texture_key|{
                 match texture_key {
-                    Some(MeshletTexture { key, size_hint }) => texture_key_creator.create_texture(key,&name,size_hint,None),
+                    Some(MeshletTexture { key, size_hint }) => texture_key_creator.create_texture(key,name,size_hint,None),
                     None => texture_key_creator.get_missing(),
                 }.key
             });
@@ -182,10 +398,513 @@ impl AssetManager {
             });
         }
 
-        let reference = app.graphics.mesh_cache.create_textured_mesh_reference(textured_mesh);
+        let reference = match app.assets.model_cache.get(hard_asset_key).cloned() {
+            /* A reload keeps the existing reference, the old geometry is freed */
+            Some(reference) => app.graphics.mesh_cache.replace_textured_mesh(reference,textured_mesh),
+            None => app.graphics.mesh_cache.create_textured_mesh_reference(textured_mesh),
+        };
         app.assets.model_cache.insert(hard_asset_key,reference.clone());
+        app.assets.model_textures.insert(hard_asset_key,get_meshlet_texture_keys(meshlet_descriptors).collect());
         Ok(reference)
     }
+
+    /// Text that is already loaded, such as by a preload group. Never loads from storage.
+    pub fn get_cached_text(&self,id: AssetId) -> Option<Rc<str>> {
+        let virtual_asset = self.manifest.text_assets.get(&id)?;
+        self.text_cache.get(virtual_asset.key).cloned()
+    }
+
+    /// A model that is already loaded, such as by a preload group. Never loads from storage.
+    pub fn get_cached_model(&self,id: AssetId) -> Option<TexturedMesh> {
+        let virtual_asset = self.manifest.model_assets.get(&id)?;
+        self.model_cache.get(virtual_asset.key).cloned()
+    }
+
+    /// Audio, binary or data that is already loaded, such as by a preload group. Never loads from storage.
+    pub fn get_cached_binary(&self,id: AssetId) -> Option<Rc<[u8]>> {
+        let virtual_asset = self.manifest.binary_assets.get(&id)?;
+        self.binary_cache.get(virtual_asset.key).cloned()
+    }
+
+    /// Resolves the group against the manifest and queues everything that is not already loaded.
+    ///
+    /// Names that are not in the manifest are counted as failures.
+    pub fn preload(group: &PreloadGroup,app: &mut WimpyAppContext) -> PreloadGroupKey {
+        let group_key = app.assets.preload.create_group();
+
+        let mut ids: Vec<AssetId> = group.ids.clone();
+        ids.extend(group.names.iter().map(|name|app.assets.resolve(name)));
+        for prefix in group.prefixes.iter() {
+            let manifest = &app.assets.manifest;
+            let mut matches: Vec<(&Rc<str>,AssetId)> = manifest.text_assets.iter().map(|(id,asset)|(&asset.name,*id))
+                .chain(manifest.image_assets.iter().map(|(id,asset)|(&asset.name,*id)))
+                .chain(manifest.model_assets.iter().map(|(id,asset)|(&asset.name,*id)))
+                .chain(manifest.binary_assets.iter().map(|(id,asset)|(&asset.name,*id)))
+                .filter(|(name,_)|name.starts_with(prefix.as_str()))
+                .collect();
+            /* Hash map order changes between runs, the load order shouldn't */
+            matches.sort();
+            ids.extend(matches.into_iter().map(|(_,id)|id));
+        }
+
+        for id in ids {
+            if !Self::preload_virtual_asset(group_key,id,app) {
+                log::warn!("Preload asset '{}' not found",app.assets.get_name(id));
+                app.assets.preload.add_failure(group_key);
+            }
+        }
+        group_key
+    }
+
+    fn preload_virtual_asset(group: PreloadGroupKey,id: AssetId,app: &mut WimpyAppContext) -> bool {
+        let assets = &mut app.assets;
+        if let Some(text) = assets.manifest.text_assets.get(&id) {
+            let key = text.key;
+            let loaded = assets.text_cache.contains_key(key);
+            assets.track_preload(group,key,PreloadKind::Text,loaded);
+            return true;
+        }
+        if let Some(image) = assets.manifest.image_assets.get(&id) {
+            let (key,name,size,slice) = (image.key,image.name.clone(),image.size_hint,image.slice);
+            Self::preload_image(group,key,&name,size,slice,StreamingHint::None,app);
+            return true;
+        }
+        if let Some(model) = assets.manifest.model_assets.get(&id) {
+            let key = model.key;
+            let name = model.name.clone();
+            let textures: Vec<MeshletTexture> = model.meshlet_layers.iter()
+                .flat_map(|layers|[layers.diffuse,layers.lightmap])
+                .flatten()
+                .collect();
+            let loaded = assets.model_cache.contains_key(key);
+            assets.track_preload(group,key,PreloadKind::Model { id },loaded);
+            /* Same hint as `insert_model`, whichever binds the texture first decides it */
+            for texture in textures {
+                Self::preload_image(group,texture.key,&name,texture.size_hint,None,StreamingHint::Atlas,app);
+            }
+            return true;
+        }
+        if let Some(binary) = assets.manifest.binary_assets.get(&id) {
+            let key = binary.key;
+            let loaded = assets.binary_cache.contains_key(key);
+            assets.track_preload(group,key,PreloadKind::Binary,loaded);
+            return true;
+        }
+        return false;
+    }
+
+    fn preload_image(
+        group: PreloadGroupKey,
+        key: HardAssetKey,
+        name: &str,
+        size: UWimpyPoint,
+        slice: Option<WimpyPointRect>,
+        streaming_hint: StreamingHint,
+        app: &mut WimpyAppContext
+    ) {
+        let texture = TextureKeyCreator { app, streaming_hint }.create_texture(key,name,size,slice);
+        if !app.assets.texture_keys.contains_key(key) {
+            app.assets.preload.add_failure(group);
+            return;
+        }
+        let loaded = matches!(
+            app.graphics.texture_manager.get_no_touch(texture.key),
+            Ok(TextureCacheEntry { load_state: TextureLoadState::Loaded, .. })
+        );
+        app.assets.track_preload(group,key,PreloadKind::Image,loaded);
+    }
+
+    /// Adds the asset to the group, which holds a reference on it until the group is released
+    fn track_preload(&mut self,group: PreloadGroupKey,key: HardAssetKey,kind: PreloadKind,loaded: bool) {
+        let Some(hard_asset) = self.manifest.hard_assets.get(key) else {
+            self.preload.add_failure(group);
+            return;
+        };
+        let added = match loaded {
+            true => self.preload.add_loaded(group,key),
+            false => {
+                let location = self.locate(key,hard_asset);
+                self.preload.add_request(group,PreloadRequest { key, kind, location })
+            },
+        };
+        if added {
+            self.acquire(key);
+        }
+    }
+
+    /// Requests are handed out in the order they were queued
+    pub fn take_preload_requests(&mut self,limit: usize) -> Vec<PreloadRequest> {
+        self.preload.take_requests(limit)
+    }
+
+    /// Turns the texture manager's stream requests into file loads, their results also go to `complete_preload`
+    pub fn take_stream_requests(limit: usize,app: &mut WimpyAppContext) -> Vec<PreloadRequest> {
+        let streams = app.graphics.texture_manager.take_stream_requests(limit);
+        let mut requests = Vec::with_capacity(streams.len());
+        for stream in streams {
+            let Some(hard_asset) = app.assets.manifest.hard_assets.get(stream.wam_key) else {
+                app.graphics.texture_manager.fail_stream(stream.texture,stream.generation);
+                continue;
+            };
+            requests.push(PreloadRequest {
+                key: stream.wam_key,
+                kind: PreloadKind::Stream {
+                    texture: stream.texture,
+                    generation: stream.generation
+                },
+                location: app.assets.locate(stream.wam_key,hard_asset)
+            });
+        }
+        return requests;
+    }
+
+    pub fn complete_preload(result: PreloadResult,app: &mut WimpyAppContext) {
+        let key = result.key;
+        /* Streams don't belong to a preload group */
+        let data = match result.data {
+            Ok(PreloadData::Stream { texture, generation, data }) => {
+                if let Err(error) = Self::insert_stream(texture,generation,data,app) {
+                    let subject = format!("Stream of '{}'",app.assets.get_hard_asset_source(key));
+                    app.report_asset_failure(&subject,&error);
+                }
+                return;
+            },
+            data => data,
+        };
+        if !app.assets.preload.is_wanted(key) || !app.assets.manifest.hard_assets.contains_key(key) {
+            app.assets.preload.finish(key,None);
+            return;
+        }
+        let bytes_loaded = match Self::insert_preload_data(key,data,app) {
+            Ok(bytes) => Some(bytes),
+            Err(error) => {
+                let subject = format!("Preload of '{}'",app.assets.get_hard_asset_source(key));
+                app.report_asset_failure(&subject,&error);
+                None
+            },
+        };
+        app.assets.preload.finish(key,bytes_loaded);
+    }
+
+    /// Results for a generation the texture no longer wants are dropped and count as zero bytes
+    fn insert_stream(
+        texture: WimpyTextureKey,
+        generation: u8,
+        data: Result<WimpyImageData<'static>,FileError>,
+        app: &mut WimpyAppContext
+    ) -> Result<u64,AssetManagerError> {
+        let graphics = &mut app.graphics;
+        let data = match data {
+            Ok(value) => value,
+            Err(error) => {
+                graphics.texture_manager.fail_stream(texture,generation);
+                return Err(AssetManagerError::FileError(error));
+            },
+        };
+        let size = data.size();
+        match graphics.texture_manager.upload_streamed_texture(&graphics.graphics_provider,texture,generation,data) {
+            Ok(true) => Ok(size.x as u64 * size.y as u64 * 4),
+            Ok(false) => Ok(0),
+            Err(error) => Err(AssetManagerError::TextureUploadFailure(error)),
+        }
+    }
+
+    fn insert_preload_data(key: HardAssetKey,data: Result<PreloadData,FileError>,app: &mut WimpyAppContext) -> Result<u64,AssetManagerError> {
+        let data = match data {
+            Ok(value) => value,
+            Err(error) => return Err(AssetManagerError::FileError(error)),
+        };
+        match data {
+            PreloadData::Text(text) => {
+                let bytes = text.len() as u64;
+                app.assets.text_cache.insert(key,Rc::from(text));
+                Ok(bytes)
+            },
+            PreloadData::Image(data) => {
+                /* A texture evicted while the load was in flight doesn't want the data anymore */
+                let Some(texture) = app.assets.texture_keys.get(key).map(|texture|texture.key) else {
+                    return Ok(0);
+                };
+                let size = data.size();
+                let graphics = &mut app.graphics;
+                match graphics.texture_manager.upload_wam_texture(&graphics.graphics_provider,texture,data) {
+                    Ok(()) => Ok(size.x as u64 * size.y as u64 * 4),
+                    Err(error) => Err(AssetManagerError::TextureUploadFailure(error)),
+                }
+            },
+            PreloadData::Model { id, data } => {
+                let (name,meshlet_descriptors) = match app.assets.manifest.model_assets.get(&id) {
+                    Some(model) => (model.name.clone(),model.meshlet_layers.clone()),
+                    None => (app.assets.get_name(id),Vec::new()),
+                };
+                Self::insert_model(app,key,&name,&meshlet_descriptors,&data)?;
+                Ok(data.len() as u64)
+            },
+            PreloadData::Binary(data) => {
+                let bytes = data.len() as u64;
+                app.assets.binary_cache.insert(key,Rc::from(data));
+                Ok(bytes)
+            },
+            PreloadData::Stream { texture, generation, data } => Self::insert_stream(texture,generation,data,app),
+        }
+    }
+
+    pub fn get_preload_progress(&self,group: PreloadGroupKey) -> Option<PreloadProgress> {
+        self.preload.get_progress(group)
+    }
+
+    /// The sum of every group that has not been released
+    pub fn get_total_preload_progress(&self) -> PreloadProgress {
+        self.preload.get_total_progress()
+    }
+
+    /// Compare against an earlier value to find out if the mounted overlays, and with them `get_hard_asset_paths`, changed
+    pub fn get_overlay_revision(&self) -> u32 {
+        self.overlay_revision
+    }
+
+    /// Paths of every hard asset in the manifest, such as for a file watcher. Empty when the assets come from an archive.
+    pub fn get_hard_asset_paths(&self) -> Vec<(HardAssetKey,PathBuf)> {
+        self.manifest.hard_assets.iter().filter_map(|(key,hard_asset)|{
+            let location = self.locate(key,hard_asset);
+            location.get_path().map(|path|(key,path.to_path_buf()))
+        }).collect()
+    }
+
+    /// A load that refreshes a hard asset that is already cached, or `None` if nothing has loaded it yet.
+    ///
+    /// Uses the same request type as preloading, the result goes to `complete_reload`.
+    pub fn create_reload_request(&self,key: HardAssetKey) -> Option<PreloadRequest> {
+        let hard_asset = self.manifest.hard_assets.get(key)?;
+        let kind = match hard_asset.data_type {
+            HardAssetType::Text => match self.text_cache.contains_key(key) {
+                true => PreloadKind::Text,
+                false => return None,
+            },
+            HardAssetType::Image => match self.texture_keys.contains_key(key) {
+                true => PreloadKind::Image,
+                false => return None,
+            },
+            HardAssetType::Model => {
+                if !self.model_cache.contains_key(key) {
+                    return None;
+                }
+                let (id,_) = self.manifest.model_assets.iter().find(|(_,model)|model.key == key)?;
+                PreloadKind::Model {
+                    id: *id
+                }
+            },
+            HardAssetType::Audio | HardAssetType::Binary | HardAssetType::Data => match self.binary_cache.contains_key(key) {
+                true => PreloadKind::Binary,
+                false => return None,
+            },
+        };
+        Some(PreloadRequest {
+            key,
+            kind,
+            location: self.locate(key,hard_asset)
+        })
+    }
+
+    /// Replaces cached data in place: text in the text cache, the GPU view behind the existing texture key, and the meshlets behind the existing textured mesh
+    pub fn complete_reload(result: PreloadResult,app: &mut WimpyAppContext) {
+        let key = result.key;
+        match Self::insert_preload_data(key,result.data,app) {
+            Ok(_) => {
+                if let Some(hard_asset) = app.assets.manifest.hard_assets.get(key) {
+                    log::info!("Reloaded hard asset '{}'",hard_asset.file_source);
+                }
+                /* Breaking the asset again is a new failure */
+                let subject = format!("Reload of '{}'",app.assets.get_hard_asset_source(key));
+                app.debug_shell.forget_asset_failure(&subject);
+            },
+            Err(error) => {
+                let subject = format!("Reload of '{}'",app.assets.get_hard_asset_source(key));
+                app.report_asset_failure(&subject,&error);
+            },
+        }
+    }
+
+    /// Stops tracking the group and gives back its references. Assets that nothing else holds are evicted.
+    pub fn release_preload_group(group: PreloadGroupKey,app: &mut WimpyAppContext) {
+        for key in app.assets.preload.release(group) {
+            Self::release(key,app);
+        }
+    }
+
+    /// Gives back a reference taken by `get_text_asset`
+    pub fn release_text_asset(id: AssetId,app: &mut WimpyAppContext) {
+        if let Some(key) = app.assets.take_held(id) {
+            Self::release(key,app);
+        }
+    }
+
+    /// Gives back a reference taken by `get_binary_asset`
+    pub fn release_binary_asset(id: AssetId,app: &mut WimpyAppContext) {
+        if let Some(key) = app.assets.take_held(id) {
+            Self::release(key,app);
+        }
+    }
+
+    /// Gives back a reference taken by `get_audio_asset`
+    pub fn release_audio_asset(id: AssetId,app: &mut WimpyAppContext) {
+        if let Some(key) = app.assets.take_held(id) {
+            Self::release(key,app);
+        }
+    }
+
+    /// Gives back a reference taken by `get_data_asset`
+    pub fn release_data_asset(id: AssetId,app: &mut WimpyAppContext) {
+        if let Some(key) = app.assets.take_held(id) {
+            Self::release(key,app);
+        }
+    }
+
+    /// Gives back a reference taken by `get_image_asset`. Once evicted, copies of the texture draw as the missing texture.
+    pub fn release_image_asset(id: AssetId,app: &mut WimpyAppContext) {
+        if let Some(key) = app.assets.take_held(id) {
+            Self::release(key,app);
+        }
+    }
+
+    /// Gives back a reference taken by `get_model_asset`, including its meshlet textures. Once evicted, the textured mesh draws nothing.
+    pub fn release_model_asset(id: AssetId,app: &mut WimpyAppContext) {
+        let Some(key) = app.assets.take_held(id) else {
+            return;
+        };
+        let textures = app.assets.model_textures.get(key).cloned().unwrap_or_default();
+        Self::release(key,app);
+        for texture in textures {
+            Self::release(texture,app);
+        }
+    }
+
+    /// References held on the hard asset behind a virtual asset, by handles and preload groups
+    pub fn get_reference_count(&self,id: AssetId) -> u32 {
+        let key = if let Some(asset) = self.manifest.text_assets.get(&id) {
+            asset.key
+        } else if let Some(asset) = self.manifest.image_assets.get(&id) {
+            asset.key
+        } else if let Some(asset) = self.manifest.model_assets.get(&id) {
+            asset.key
+        } else if let Some(asset) = self.manifest.binary_assets.get(&id) {
+            asset.key
+        } else {
+            return 0;
+        };
+        self.ref_counts.get(key).copied().unwrap_or(0)
+    }
+
+    fn acquire(&mut self,key: HardAssetKey) {
+        match self.ref_counts.get_mut(key) {
+            Some(count) => *count += 1,
+            None => {
+                self.ref_counts.insert(key,1);
+            },
+        }
+    }
+
+    fn acquire_model(&mut self,key: HardAssetKey) {
+        self.acquire(key);
+        let Some(textures) = self.model_textures.get(key).cloned() else {
+            return;
+        };
+        for texture in textures {
+            self.acquire(texture);
+        }
+    }
+
+    fn hold(&mut self,id: AssetId,key: HardAssetKey) {
+        self.held.entry(id).or_default().push(key);
+    }
+
+    fn take_held(&mut self,id: AssetId) -> Option<HardAssetKey> {
+        let key = self.held.get_mut(&id).and_then(|keys|keys.pop());
+        if key.is_none() {
+            log::warn!("Release of '{}' without a matching get",self.get_name(id));
+        }
+        return key;
+    }
+
+    fn release(key: HardAssetKey,app: &mut WimpyAppContext) {
+        let Some(count) = app.assets.ref_counts.get_mut(key) else {
+            /* Unmounted assets were already evicted */
+            if app.assets.manifest.hard_assets.contains_key(key) {
+                log::warn!("Release of a hard asset that holds no references");
+            }
+            return;
+        };
+        *count -= 1;
+        if *count > 0 {
+            return;
+        }
+        app.assets.ref_counts.remove(key);
+        Self::evict(key,app);
+    }
+
+    /// Doesn't look at the manifest, the hard asset may have been unmounted
+    fn evict(key: HardAssetKey,app: &mut WimpyAppContext) {
+        let assets = &mut app.assets;
+        assets.text_cache.remove(key);
+        assets.binary_cache.remove(key);
+        if let Some(texture) = assets.texture_keys.remove(key) &&
+            let Err(error) = app.graphics.texture_manager.remove_wam_texture(texture.key)
+        {
+            log::error!("Texture eviction failure: {:?}",error);
+        }
+        if let Some(mesh) = assets.model_cache.remove(key) {
+            app.graphics.mesh_cache.remove_textured_mesh(mesh);
+        }
+        assets.model_textures.remove(key);
+    }
+
+    /// Mounts manifest data over the current manifest, see `WamManifest::mount_overlay`. Its hard asset sources are relative to the root of the first mounted manifest.
+    pub fn mount_overlay(&mut self,json_text: &str,label: &str) -> Result<OverlayKey,WamManifestError> {
+        let base_source = self.manifest.get_overlays().next().and_then(|(key,_)|self.sources.get(key)).cloned();
+        let key = self.manifest.mount_overlay(json_text,label)?;
+        self.overlay_revision = self.overlay_revision.wrapping_add(1);
+        if let Some(source) = base_source {
+            self.sources.insert(key,source);
+        }
+        for item in self.manifest.get_overrides().iter().filter(|item|item.winner == key && item.hidden != key) {
+            let hidden = self.manifest.get_overlay(item.hidden).map(|overlay|overlay.label.clone()).unwrap_or_default();
+            log::info!("Virtual asset '{}' from overlay '{label}' overrides overlay '{hidden}'",self.get_name(item.id));
+        }
+        Ok(key)
+    }
+
+    /// Unmounts the overlay and evicts its cached assets, even the ones that are still referenced. Names it overrode resolve to earlier overlays again.
+    pub fn unmount_overlay(key: OverlayKey,app: &mut WimpyAppContext) -> bool {
+        let Some(removed) = app.assets.manifest.unmount_overlay(key) else {
+            return false;
+        };
+        app.assets.sources.remove(key);
+        app.assets.overlay_revision = app.assets.overlay_revision.wrapping_add(1);
+        let removed_set: HashSet<HardAssetKey> = removed.iter().copied().collect();
+        for hard_asset_key in removed {
+            let count = app.assets.ref_counts.remove(hard_asset_key).unwrap_or(0);
+            /* A model can reference textures from other overlays, those references go back */
+            let textures = app.assets.model_textures.get(hard_asset_key).cloned().unwrap_or_default();
+            for texture in textures.into_iter().filter(|texture|!removed_set.contains(texture)) {
+                for _ in 0..count {
+                    Self::release(texture,app);
+                }
+            }
+            Self::evict(hard_asset_key,app);
+        }
+        let assets = &mut app.assets;
+        for keys in assets.held.values_mut() {
+            keys.retain(|key|!removed_set.contains(key));
+        }
+        return true;
+    }
+}
+
+fn get_meshlet_texture_keys(meshlet_descriptors: &[MeshletTextureLayers]) -> impl Iterator<Item = HardAssetKey> + '_ {
+    meshlet_descriptors.iter()
+        .flat_map(|layers|[layers.diffuse,layers.lightmap])
+        .flatten()
+        .map(|texture|texture.key)
 }
 
 struct TextureKeyCreator<'a> {
@@ -201,7 +920,7 @@ impl TextureKeyCreator<'_> {
     fn create_texture(
         &mut self,
         hard_asset_key: HardAssetKey,
-        name: &'static str,
+        name: &str,
         size: UWimpyPoint,
         slice: Option<WimpyPointRect>
     ) -> WimpyTexture {
@@ -224,9 +943,11 @@ impl TextureKeyCreator<'_> {
 
         let texture = self.app.graphics.texture_manager.bind_wam_asset(TextureCreationParameters {
             wam_id: hard_asset.clone(),
+            wam_key: hard_asset_key,
             policy_hint: self.streaming_hint,
             slice,
             size_hint: size,
+            mipmaps: self.app.assets.manifest.mipmapped_images.contains(&hard_asset_key),
         });
 
         self.app.assets.texture_keys.insert(hard_asset_key,texture.clone());
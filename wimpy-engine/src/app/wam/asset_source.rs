@@ -0,0 +1,111 @@
+use std::{path::{Path, PathBuf}, rc::Rc};
+
+use crate::app::{FileError, WimpyIO, WimpyImageData, graphics::textures::{CompressedImage, KTX2_EXTENSION}};
+use super::{ArchiveError, WamArchive};
+
+/// Where the hard assets of the manifest are read from
+#[derive(Clone)]
+pub(super) enum AssetSource {
+    /// Loose files, relative to the directory of the manifest
+    Files(PathBuf),
+    Archive(Rc<WamArchive>),
+}
+
+impl Default for AssetSource {
+    fn default() -> Self {
+        Self::Files(PathBuf::new())
+    }
+}
+
+fn get_full_path(root: &PathBuf,hard_asset_path: &str) -> PathBuf {
+    let mut path_buffer = PathBuf::new();
+    path_buffer.push(root);
+    for component in hard_asset_path.split('/') {
+        path_buffer.push(component);
+    }
+    return path_buffer;
+}
+
+impl AssetSource {
+    pub fn locate(&self,hard_asset_path: &Rc<str>) -> AssetLocation {
+        match self {
+            AssetSource::Files(root) => AssetLocation::File(get_full_path(root,hard_asset_path)),
+            AssetSource::Archive(archive) => AssetLocation::Archive {
+                archive: archive.clone(),
+                entry: hard_asset_path.clone()
+            },
+        }
+    }
+}
+
+/// A hard asset file, either on storage or inside an archive
+pub(super) enum AssetLocation {
+    File(PathBuf),
+    Archive {
+        archive: Rc<WamArchive>,
+        entry: Rc<str>
+    },
+}
+
+fn map_archive_error(error: ArchiveError) -> FileError {
+    match error {
+        ArchiveError::EntryNotFound(_) => FileError::NotFound,
+        error => {
+            log::error!("Archive read error: {:?}",error);
+            FileError::DecodeFailure
+        },
+    }
+}
+
+impl AssetLocation {
+    /// `None` for archive entries, they can't change on their own
+    pub fn get_path(&self) -> Option<&Path> {
+        match self {
+            AssetLocation::File(path) => Some(path),
+            AssetLocation::Archive { .. } => None,
+        }
+    }
+
+    fn is_ktx2(&self) -> bool {
+        let extension = match self {
+            AssetLocation::File(path) => path.extension().and_then(|extension|extension.to_str()),
+            AssetLocation::Archive { entry, .. } => entry.rsplit_once('.').map(|(_,extension)|extension),
+        };
+        extension.is_some_and(|extension|extension.eq_ignore_ascii_case(KTX2_EXTENSION))
+    }
+
+    pub async fn load_binary<IO: WimpyIO>(&self) -> Result<Vec<u8>,FileError> {
+        match self {
+            AssetLocation::File(path) => IO::load_binary_file(path).await,
+            AssetLocation::Archive { archive, entry } => archive.read(entry).map_err(map_archive_error),
+        }
+    }
+
+    pub async fn load_text<IO: WimpyIO>(&self) -> Result<String,FileError> {
+        match self {
+            AssetLocation::File(path) => IO::load_text_file(path).await,
+            AssetLocation::Archive { archive, entry } => archive.read_text(entry).map_err(map_archive_error),
+        }
+    }
+
+    /// KTX2 containers are read by the engine, every other image is decoded by `WimpyIO`
+    pub async fn load_image<IO: WimpyIO>(&self) -> Result<WimpyImageData<'static>,FileError> {
+        if self.is_ktx2() {
+            let data = self.load_binary::<IO>().await?;
+            return match CompressedImage::from_ktx2(&data) {
+                Ok(image) => Ok(WimpyImageData::Compressed { image }),
+                Err(error) => {
+                    log::error!("KTX2 read error: {:?}",error);
+                    Err(FileError::DecodeFailure)
+                },
+            };
+        }
+        match self {
+            AssetLocation::File(path) => IO::load_image_file(path).await,
+            AssetLocation::Archive { archive, entry } => match archive.read(entry) {
+                Ok(data) => IO::decode_image(data).await,
+                Err(error) => Err(map_archive_error(error)),
+            },
+        }
+    }
+}
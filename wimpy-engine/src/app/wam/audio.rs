@@ -0,0 +1,62 @@
+use std::rc::Rc;
+
+/// Container formats recognized by their header. The engine doesn't decode audio, that is left to the platform's mixer.
+#[derive(Debug,Copy,Clone,PartialEq,Eq)]
+pub enum AudioFormat {
+    Wave,
+    Ogg,
+    Flac,
+    Mp3,
+}
+
+impl AudioFormat {
+    /// `None` if the data doesn't start with a known header
+    pub fn detect(data: &[u8]) -> Option<Self> {
+        if data.len() >= 12 && &data[0..4] == b"RIFF" && &data[8..12] == b"WAVE" {
+            return Some(AudioFormat::Wave);
+        }
+        if data.starts_with(b"OggS") {
+            return Some(AudioFormat::Ogg);
+        }
+        if data.starts_with(b"fLaC") {
+            return Some(AudioFormat::Flac);
+        }
+        /* An ID3 tag, or the sync bits of a bare MPEG frame */
+        if data.starts_with(b"ID3") || (data.len() >= 2 && data[0] == 0xFF && data[1] & 0xE0 == 0xE0) {
+            return Some(AudioFormat::Mp3);
+        }
+        return None;
+    }
+}
+
+#[derive(Debug,Clone)]
+pub struct AudioAsset {
+    pub format: AudioFormat,
+    /// Still encoded, shared with the asset cache
+    pub data: Rc<[u8]>,
+}
+
+const SILENCE_SAMPLE_RATE: u32 = 44100;
+
+impl AudioAsset {
+    /// An empty 16-bit mono wave, the placeholder for audio that failed to load
+    pub fn silence() -> Self {
+        let mut data = Vec::with_capacity(44);
+        data.extend_from_slice(b"RIFF");
+        data.extend_from_slice(&36u32.to_le_bytes());
+        data.extend_from_slice(b"WAVEfmt ");
+        data.extend_from_slice(&16u32.to_le_bytes());
+        data.extend_from_slice(&1u16.to_le_bytes()); // PCM
+        data.extend_from_slice(&1u16.to_le_bytes()); // Channels
+        data.extend_from_slice(&SILENCE_SAMPLE_RATE.to_le_bytes());
+        data.extend_from_slice(&(SILENCE_SAMPLE_RATE * 2).to_le_bytes()); // Byte rate
+        data.extend_from_slice(&2u16.to_le_bytes()); // Block align
+        data.extend_from_slice(&16u16.to_le_bytes()); // Bits per sample
+        data.extend_from_slice(b"data");
+        data.extend_from_slice(&0u32.to_le_bytes());
+        return Self {
+            format: AudioFormat::Wave,
+            data: Rc::from(data),
+        };
+    }
+}
@@ -55,4 +55,6 @@ pub struct SizeHint {
     pub id: u32,
     pub x: u32,
     pub y: u32,
+    #[serde(default)]
+    pub mipmaps: bool,
 }
@@ -0,0 +1,269 @@
+const START_PRELOAD_GROUP_CAPACITY: usize = 4;
+const START_PRELOAD_QUEUE_CAPACITY: usize = 32;
+
+use std::collections::{HashSet, VecDeque};
+use slotmap::SlotMap;
+
+use crate::app::{FileError, WimpyIO, WimpyImageData, graphics::textures::WimpyTextureKey};
+use super::{AssetId, HardAssetKey, asset_source::AssetLocation};
+
+slotmap::new_key_type! {
+    pub struct PreloadGroupKey;
+}
+
+/// A batch of virtual assets to load ahead of time, such as everything a level needs.
+///
+/// Start it with `WimpyAppContext::preload_assets`, poll it with `AssetManager::get_preload_progress`.
+#[derive(Debug,Default,Clone)]
+pub struct PreloadGroup {
+    pub(super) ids:         Vec<AssetId>,
+    pub(super) names:       Vec<String>,
+    pub(super) prefixes:    Vec<String>,
+}
+
+impl PreloadGroup {
+    /// A virtual asset that was already resolved
+    pub fn add_id(mut self,id: AssetId) -> Self {
+        self.ids.push(id);
+        self
+    }
+
+    /// A full virtual asset name (e.g., `wimpy/font/classic`)
+    pub fn add_name(mut self,name: &str) -> Self {
+        self.names.push(name.to_string());
+        self
+    }
+
+    /// Every virtual asset whose name starts with `prefix` (e.g., `level-1/` for a whole namespace)
+    pub fn add_prefix(mut self,prefix: &str) -> Self {
+        self.prefixes.push(prefix.to_string());
+        self
+    }
+}
+
+#[derive(Debug,Default,Clone,Copy,PartialEq,Eq)]
+pub struct PreloadProgress {
+    /// Hard assets in the group, a model's meshlet textures are counted separately
+    pub total:          usize,
+    pub loaded:         usize,
+    pub failed:         usize,
+    /// Data read from storage by this group's loads. Images count their decoded RGBA8 size.
+    pub bytes_loaded:   u64,
+}
+
+impl PreloadProgress {
+    pub fn is_complete(&self) -> bool {
+        self.loaded + self.failed >= self.total
+    }
+
+    /// From 0 to 1, failures count towards completion
+    pub fn fraction(&self) -> f32 {
+        if self.total == 0 {
+            return 1.0;
+        }
+        (self.loaded + self.failed) as f32 / self.total as f32
+    }
+
+    fn add(&mut self,other: &PreloadProgress) {
+        self.total += other.total;
+        self.loaded += other.loaded;
+        self.failed += other.failed;
+        self.bytes_loaded += other.bytes_loaded;
+    }
+}
+
+pub(super) enum PreloadKind {
+    Text,
+    /// The texture key is looked up when the load completes, the asset can be unmounted and bound again in the meantime
+    Image,
+    Model {
+        /// The virtual model that provides the meshlet descriptors
+        id: AssetId
+    },
+    /// Audio, binary and data
+    Binary,
+    /// A texture that was touched while unloaded, see `TextureManager::take_stream_requests`
+    Stream {
+        texture: WimpyTextureKey,
+        generation: u8
+    },
+}
+
+/// A file load handed to the platform by `WimpyAppContext::take_preload_requests`
+pub struct PreloadRequest {
+    pub(super) key:         HardAssetKey,
+    pub(super) kind:        PreloadKind,
+    pub(super) location:    AssetLocation,
+}
+
+pub(super) enum PreloadData {
+    Text(String),
+    Image(WimpyImageData<'static>),
+    Model {
+        id: AssetId,
+        data: Vec<u8>
+    },
+    Binary(Vec<u8>),
+    /// A failed stream still has to reach its texture, so the file error is kept inside
+    Stream {
+        texture: WimpyTextureKey,
+        generation: u8,
+        data: Result<WimpyImageData<'static>,FileError>
+    },
+}
+
+/// The outcome of `PreloadRequest::load`, returned to the engine with `WimpyAppContext::complete_preload`
+pub struct PreloadResult {
+    pub(super) key:     HardAssetKey,
+    pub(super) data:    Result<PreloadData,FileError>,
+}
+
+impl PreloadRequest {
+    /// Does not touch the app context, so the platform is free to run it in the background
+    pub async fn load<IO: WimpyIO>(self) -> PreloadResult {
+        let data = match self.kind {
+            PreloadKind::Text => self.location.load_text::<IO>().await.map(PreloadData::Text),
+            PreloadKind::Image => self.location.load_image::<IO>().await.map(PreloadData::Image),
+            PreloadKind::Model { id } => self.location.load_binary::<IO>().await.map(|data|PreloadData::Model { id, data }),
+            PreloadKind::Binary => self.location.load_binary::<IO>().await.map(PreloadData::Binary),
+            PreloadKind::Stream { texture, generation } => Ok(PreloadData::Stream {
+                texture,
+                generation,
+                data: self.location.load_image::<IO>().await
+            }),
+        };
+        PreloadResult {
+            key: self.key,
+            data
+        }
+    }
+}
+
+#[derive(Default)]
+struct PreloadGroupState {
+    /// Every hard asset in the group, loaded or not
+    assets:     HashSet<HardAssetKey>,
+    pending:    HashSet<HardAssetKey>,
+    progress:   PreloadProgress,
+}
+
+pub(super) struct PreloadQueue {
+    groups:     SlotMap<PreloadGroupKey,PreloadGroupState>,
+    queue:      VecDeque<PreloadRequest>,
+    /// Queued or in flight. A group that wants an asset that is already requested waits on the same load.
+    requested:  HashSet<HardAssetKey>,
+}
+
+impl Default for PreloadQueue {
+    fn default() -> Self {
+        Self {
+            groups:     SlotMap::with_capacity_and_key(START_PRELOAD_GROUP_CAPACITY),
+            queue:      VecDeque::with_capacity(START_PRELOAD_QUEUE_CAPACITY),
+            requested:  HashSet::with_capacity(START_PRELOAD_QUEUE_CAPACITY),
+        }
+    }
+}
+
+impl PreloadQueue {
+    pub fn create_group(&mut self) -> PreloadGroupKey {
+        self.groups.insert(Default::default())
+    }
+
+    /// An asset that could not be resolved, there is nothing to load
+    pub fn add_failure(&mut self,group: PreloadGroupKey) {
+        let Some(state) = self.groups.get_mut(group) else {
+            return;
+        };
+        state.progress.total += 1;
+        state.progress.failed += 1;
+    }
+
+    /// An asset that is already in its cache. Returns `false` if the group already has it.
+    pub fn add_loaded(&mut self,group: PreloadGroupKey,key: HardAssetKey) -> bool {
+        let Some(state) = self.groups.get_mut(group) else {
+            return false;
+        };
+        if !state.assets.insert(key) {
+            return false;
+        }
+        state.progress.total += 1;
+        state.progress.loaded += 1;
+        return true;
+    }
+
+    /// Returns `false` if the group already has the asset
+    pub fn add_request(&mut self,group: PreloadGroupKey,request: PreloadRequest) -> bool {
+        let Some(state) = self.groups.get_mut(group) else {
+            return false;
+        };
+        if !state.assets.insert(request.key) {
+            return false;
+        }
+        state.progress.total += 1;
+        state.pending.insert(request.key);
+        if self.requested.insert(request.key) {
+            self.queue.push_back(request);
+        }
+        return true;
+    }
+
+    pub fn take_requests(&mut self,limit: usize) -> Vec<PreloadRequest> {
+        let count = limit.min(self.queue.len());
+        self.queue.drain(..count).collect()
+    }
+
+    /// If no group is waiting on `key` (e.g., its groups were released), the result should be discarded
+    pub fn is_wanted(&self,key: HardAssetKey) -> bool {
+        self.groups.values().any(|state|state.pending.contains(&key))
+    }
+
+    /// `bytes_loaded` is `None` if the load failed
+    pub fn finish(&mut self,key: HardAssetKey,bytes_loaded: Option<u64>) {
+        self.requested.remove(&key);
+        for state in self.groups.values_mut() {
+            if !state.pending.remove(&key) {
+                continue;
+            }
+            match bytes_loaded {
+                Some(bytes) => {
+                    state.progress.loaded += 1;
+                    state.progress.bytes_loaded += bytes;
+                },
+                None => state.progress.failed += 1,
+            }
+        }
+    }
+
+    pub fn get_progress(&self,group: PreloadGroupKey) -> Option<PreloadProgress> {
+        self.groups.get(group).map(|state|state.progress)
+    }
+
+    pub fn get_total_progress(&self) -> PreloadProgress {
+        let mut progress = PreloadProgress::default();
+        for state in self.groups.values() {
+            progress.add(&state.progress);
+        }
+        progress
+    }
+
+    /// Returns every asset the group held
+    pub fn release(&mut self,group: PreloadGroupKey) -> Vec<HardAssetKey> {
+        let Some(released) = self.groups.remove(group) else {
+            return Vec::new();
+        };
+
+        let held: HashSet<HardAssetKey> = self.groups.values().flat_map(|state|state.assets.iter().copied()).collect();
+
+        /* Queued loads that nobody is waiting on are dropped. Loads already in flight are discarded when they finish. */
+        let requested = &mut self.requested;
+        self.queue.retain(|request|{
+            let keep = held.contains(&request.key);
+            if !keep {
+                requested.remove(&request.key);
+            }
+            keep
+        });
+
+        released.assets.into_iter().collect()
+    }
+}
@@ -1,14 +1,31 @@
 use std::{rc::Rc, collections::HashMap};
 
-use super::{HardAsset, HardAssetType, HardAssetKey, wam_manifest::WamManifest, json_input, reference_types, wam_manifest::WamManifestError};
+use super::{AssetId, HardAsset, HardAssetType, HardAssetKey, json_input, reference_types, wam_manifest::{ManifestOverlay, WamManifest, WamManifestError}};
 
 pub struct VirtualAssetTranslator<'a> {
     pub namespaces_ids: HashMap::<u32,HardAssetKey>,
     pub namespace_name: &'a str,
-    pub manifest: &'a mut WamManifest
+    pub manifest: &'a mut WamManifest,
+    pub overlay: &'a mut ManifestOverlay,
 }
 
 impl VirtualAssetTranslator<'_> {
+    /// A name that is already in the overlay is replaced, whatever its type was
+    fn insert_name(&mut self,name: &Rc<str>) -> AssetId {
+        let id = self.manifest.names.intern_rc(name);
+        let overlay = &mut *self.overlay;
+        let replaced =
+            overlay.text_assets.remove(&id).is_some() |
+            overlay.image_assets.remove(&id).is_some() |
+            overlay.model_assets.remove(&id).is_some() |
+            overlay.binary_assets.remove(&id).is_some();
+        if replaced {
+            log::warn!("Virtual asset '{name}' is defined more than once in overlay '{}'",overlay.label);
+            overlay.duplicates.push(id);
+        }
+        return id;
+    }
+
     pub fn parse_hard_assets(&mut self,hard_assets: Vec<json_input::HardAsset>) -> Result<(),WamManifestError> {
         for hard_asset_input in hard_assets.into_iter() {
             let id = hard_asset_input.id;
@@ -19,19 +36,23 @@ impl VirtualAssetTranslator<'_> {
             });
 
             self.namespaces_ids.insert(id,key);
+            self.overlay.hard_assets.push(key);
         }
         return Ok(());
     }
 
     pub fn parse_size_hints(&mut self,size_hints: Vec<json_input::SizeHint>) -> Result<(),WamManifestError> {
         for size_hint in size_hints.into_iter() {
-            let Some(key) = self.namespaces_ids.get(&size_hint.id) else {
+            let Some(key) = self.namespaces_ids.get(&size_hint.id).copied() else {
                 return Err(WamManifestError::ImageSizeHintMissingOwner { id: size_hint.id });
             };
-            self.manifest.size_hints.insert(*key,crate::UWimpyPoint {
+            self.manifest.size_hints.insert(key,crate::UWimpyPoint {
                 x: size_hint.x,
                 y: size_hint.y,
             });
+            if size_hint.mipmaps {
+                self.manifest.mipmapped_images.insert(key);
+            }
         }
         return Ok(());
     }
@@ -39,24 +60,26 @@ impl VirtualAssetTranslator<'_> {
     pub fn parse_generic_assets(&mut self,assets: Vec<json_input::VirtualAsset>) -> Result<(),WamManifestError> {
         for asset in assets.into_iter() {
             let rc_name = self.manifest.get_virtual_asset_name(asset.name,self.namespace_name);
-            let Some(key) = self.namespaces_ids.get(&asset.id) else {
+            let Some(key) = self.namespaces_ids.get(&asset.id).copied() else {
                 return Err(WamManifestError::MissingAsset {
                     name: rc_name,
                     id: asset.id
                 });
             };
 
-            let hard_asset = self.manifest.hard_assets.get(*key).unwrap();
+            let hard_asset = self.manifest.hard_assets.get(key).unwrap();
             match hard_asset.data_type {
                 HardAssetType::Text => {
-                    self.manifest.text_assets.insert(rc_name.clone(),reference_types::Text {
+                    let id = self.insert_name(&rc_name);
+                    self.overlay.text_assets.insert(id,reference_types::Text {
                         name: rc_name,
-                        key: *key
+                        key
                     });
                 },
                 HardAssetType::Image => {
-                    self.manifest.image_assets.insert(rc_name.clone(),reference_types::Image {
-                        size_hint: match self.manifest.size_hints.get(*key) {
+                    let id = self.insert_name(&rc_name);
+                    self.overlay.image_assets.insert(id,reference_types::Image {
+                        size_hint: match self.manifest.size_hints.get(key) {
                             Some(value) => *value,
                             None => return Err(WamManifestError::ImageMissingSizeHint {
                                 id: asset.id,
@@ -64,10 +87,17 @@ impl VirtualAssetTranslator<'_> {
                             }),
                         },
                         name: rc_name,
-                        key: *key,
+                        key,
                         slice: None,
                     });
                 },
+                HardAssetType::Audio | HardAssetType::Binary | HardAssetType::Data => {
+                    let id = self.insert_name(&rc_name);
+                    self.overlay.binary_assets.insert(id,reference_types::Binary {
+                        name: rc_name,
+                        key
+                    });
+                },
                 HardAssetType::Model => {
                     return Err(WamManifestError::UnexpectedType {
                         name: rc_name,
@@ -83,13 +113,13 @@ impl VirtualAssetTranslator<'_> {
     pub fn parse_slice_images(&mut self,images: Vec<json_input::VirtualImageAsset>) -> Result<(),WamManifestError> {
         for image in images.into_iter() {
             let rc_name = self.manifest.get_virtual_asset_name(image.name,self.namespace_name);
-            let Some(key) = self.namespaces_ids.get(&image.id) else {
+            let Some(key) = self.namespaces_ids.get(&image.id).copied() else {
                 return Err(WamManifestError::MissingAsset {
                     name: rc_name,
                     id: image.id
                 });
             };
-            let hard_asset = self.manifest.hard_assets.get(*key).unwrap();
+            let hard_asset = self.manifest.hard_assets.get(key).unwrap();
             if hard_asset.data_type != HardAssetType::Image {
                 return Err(WamManifestError::AssetTypeMismatch {
                     name: rc_name,
@@ -98,8 +128,9 @@ impl VirtualAssetTranslator<'_> {
                     found_type: hard_asset.data_type
                 });
             }
-            self.manifest.image_assets.insert(rc_name.clone(),reference_types::Image {
-                size_hint: match self.manifest.size_hints.get(*key) {
+            let id = self.insert_name(&rc_name);
+            self.overlay.image_assets.insert(id,reference_types::Image {
+                size_hint: match self.manifest.size_hints.get(key) {
                     Some(value) => *value,
                     None => return Err(WamManifestError::ImageMissingSizeHint {
                         id: image.id,
@@ -107,7 +138,7 @@ impl VirtualAssetTranslator<'_> {
                     }),
                 },
                 name: rc_name,
-                key: *key,
+                key,
                 slice: Some(image.slice)
             });
         }
@@ -120,14 +151,14 @@ impl VirtualAssetTranslator<'_> {
         for model in models.into_iter() {
             let rc_name = self.manifest.get_virtual_asset_name(model.name,self.namespace_name);
 
-            let Some(key) = self.namespaces_ids.get(&model.id) else {
+            let Some(key) = self.namespaces_ids.get(&model.id).copied() else {
                 return Err(WamManifestError::MissingAsset {
                     name: rc_name,
                     id: model.id
                 });
             };
 
-            let hard_asset = self.manifest.hard_assets.get(*key).unwrap();
+            let hard_asset = self.manifest.hard_assets.get(key).unwrap();
             if hard_asset.data_type != HardAssetType::Model {
                 return Err(WamManifestError::AssetTypeMismatch {
                     name: rc_name,
@@ -152,13 +183,13 @@ impl VirtualAssetTranslator<'_> {
                     let Some(id) = id else {
                         continue;
                     };
-                    let Some(key) = self.namespaces_ids.get(&id) else {
+                    let Some(key) = self.namespaces_ids.get(&id).copied() else {
                         return Err(WamManifestError::MissingAsset {
                             name: rc_name,
                             id
                         });
                     };
-                    let hard_asset = self.manifest.hard_assets.get(*key).unwrap();
+                    let hard_asset = self.manifest.hard_assets.get(key).unwrap();
                     if hard_asset.data_type != HardAssetType::Image {
                         return Err(WamManifestError::MismatchedMeshletField {
                             name: rc_name,
@@ -168,14 +199,14 @@ impl VirtualAssetTranslator<'_> {
                         });
                     }
                     let descriptor = Some(MeshletTexture {
-                        size_hint: match self.manifest.size_hints.get(*key) {
+                        size_hint: match self.manifest.size_hints.get(key) {
                             Some(value) => *value,
                             None => return Err(WamManifestError::ImageMissingSizeHint {
                                 id,
                                 name: rc_name
                             }),
                         },
-                        key: *key,
+                        key,
                     });
                     match field {
                         MeshletField::Diffuse => ref_meshlet.diffuse = descriptor,
@@ -185,9 +216,11 @@ impl VirtualAssetTranslator<'_> {
                 meshlets.push(ref_meshlet);
             }
 
-            self.manifest.model_assets.insert(rc_name.clone(),Model {
+            let id = self.insert_name(&rc_name);
+
+            self.overlay.model_assets.insert(id,Model {
                 name: rc_name,
-                key: *key,
+                key,
                 meshlet_layers: meshlets
             });
         }
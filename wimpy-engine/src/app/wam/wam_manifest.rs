@@ -1,13 +1,45 @@
-use std::{collections::HashMap, rc::Rc};
+use std::{collections::{BTreeMap, HashMap, HashSet}, fmt, rc::Rc};
 use slotmap::{SparseSecondaryMap, SlotMap};
 
 use crate::UWimpyPoint;
-use super::{HardAsset, json_input, HardAssetKey, HardAssetType, reference_types};
+use super::{HardAsset, json_input, HardAssetKey, HardAssetType, AssetId, AssetNames, reference_types};
 
 const DEFAULT_NAME_STRING_BUILDER_CAPACITY: usize = 64;
 const DEFAULT_HARD_ASSET_CAPACITY: usize = 32;
 const DEFAULT_VIRTUAL_ASSET_BUCKET_CAPACITY: usize = 32;
+const DEFAULT_OVERLAY_CAPACITY: usize = 4;
 
+/// The label of the overlay made by `WamManifest::create`
+pub const BASE_OVERLAY_LABEL: &'static str = "base";
+
+slotmap::new_key_type! {
+    pub struct OverlayKey;
+}
+
+/// The virtual assets of one mounted manifest, such as the base game, a mod, or a patch
+#[derive(Debug,Default)]
+pub struct ManifestOverlay {
+    pub label:          Rc<str>,
+    /// Owned by this overlay, removed from the manifest when it is unmounted
+    pub hard_assets:    Vec<HardAssetKey>,
+    pub text_assets:    HashMap<AssetId,    reference_types::Text>,
+    pub image_assets:   HashMap<AssetId,    reference_types::Image>,
+    pub model_assets:   HashMap<AssetId,    reference_types::Model>,
+    pub binary_assets:  HashMap<AssetId,    reference_types::Binary>,
+    /// Names defined more than once by this overlay's own namespaces
+    pub(super) duplicates: Vec<AssetId>,
+}
+
+/// A virtual asset name that is defined by more than one overlay (or twice by one overlay), see `WamManifest::get_overrides`
+#[derive(Debug,Clone,Copy)]
+pub struct AssetOverride {
+    pub id:         AssetId,
+    /// The overlay the name resolves to
+    pub winner:     OverlayKey,
+    pub hidden:     OverlayKey,
+}
+
+/// The virtual asset maps are the resolved view of every overlay. A virtual name resolves to the most recently mounted overlay that defines it.
 #[derive(Debug,Default)]
 pub struct WamManifest {
     string_builder: String,
@@ -16,9 +48,26 @@ pub struct WamManifest {
 
     pub size_hints: SparseSecondaryMap<HardAssetKey,UWimpyPoint>,
 
-    pub text_assets:    HashMap<Rc<str>,    reference_types::Text>,
-    pub image_assets:   HashMap<Rc<str>,    reference_types::Image>,
-    pub model_assets:   HashMap<Rc<str>,    reference_types::Model>,
+    /// Images whose size hint asks for a mip chain
+    pub mipmapped_images: HashSet<HardAssetKey>,
+
+    /// The overlay that owns each hard asset
+    pub hard_asset_overlays: SparseSecondaryMap<HardAssetKey,OverlayKey>,
+
+    /// Every virtual asset name, and any name resolved at runtime
+    pub names: AssetNames,
+
+    pub text_assets:    HashMap<AssetId,    reference_types::Text>,
+    pub image_assets:   HashMap<AssetId,    reference_types::Image>,
+    pub model_assets:   HashMap<AssetId,    reference_types::Model>,
+    /// Audio, binary and data assets, which are all loaded as bytes
+    pub binary_assets:  HashMap<AssetId,    reference_types::Binary>,
+
+    overlays:       SlotMap<OverlayKey,ManifestOverlay>,
+    /// Mount order, the last overlay wins
+    overlay_order:  Vec<OverlayKey>,
+    winners:        HashMap<AssetId,OverlayKey>,
+    overrides:      Vec<AssetOverride>,
 }
 
 #[derive(Debug)]
@@ -52,42 +101,187 @@ pub enum WamManifestError {
         found_type: HardAssetType
     },
     IOError(std::io::Error),
-    JsonError(String),
+    /// The manifest is not valid json, or doesn't match the manifest layout
+    JsonError {
+        line: usize,
+        column: usize,
+        /// Where in the document the error is, e.g., `wimpy.hard-assets[2].type`. Empty for syntax errors before the first value.
+        path: String,
+        category: serde_json::error::Category,
+        message: String
+    },
+}
+
+impl fmt::Display for WamManifestError {
+    fn fmt(&self,f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            /* The serde message already ends with the line and column */
+            WamManifestError::JsonError { path, message, .. } => match path.is_empty() {
+                true => write!(f,"{message}"),
+                false => write!(f,"at '{path}': {message}"),
+            },
+            WamManifestError::MissingAsset { name, id } => write!(f,"'{name}' points to hard asset {id} but it does not exist"),
+            WamManifestError::ImageMissingSizeHint { name, id } => write!(f,"'{name}' uses image {id} but the image has no size hint"),
+            WamManifestError::ImageSizeHintMissingOwner { id } => write!(f,"size hint for id {id} has no hard asset"),
+            WamManifestError::UnexpectedType { name, id, found_type } => write!(f,"'{name}' points to hard asset {id} of unexpected type {:?}",found_type),
+            WamManifestError::AssetTypeMismatch { name, id, expected_type, found_type } => write!(f,
+                "'{name}' points to hard asset {id} of type {:?} but expected {:?}",found_type,expected_type
+            ),
+            WamManifestError::MismatchedMeshletField { name, field, expected_type, found_type } => write!(f,
+                "model '{name}' {:?} field is of type {:?} but expected {:?}",field,found_type,expected_type
+            ),
+            WamManifestError::IOError(error) => write!(f,"{error}"),
+        }
+    }
 }
 
 impl WamManifest {
 
     pub fn create(json_text: &str) -> Result<Self,WamManifestError> {
+        let mut manifest = Self::new();
+        manifest.mount_overlay(json_text,BASE_OVERLAY_LABEL)?;
+        return Ok(manifest);
+    }
 
-        let namespace_table: HashMap<String,json_input::Namespace> = match serde_json::from_str(&json_text) {
-            Ok(value) => value,
-            Err(error) => {
-                // TODO: match the serde_json error instead of formatting it
-                return Err(WamManifestError::JsonError(format!("{:?}",error)))
-            },
-        };
-
-        let mut manifest = Self {
+    /// A manifest without any overlays
+    pub fn new() -> Self {
+        return Self {
             string_builder: String::with_capacity               (DEFAULT_NAME_STRING_BUILDER_CAPACITY),
             hard_assets:    SlotMap::with_capacity_and_key      (DEFAULT_HARD_ASSET_CAPACITY),
+            names:          AssetNames::with_capacity           (DEFAULT_VIRTUAL_ASSET_BUCKET_CAPACITY),
             text_assets:    HashMap::with_capacity              (DEFAULT_VIRTUAL_ASSET_BUCKET_CAPACITY),
             image_assets:   HashMap::with_capacity              (DEFAULT_VIRTUAL_ASSET_BUCKET_CAPACITY),
             model_assets:   HashMap::with_capacity              (DEFAULT_VIRTUAL_ASSET_BUCKET_CAPACITY),
+            binary_assets:  HashMap::with_capacity              (DEFAULT_VIRTUAL_ASSET_BUCKET_CAPACITY),
             size_hints:     SparseSecondaryMap::with_capacity   (DEFAULT_VIRTUAL_ASSET_BUCKET_CAPACITY),
+            mipmapped_images: HashSet::new(),
+            hard_asset_overlays: SparseSecondaryMap::with_capacity(DEFAULT_HARD_ASSET_CAPACITY),
+            overlays:       SlotMap::with_capacity_and_key      (DEFAULT_OVERLAY_CAPACITY),
+            overlay_order:  Vec::with_capacity                  (DEFAULT_OVERLAY_CAPACITY),
+            winners:        HashMap::with_capacity              (DEFAULT_VIRTUAL_ASSET_BUCKET_CAPACITY),
+            overrides:      Vec::new(),
         };
+    }
 
-        let item_count = namespace_table.len();
+    /// Parses manifest data on top of everything that is already mounted. Its virtual assets override earlier overlays by name.
+    ///
+    /// Nothing is mounted if the data is invalid.
+    pub fn mount_overlay(&mut self,json_text: &str,label: &str) -> Result<OverlayKey,WamManifestError> {
+        /* Sorted, so duplicate names between namespaces resolve the same way every run */
+        let mut deserializer = serde_json::Deserializer::from_str(json_text);
+        let namespace_table: BTreeMap<String,json_input::Namespace> = match serde_path_to_error::deserialize(&mut deserializer) {
+            Ok(value) => value,
+            Err(error) => {
+                let path = error.path().to_string();
+                let error = error.into_inner();
+                return Err(WamManifestError::JsonError {
+                    line: error.line(),
+                    column: error.column(),
+                    path: match path.as_str() {
+                        "." => String::new(),
+                        _ => path
+                    },
+                    category: error.classify(),
+                    message: error.to_string()
+                });
+            },
+        };
 
-        let mut id_table = HashMap::with_capacity(item_count);
-        let mut namespaces = Vec::with_capacity(item_count);
+        let mut overlay = ManifestOverlay {
+            label: Rc::from(label),
+            ..Default::default()
+        };
 
         for (name,value) in namespace_table.into_iter() {
-            let id = namespaces.len();
-            namespaces.push(manifest.add_namespace(value,&name)?);
-            id_table.insert(name,id);
+            if let Err(error) = self.add_namespace(value,&name,&mut overlay) {
+                self.remove_hard_assets(&overlay.hard_assets);
+                return Err(error);
+            }
         }
 
-        return Ok(manifest);
+        let key = self.overlays.insert(overlay);
+        for hard_asset in self.overlays[key].hard_assets.iter() {
+            self.hard_asset_overlays.insert(*hard_asset,key);
+        }
+        self.overlay_order.push(key);
+        self.resolve_overlays();
+        return Ok(key);
+    }
+
+    /// Returns the hard assets that were removed with the overlay, their cached data is stale. `None` if the overlay is not mounted.
+    pub fn unmount_overlay(&mut self,key: OverlayKey) -> Option<Vec<HardAssetKey>> {
+        let overlay = self.overlays.remove(key)?;
+        self.overlay_order.retain(|value|*value != key);
+        self.remove_hard_assets(&overlay.hard_assets);
+        self.resolve_overlays();
+        return Some(overlay.hard_assets);
+    }
+
+    fn remove_hard_assets(&mut self,hard_assets: &[HardAssetKey]) {
+        for key in hard_assets {
+            self.hard_assets.remove(*key);
+            self.size_hints.remove(*key);
+            self.mipmapped_images.remove(key);
+            self.hard_asset_overlays.remove(*key);
+        }
+    }
+
+    /// Rebuilds the virtual asset maps from the overlays, in mount order
+    fn resolve_overlays(&mut self) {
+        self.text_assets.clear();
+        self.image_assets.clear();
+        self.model_assets.clear();
+        self.binary_assets.clear();
+        self.winners.clear();
+        self.overrides.clear();
+
+        for key in self.overlay_order.iter().copied() {
+            let overlay = &self.overlays[key];
+            for id in overlay.duplicates.iter().copied() {
+                self.overrides.push(AssetOverride { id, winner: key, hidden: key });
+            }
+            let ids = overlay.text_assets.keys()
+                .chain(overlay.image_assets.keys())
+                .chain(overlay.model_assets.keys())
+                .chain(overlay.binary_assets.keys());
+            for id in ids.copied() {
+                if let Some(hidden) = self.winners.insert(id,key) {
+                    /* The name can change type between overlays */
+                    self.text_assets.remove(&id);
+                    self.image_assets.remove(&id);
+                    self.model_assets.remove(&id);
+                    self.binary_assets.remove(&id);
+                    self.overrides.push(AssetOverride { id, winner: key, hidden });
+                }
+            }
+            self.text_assets.extend(overlay.text_assets.iter().map(|(id,asset)|(*id,asset.clone())));
+            self.image_assets.extend(overlay.image_assets.iter().map(|(id,asset)|(*id,asset.clone())));
+            self.model_assets.extend(overlay.model_assets.iter().map(|(id,asset)|(*id,asset.clone())));
+            self.binary_assets.extend(overlay.binary_assets.iter().map(|(id,asset)|(*id,asset.clone())));
+        }
+
+        /* Overrides from earlier overlays are stale if a later overlay took the name */
+        let winners = &self.winners;
+        self.overrides.retain(|item|winners.get(&item.id) == Some(&item.winner));
+    }
+
+    /// The overlay that a virtual asset resolves to
+    pub fn get_asset_overlay(&self,id: AssetId) -> Option<OverlayKey> {
+        self.winners.get(&id).copied()
+    }
+
+    /// Every name that hides another definition, in mount order
+    pub fn get_overrides(&self) -> &[AssetOverride] {
+        &self.overrides
+    }
+
+    pub fn get_overlay(&self,key: OverlayKey) -> Option<&ManifestOverlay> {
+        self.overlays.get(key)
+    }
+
+    /// Mount order, the last overlay wins
+    pub fn get_overlays(&self) -> impl Iterator<Item = (OverlayKey,&ManifestOverlay)> {
+        self.overlay_order.iter().map(|key|(*key,&self.overlays[*key]))
     }
 
     pub fn get_virtual_asset_name(&mut self,mut local_name: String,namespace_name: &str) -> Rc<str> {
@@ -100,7 +294,7 @@ impl WamManifest {
         return Rc::from(local_name);
     }
 
-    fn add_namespace(&mut self,namespace: json_input::Namespace,namespace_name: &str) -> Result<(),WamManifestError> {
+    fn add_namespace(&mut self,namespace: json_input::Namespace,namespace_name: &str,overlay: &mut ManifestOverlay) -> Result<(),WamManifestError> {
         let hard_asset_count = namespace.hard_assets.len();
 
         /*  
@@ -110,12 +304,13 @@ impl WamManifest {
 
         let mut translator = super::virtual_asset_translator::VirtualAssetTranslator {
             manifest: self,
+            overlay,
             namespaces_ids: HashMap::with_capacity(hard_asset_count),
             namespace_name,
         };
 
         translator.parse_hard_assets    (namespace.hard_assets)?;
-        translator.parse_size_hints     (namespace.image_size_hints);
+        translator.parse_size_hints     (namespace.image_size_hints)?;
         translator.parse_generic_assets (namespace.virtual_assets)?;
         translator.parse_slice_images   (namespace.virtual_image_slice_assets)?;
         translator.parse_models         (namespace.virtual_model_assets)?;
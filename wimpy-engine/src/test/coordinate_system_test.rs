@@ -6,8 +6,8 @@ pub struct CoordinateSystemTest {
     in_movement_mode:   bool,
     camera:             WimpyCamera,
     lines:              Vec<LinePoint3D>,
-    cube_mesh:          Option<TexturedMesh>,
-    test_room_mesh:     Option<TexturedMesh>,
+    cube_mesh:          TexturedMesh,
+    test_room_mesh:     TexturedMesh,
 }
 
 const LINE_COUNT: usize = 11;
@@ -200,19 +200,15 @@ where
             return;
         };
 
-        if let Some(mesh) = self.test_room_mesh {
-            output.builder.batch_meshes(TextureStrategy::Standard,[DrawData3D {
-                transform: Mat4::IDENTITY,
-                mesh,
-            }]);
-        }
+        output.builder.batch_meshes(TextureStrategy::Standard,[DrawData3D {
+            transform: Mat4::IDENTITY,
+            mesh: self.test_room_mesh,
+        }]);
 
-        if let Some(mesh) = self.cube_mesh {
-            output.builder.batch_meshes(TextureStrategy::Standard,[DrawData3D {
-                transform: Mat4::IDENTITY,
-                mesh,
-            }]);
-        }
+        output.builder.batch_meshes(TextureStrategy::Standard,[DrawData3D {
+            transform: Mat4::IDENTITY,
+            mesh: self.cube_mesh,
+        }]);
 
         output.builder.submit_batched_meshes();
 
@@ -95,6 +95,11 @@ where
             format_args!("dx: {:.0} dy: {:.0}",mouse.delta().x,mouse.delta().y)
         );
 
+        context.debug_shell.set_label_fmt(
+            LabelID::Three,
+            format_args!("bind groups: {}",context.graphics.texture_manager.get_live_bind_group_count())
+        );
+
         context.debug_shell.set_graph(GraphID::One,delta_norm(mouse.delta().x));
         context.debug_shell.set_graph(GraphID::Two,delta_norm(mouse.delta().y));
 
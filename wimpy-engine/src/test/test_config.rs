@@ -15,4 +15,6 @@ impl GraphicsConfig for TestConfig {
 
     const TEXT_PIPELINE_BUFFER_SIZE: usize = BASE;
     const LINE_BUFFER_SIZE: usize = BASE;
+
+    const TEXTURE_MEMORY_BUDGET: usize = 256 * 1024 * 1024;
 }
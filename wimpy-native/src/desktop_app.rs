@@ -4,6 +4,9 @@ const MINIMUM_WINDOW_SIZE: (u32,u32) = (1600,900);
 const LEFT_EDGE_VIRTUAL_MODE_MARGIN: u32 = 2;
 const RIGHT_EDGE_VIRTUAL_MODE_MARGIN: u32 = 8;
 
+/// Preload loads block the frame on desktop, so only a few run each frame
+const PRELOAD_REQUESTS_PER_FRAME: usize = 4;
+
 use std::{collections::HashMap, path::Path};
 use sdl2::{EventPump, GameControllerSubsystem, Sdl, TimerSubsystem, VideoSubsystem, controller::{Axis, Button, GameController}, event::{Event, WindowEvent}, mouse::MouseButton, video::Window};
 use wgpu::{Instance, Limits, Surface};
@@ -11,6 +14,7 @@ use wimpy_engine::{WimpyRect, WimpyVec, app::{*,input::*,graphics::{*,textures::
 
 use crate::{
     desktop_io::DekstopAppIO,
+    hot_reload::HotReloadWatcher,
     key_code::translate_key_code
 };
 
@@ -28,7 +32,8 @@ struct InnerApp<TWimpyApp> {
     now: u64,
     app: TWimpyApp,
     app_context: WimpyAppContext,
-    has_focus: bool
+    has_focus: bool,
+    hot_reload: Option<HotReloadWatcher>
 }
 
 struct SDLSystems {
@@ -40,6 +45,7 @@ struct SDLSystems {
 
 async fn async_load<TWimpyApp,TConfig>(
     manifest_path: Option<&Path>,
+    hot_reload: bool,
     instance: Instance,
     surface: Surface<'static>,
     window: Window,
@@ -63,15 +69,28 @@ where
     let window_size = window.size();
     graphics_provider.set_size(window_size.0,window_size.1);
 
-    let mut app_context = WimpyAppContext::create::<DekstopAppIO,TConfig>(WimpyContextCreationConfig {
+    let mut app_context = match WimpyAppContext::create::<DekstopAppIO,TConfig>(WimpyContextCreationConfig {
         manifest_path,
         input_device_hint: InputDevice::MouseAndKeyboard,
         texture_stream_policy: StreamingPolicy::Default,
         graphics_provider,
-    }).await;
+        key_value_store_migrations: TWimpyApp::key_value_store_migrations(),
+        manifest_load_mode: TWimpyApp::manifest_load_mode(),
+    }).await {
+        Ok(value) => value,
+        Err(error) => {
+            log::error!("Manifest load failure: {}",error);
+            return None;
+        }
+    };
 
     let app = TWimpyApp::create(&mut app_context).await;
 
+    let hot_reload = match hot_reload {
+        true => Some(HotReloadWatcher::new(&app_context)),
+        false => None,
+    };
+
     let now = sdl_systems.timer.performance_counter();
 
     return Some(InnerApp {
@@ -83,11 +102,13 @@ where
         window,
         now,
         app,
-        app_context
+        app_context,
+        hot_reload
     });
 }
 
-pub fn run_desktop_app<TWimpyApp,TConfig>(manifest: Option<&Path>)
+/// `hot_reload` watches the files behind the manifest and reloads assets that change, it is meant for development
+pub fn run_desktop_app<TWimpyApp,TConfig>(manifest: Option<&Path>,hot_reload: bool)
 where
     TWimpyApp: WimpyAppHandler<DekstopAppIO>,
     TConfig: GraphicsConfig
@@ -135,6 +156,7 @@ where
 
     if let Some(mut inner_app) = pollster::block_on(async_load::<TWimpyApp,TConfig>(
         manifest,
+        hot_reload,
         instance,
         surface,
         window,
@@ -157,6 +179,8 @@ where
                     self.update(event_pump);
                 },
                 EventLoopOperation::Terminate => {
+                    let data = self.app_context.flush_key_value_store();
+                    self.save_key_value_store(data);
                     break 'event_loop;
                 },
             }
@@ -236,6 +260,35 @@ where
         }
 
         self.app.update(&mut self.app_context);
+        self.app_context.update_texture_streaming(delta_seconds);
+
+        for request in self.app_context.take_preload_requests(PRELOAD_REQUESTS_PER_FRAME) {
+            let result = pollster::block_on(request.load::<DekstopAppIO>());
+            self.app_context.complete_preload(result);
+        }
+
+        if let Some(watcher) = &mut self.hot_reload {
+            for key in watcher.update(&self.app_context,delta_seconds) {
+                let Some(request) = self.app_context.assets.create_reload_request(key) else {
+                    continue;
+                };
+                let result = pollster::block_on(request.load::<DekstopAppIO>());
+                self.app_context.complete_reload(result);
+            }
+        }
+
+        let autosave = self.app_context.update_key_value_store(delta_seconds);
+        self.save_key_value_store(autosave);
+    }
+
+    fn save_key_value_store(&mut self,data: Option<Vec<u8>>) {
+        let Some(data) = data else {
+            return;
+        };
+        if let Err(error) = pollster::block_on(DekstopAppIO::save_key_value_store(&data)) {
+            log::error!("Key value store autosave failure: {:?}",error);
+            self.app_context.key_value_store.mark_dirty();
+        }
     }
 
     fn poll_events(&mut self,event_pump: &mut EventPump) -> EventLoopOperation {
@@ -1,14 +1,56 @@
-use std::path::Path;
+const KEY_VALUE_STORE_FILE_NAME: &'static str = "kvs.bin";
+
+const SAVE_SLOT_DIRECTORY_NAME: &'static str = "saves";
+const SAVE_SLOT_EXTENSION: &'static str = "sav";
+/// A slot file starts with the length of its metadata, then the metadata, then the data
+const SAVE_SLOT_HEADER_SIZE: usize = 4;
+
+use std::{path::{Path, PathBuf}, time::{SystemTime, UNIX_EPOCH}};
 
 use image::{DynamicImage, ImageError, ImageReader};
+use wgpu::{Extent3d, Origin3d, Queue, TexelCopyBufferLayout, TexelCopyTextureInfo, Texture, TextureAspect};
+
+use wimpy_engine::{UWimpyPoint, app::{*, kvs::KeyValueStore}};
+
+use crate::user_data;
 
-use wimpy_engine::app::*;
 pub struct DekstopAppIO;
 
 struct DynamicImageWrapper {
     value: DynamicImage
 }
 
+impl WimpyImageDataWriter for DynamicImageWrapper {
+    fn write(self: Box<Self>,queue: &Queue,texture: &Texture,max_size: UWimpyPoint) {
+        let size = self.size();
+        queue.write_texture(
+            TexelCopyTextureInfo {
+                texture,
+                mip_level: 0,
+                origin: Origin3d::ZERO,
+                aspect: TextureAspect::All,
+            },
+            &self.value.into_rgba8(),
+            TexelCopyBufferLayout {
+                offset: 0,
+                bytes_per_row:  Some(4 * size.x),
+                rows_per_image: Some(size.y),
+            },
+            Extent3d {
+                width: size.x.min(max_size.x),
+                height: size.y.min(max_size.y),
+                depth_or_array_layers: 1,
+            },
+        );
+    }
+    fn size(&self) -> UWimpyPoint {
+        UWimpyPoint {
+            x: self.value.width(),
+            y: self.value.height()
+        }
+    }
+}
+
 fn map_std_io_error(error: std::io::ErrorKind) -> FileError {
     use std::io::ErrorKind;
     return match error {
@@ -22,13 +64,12 @@ fn map_std_io_error(error: std::io::ErrorKind) -> FileError {
 }
 
 impl WimpyIO for DekstopAppIO {
-    async fn load_image_file(path: &Path) -> Result<WimpyImageData,FileError> {
+    async fn load_image_file(path: &Path) -> Result<WimpyImageData<'static>,FileError> {
         match ImageReader::open(path) {
             Ok(image_reader) => match image_reader.decode() {
                 Ok(value) => {
-                    Ok(WimpyImageData {
-                        size: [value.width(),value.height()].into(),
-                        data: value.to_rgba8().to_vec(),
+                    Ok(WimpyImageData::Custom {
+                        data: Box::new(DynamicImageWrapper { value })
                     })
                 },
                 Err(image_error) => Err(match image_error {
@@ -54,6 +95,18 @@ impl WimpyIO for DekstopAppIO {
         }
     }
 
+    async fn decode_image(data: Vec<u8>) -> Result<WimpyImageData<'static>,FileError> {
+        match image::load_from_memory(&data) {
+            Ok(value) => Ok(WimpyImageData::Custom {
+                data: Box::new(DynamicImageWrapper { value })
+            }),
+            Err(error) => {
+                log::error!("Image decode error: {:?}",error);
+                Err(FileError::DecodeFailure)
+            },
+        }
+    }
+
     async fn load_binary_file(path: &Path) -> Result<Vec<u8>,FileError> {
         match std::fs::read(path) {
             Ok(value) => Ok(value),
@@ -75,10 +128,167 @@ impl WimpyIO for DekstopAppIO {
     }
 
     async fn save_key_value_store(data: &[u8]) -> Result<(),FileError> {
-        todo!()
+        let path = get_key_value_store_path();
+        match user_data::write_atomic(&path,data) {
+            Ok(()) => Ok(()),
+            Err(error) => {
+                log::error!("Key value store write error '{:?}': {}",path,error);
+                Err(match map_std_io_error(error.kind()) {
+                    FileError::Other => FileError::WriteFailure,
+                    file_error => file_error
+                })
+            },
+        }
     }
 
     async fn load_key_value_store() -> Result<Vec<u8>,FileError> {
-        todo!()
+        let path = get_key_value_store_path();
+
+        let primary = match std::fs::read(&path) {
+            Ok(data) => match KeyValueStore::from_bytes(&data) {
+                Ok(_) => return Ok(data),
+                Err(error) => {
+                    log::warn!("Key value store '{:?}' failed to decode, trying backup: {:?}",path,error);
+                    Ok(data)
+                },
+            },
+            Err(error) => {
+                if error.kind() != std::io::ErrorKind::NotFound {
+                    log::warn!("Key value store '{:?}' failed to load, trying backup: {}",path,error);
+                }
+                Err(map_std_io_error(error.kind()))
+            },
+        };
+
+        let backup_path = user_data::backup_path(&path);
+        match std::fs::read(&backup_path) {
+            Ok(data) => match KeyValueStore::from_bytes(&data) {
+                Ok(_) => {
+                    log::warn!("Key value store restored from backup '{:?}'",backup_path);
+                    return Ok(data);
+                },
+                Err(error) => {
+                    log::error!("Key value store backup '{:?}' failed to decode: {:?}",backup_path,error);
+                },
+            },
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => {},
+            Err(error) => {
+                log::error!("Key value store backup '{:?}' failed to load: {}",backup_path,error);
+            },
+        }
+
+        // Nothing usable, hand back the primary so the store can report exactly what went wrong with it
+        primary
+    }
+
+    async fn save_slot(name: &str,data: &[u8],metadata: &[u8]) -> Result<(),FileError> {
+        // One file and one atomic write, data and metadata can't get out of step
+        let Ok(metadata_length) = u32::try_from(metadata.len()) else {
+            return Err(FileError::WriteFailure);
+        };
+        let mut bytes = Vec::with_capacity(SAVE_SLOT_HEADER_SIZE + metadata.len() + data.len());
+        bytes.extend_from_slice(&metadata_length.to_le_bytes());
+        bytes.extend_from_slice(metadata);
+        bytes.extend_from_slice(data);
+
+        let path = get_save_slot_path(name);
+        match user_data::write_atomic(&path,&bytes) {
+            Ok(()) => Ok(()),
+            Err(error) => {
+                log::error!("Save slot write error '{:?}': {}",path,error);
+                Err(match map_std_io_error(error.kind()) {
+                    FileError::Other => FileError::WriteFailure,
+                    file_error => file_error
+                })
+            },
+        }
+    }
+
+    async fn load_slot(name: &str) -> Result<Vec<u8>,FileError> {
+        let mut bytes = read_save_slot(name)?;
+        let metadata_end = get_slot_metadata_end(name,&bytes)?;
+        bytes.drain(..metadata_end);
+        Ok(bytes)
+    }
+
+    async fn load_slot_metadata(name: &str) -> Result<Vec<u8>,FileError> {
+        let mut bytes = read_save_slot(name)?;
+        let metadata_end = get_slot_metadata_end(name,&bytes)?;
+        bytes.truncate(metadata_end);
+        bytes.drain(..SAVE_SLOT_HEADER_SIZE);
+        Ok(bytes)
+    }
+
+    async fn delete_slot(name: &str) -> Result<(),FileError> {
+        let path = get_save_slot_path(name);
+        for path in [user_data::backup_path(&path),path] {
+            match std::fs::remove_file(&path) {
+                Ok(()) => {},
+                Err(error) if error.kind() == std::io::ErrorKind::NotFound => {},
+                Err(error) => {
+                    log::error!("Save slot delete error '{:?}': {}",path,error);
+                    return Err(map_std_io_error(error.kind()));
+                },
+            }
+        }
+        Ok(())
+    }
+
+    async fn list_slots() -> Result<Vec<String>,FileError> {
+        let entries = match std::fs::read_dir(get_save_slot_directory()) {
+            Ok(value) => value,
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(error) => return Err(map_std_io_error(error.kind())),
+        };
+        let mut names = Vec::new();
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if path.extension().and_then(|value|value.to_str()) != Some(SAVE_SLOT_EXTENSION) {
+                continue;
+            }
+            if let Some(name) = path.file_stem().and_then(|value|value.to_str()) {
+                names.push(name.to_string());
+            }
+        }
+        Ok(names)
+    }
+
+    fn unix_timestamp() -> u64 {
+        match SystemTime::now().duration_since(UNIX_EPOCH) {
+            Ok(duration) => duration.as_secs(),
+            Err(_) => 0,
+        }
+    }
+}
+
+fn get_key_value_store_path() -> PathBuf {
+    user_data::get_data_directory().join(KEY_VALUE_STORE_FILE_NAME)
+}
+
+fn get_save_slot_directory() -> PathBuf {
+    user_data::get_data_directory().join(SAVE_SLOT_DIRECTORY_NAME)
+}
+
+fn get_save_slot_path(name: &str) -> PathBuf {
+    get_save_slot_directory().join(name).with_extension(SAVE_SLOT_EXTENSION)
+}
+
+fn read_save_slot(name: &str) -> Result<Vec<u8>,FileError> {
+    match std::fs::read(get_save_slot_path(name)) {
+        Ok(value) => Ok(value),
+        Err(error) => Err(map_std_io_error(error.kind())),
+    }
+}
+
+/// The offset where the slot's data starts
+fn get_slot_metadata_end(name: &str,bytes: &[u8]) -> Result<usize,FileError> {
+    let metadata_end = match bytes.first_chunk::<SAVE_SLOT_HEADER_SIZE>() {
+        Some(header) => SAVE_SLOT_HEADER_SIZE + u32::from_le_bytes(*header) as usize,
+        None => usize::MAX,
+    };
+    if metadata_end > bytes.len() {
+        log::error!("Save slot '{}' is truncated",name);
+        return Err(FileError::DecodeFailure);
     }
+    Ok(metadata_end)
 }
@@ -0,0 +1,97 @@
+const HOT_RELOAD_ENV_VAR: &'static str = "WIMPY_HOT_RELOAD";
+const HOT_RELOAD_ARG: &'static str = "--hot-reload";
+
+/// How often the files behind the manifest's hard assets are checked for changes
+const POLL_INTERVAL_SECONDS: f32 = 0.5;
+
+use std::{collections::HashMap, env, ffi::OsString, fs, path::PathBuf, time::SystemTime};
+use wimpy_engine::app::{WimpyAppContext, wam::HardAssetKey};
+
+/// Hot reloading is a development mode, it is enabled with `--hot-reload` or a non-empty `WIMPY_HOT_RELOAD` that is not `0`
+pub fn is_hot_reload_enabled<I>(args: I) -> bool
+where
+    I: IntoIterator<Item = OsString>
+{
+    if args.into_iter().any(|arg|arg == HOT_RELOAD_ARG) {
+        return true;
+    }
+    match env::var_os(HOT_RELOAD_ENV_VAR) {
+        Some(value) => !value.is_empty() && value != "0",
+        None => false,
+    }
+}
+
+struct WatchedFile {
+    key: HardAssetKey,
+    path: PathBuf,
+    modified: Option<SystemTime>,
+}
+
+fn get_modified_time(path: &PathBuf) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|metadata|metadata.modified()).ok()
+}
+
+/// Polls the modification time of every hard asset file. Assets that were never loaded are skipped by `AssetManager::create_reload_request`.
+///
+/// The file list is rebuilt when an overlay is mounted or unmounted.
+pub struct HotReloadWatcher {
+    files: Vec<WatchedFile>,
+    overlay_revision: u32,
+    elapsed: f32,
+}
+
+impl HotReloadWatcher {
+    pub fn new(context: &WimpyAppContext) -> Self {
+        let mut watcher = Self {
+            files: Vec::new(),
+            overlay_revision: context.assets.get_overlay_revision(),
+            elapsed: 0.0,
+        };
+        watcher.sync_files(context);
+        log::info!("Hot reload enabled, watching {} files",watcher.files.len());
+        return watcher;
+    }
+
+    /// Files that were already watched keep their last modification time, so a change made before the resync still reloads
+    fn sync_files(&mut self,context: &WimpyAppContext) {
+        let previous: HashMap<PathBuf,Option<SystemTime>> = self.files.drain(..).map(|file|(file.path,file.modified)).collect();
+        self.files = context.assets.get_hard_asset_paths().into_iter().map(|(key,path)|{
+            WatchedFile {
+                modified: match previous.get(&path) {
+                    Some(modified) => *modified,
+                    None => get_modified_time(&path),
+                },
+                key,
+                path,
+            }
+        }).collect();
+    }
+
+    /// Returns the hard assets whose files changed since the last poll
+    pub fn update(&mut self,context: &WimpyAppContext,delta_seconds: f32) -> Vec<HardAssetKey> {
+        let overlay_revision = context.assets.get_overlay_revision();
+        if overlay_revision != self.overlay_revision {
+            self.overlay_revision = overlay_revision;
+            self.sync_files(context);
+            log::info!("Mounted overlays changed, watching {} files",self.files.len());
+        }
+
+        self.elapsed += delta_seconds;
+        if self.elapsed < POLL_INTERVAL_SECONDS {
+            return Vec::new();
+        }
+        self.elapsed = 0.0;
+
+        let mut changed = Vec::new();
+        for file in self.files.iter_mut() {
+            let modified = get_modified_time(&file.path);
+            /* A file that is missing mid-save is picked up once it is written again */
+            if modified.is_none() || modified == file.modified {
+                continue;
+            }
+            file.modified = modified;
+            changed.push(file.key);
+        }
+        return changed;
+    }
+}
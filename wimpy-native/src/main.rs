@@ -1,6 +1,8 @@
 mod key_code;
 mod desktop_io;
 mod desktop_app;
+mod user_data;
+mod hot_reload;
 
 use std::{
     env,
@@ -30,5 +32,9 @@ pub fn main() {
     builder.target(Target::Stdout);
     builder.init();
 
-    desktop_app::run_desktop_app::<GenericTestApp,TestConfig>(Some(manifest_path));
+    user_data::configure_data_directory(user_data::find_data_directory_arg(env::args_os().skip(1)));
+
+    let hot_reload = hot_reload::is_hot_reload_enabled(env::args_os().skip(1));
+
+    desktop_app::run_desktop_app::<GenericTestApp,TestConfig>(Some(manifest_path),hot_reload);
 }
@@ -0,0 +1,116 @@
+const DATA_DIRECTORY_NAME: &'static str = "wimpy-engine";
+const DATA_DIRECTORY_ENV_VAR: &'static str = "WIMPY_DATA_DIR";
+const DATA_DIRECTORY_ARG: &'static str = "--data-dir";
+
+const TEMP_EXTENSION: &'static str = "tmp";
+const BACKUP_EXTENSION: &'static str = "bak";
+
+use std::{env, ffi::OsString, fs::{self, File}, io::{self, Write}, path::{Path, PathBuf}, sync::OnceLock};
+
+static DATA_DIRECTORY: OnceLock<PathBuf> = OnceLock::new();
+
+/// Pulls `--data-dir <path>` (or `--data-dir=<path>`) out of the command line arguments.
+pub fn find_data_directory_arg<I>(args: I) -> Option<PathBuf>
+where
+    I: IntoIterator<Item = OsString>
+{
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        let Some(text) = arg.to_str() else {
+            continue;
+        };
+        if text == DATA_DIRECTORY_ARG {
+            return args.next().map(PathBuf::from);
+        }
+        if let Some(value) = text.strip_prefix(DATA_DIRECTORY_ARG).and_then(|value|value.strip_prefix('=')) {
+            return Some(PathBuf::from(value));
+        }
+    }
+    None
+}
+
+/// Must be called before the first key value store operation, otherwise the platform default is locked in.
+///
+/// Priority: `cli_override`, then `WIMPY_DATA_DIR`, then the platform's per-user data directory.
+pub fn configure_data_directory(cli_override: Option<PathBuf>) {
+    let path = match cli_override {
+        Some(path) => path,
+        None => resolve_data_directory(),
+    };
+    log::info!("User data directory: {:?}",path);
+    if DATA_DIRECTORY.set(path).is_err() {
+        log::warn!("User data directory was already configured, ignoring new value");
+    }
+}
+
+pub fn get_data_directory() -> &'static Path {
+    DATA_DIRECTORY.get_or_init(resolve_data_directory)
+}
+
+fn non_empty_env_path(name: &str) -> Option<PathBuf> {
+    match env::var_os(name) {
+        Some(value) if !value.is_empty() => Some(PathBuf::from(value)),
+        _ => None,
+    }
+}
+
+fn platform_data_directory() -> Option<PathBuf> {
+    if cfg!(target_os = "windows") {
+        non_empty_env_path("APPDATA")
+    } else if cfg!(target_os = "macos") {
+        non_empty_env_path("HOME").map(|home|home.join("Library").join("Application Support"))
+    } else {
+        non_empty_env_path("XDG_DATA_HOME").or_else(||{
+            non_empty_env_path("HOME").map(|home|home.join(".local").join("share"))
+        })
+    }
+}
+
+fn resolve_data_directory() -> PathBuf {
+    if let Some(path) = non_empty_env_path(DATA_DIRECTORY_ENV_VAR) {
+        return path;
+    }
+    match platform_data_directory() {
+        Some(path) => path.join(DATA_DIRECTORY_NAME),
+        None => {
+            log::warn!("Could not find a per-user data directory, falling back to the working directory");
+            PathBuf::from(DATA_DIRECTORY_NAME)
+        }
+    }
+}
+
+fn with_extra_extension(path: &Path,extension: &str) -> PathBuf {
+    let mut value = path.as_os_str().to_owned();
+    value.push(".");
+    value.push(extension);
+    PathBuf::from(value)
+}
+
+pub fn backup_path(path: &Path) -> PathBuf {
+    with_extra_extension(path,BACKUP_EXTENSION)
+}
+
+/// Writes to a temporary file that is renamed over `path` once it is flushed to disk, so a crash never leaves a half written file behind.
+///
+/// The previous contents of `path` are kept as the one rolling backup.
+pub fn write_atomic(path: &Path,data: &[u8]) -> io::Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    let temp_path = with_extra_extension(path,TEMP_EXTENSION);
+    {
+        let mut file = File::create(&temp_path)?;
+        file.write_all(data)?;
+        file.sync_all()?;
+    }
+
+    if path.exists() {
+        // If this fails, we keep the last good backup rather than aborting the save
+        if let Err(error) = fs::rename(path,backup_path(path)) {
+            log::warn!("Could not rotate backup for '{:?}': {}",path,error);
+        }
+    }
+
+    fs::rename(&temp_path,path)
+}
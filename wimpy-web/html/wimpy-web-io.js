@@ -1,4 +1,7 @@
 const KEY_VALUE_STORE_KEY = "wimpy-kvs";
+const SAVE_SLOT_PREFIX = "wimpy-save/";
+const SAVE_SLOT_DATA_SUFFIX = "/data";
+const SAVE_SLOT_METADATA_SUFFIX = "/meta";
 
 function getErrorType(statusCode) {
     switch(statusCode) {
@@ -67,7 +70,9 @@ export async function saveKeyValueStore(data) {
 export async function loadKeyValueStore() {
     const localStorageData = localStorage.getItem(KEY_VALUE_STORE_KEY);
     if(!localStorageData) {
-        return new Uint8Array();
+        return {
+            value: new Uint8Array()
+        };
     }
     let byteArray;
     try {
@@ -82,6 +87,91 @@ export async function loadKeyValueStore() {
     };
 }
 
+function getSaveSlotKey(name,suffix) {
+    return SAVE_SLOT_PREFIX + name + suffix;
+}
+
+function loadBase64Item(key) {
+    const localStorageData = localStorage.getItem(key);
+    if(localStorageData === null) {
+        return {
+            error: "NotFound"
+        };
+    }
+    try {
+        return {
+            value: Uint8Array.fromBase64(localStorageData)
+        };
+    } catch {
+        return {
+            error: "DecodeFailure"
+        };
+    }
+}
+
+export async function saveSlot(name,data,metadata) {
+    let base64Data, base64Metadata;
+    try {
+        base64Data = data.toBase64();
+        base64Metadata = metadata.toBase64();
+    } catch {
+        return {
+            error: "EncodeFailure"
+        };
+    }
+    const dataKey = getSaveSlotKey(name,SAVE_SLOT_DATA_SUFFIX);
+    const metadataKey = getSaveSlotKey(name,SAVE_SLOT_METADATA_SUFFIX);
+    const previousData = localStorage.getItem(dataKey);
+    try {
+        localStorage.setItem(dataKey,base64Data);
+        localStorage.setItem(metadataKey,base64Metadata);
+    } catch {
+        // Most likely over quota. Don't leave new data paired with old metadata.
+        try {
+            if(previousData === null) {
+                localStorage.removeItem(dataKey);
+            } else {
+                localStorage.setItem(dataKey,previousData);
+            }
+        } catch {}
+        return {
+            error: "WriteFailure"
+        };
+    }
+    return {
+        value: true
+    };
+}
+
+export async function loadSlot(name) {
+    return loadBase64Item(getSaveSlotKey(name,SAVE_SLOT_DATA_SUFFIX));
+}
+
+export async function loadSlotMetadata(name) {
+    return loadBase64Item(getSaveSlotKey(name,SAVE_SLOT_METADATA_SUFFIX));
+}
+
+export async function deleteSlot(name) {
+    localStorage.removeItem(getSaveSlotKey(name,SAVE_SLOT_DATA_SUFFIX));
+    localStorage.removeItem(getSaveSlotKey(name,SAVE_SLOT_METADATA_SUFFIX));
+    return {
+        value: true
+    };
+}
+
+export async function listSlots() {
+    const names = [];
+    for(let i = 0;i < localStorage.length;i++) {
+        const key = localStorage.key(i);
+        if(key.startsWith(SAVE_SLOT_PREFIX) && key.endsWith(SAVE_SLOT_METADATA_SUFFIX)) {
+            names.push(key.slice(SAVE_SLOT_PREFIX.length,-SAVE_SLOT_METADATA_SUFFIX.length));
+        }
+    }
+    return {
+        value: names
+    };
+}
+
 export function loadTextFile(path) {
     return defaultLoader(path,response => response.text());
 }
@@ -90,13 +180,28 @@ export function loadBinaryFile(path) {
     return defaultLoader(path,response => response.bytes());
 }
 
+function createUnprocessedImageBitmap(blob) {
+    return createImageBitmap(blob,{
+        premultiplyAlpha: "none",
+        colorSpaceConversion: "none"
+    });
+}
+
 export function loadImageFile(path) {
     return defaultLoader(path,async response => {
         const blob = await response.blob();
-        const imageBitmap = await createImageBitmap(blob,{
-            premultiplyAlpha: "none",
-            colorSpaceConversion: "none"
-        });
-        return imageBitmap;
+        return createUnprocessedImageBitmap(blob);
     });
 }
+
+export async function decodeImage(data) {
+    try {
+        return {
+            value: await createUnprocessedImageBitmap(new Blob([data]))
+        };
+    } catch {
+        return {
+            error: "DecodeFailure"
+        };
+    }
+}
@@ -6,10 +6,13 @@ use wasm_bindgen::{JsCast, JsValue, prelude::Closure};
 use web_sys::{js_sys::Float32Array, Document, Event, HtmlCanvasElement, KeyboardEvent, Window};
 use wgpu::{InstanceDescriptor, Limits, SurfaceTarget};
 
-use wimpy_engine::{UWimpyPoint, WimpyRect, WimpyVec, app::{*, graphics::{*, textures::StreamingPolicy}, input::*}};
+use wimpy_engine::{UWimpyPoint, WimpyRect, WimpyVec, app::{*, graphics::{*, textures::StreamingPolicy}, input::*, wam::PreloadRequest}};
 
 const CANVAS_ID: &'static str = "main-canvas";
 
+/// Preload loads run in the background, this only limits how many are started each frame
+const PRELOAD_REQUESTS_PER_FRAME: usize = 16;
+
 /* Must match 'html/style.css @ div#virtual-cursor' */
 const EMULATED_CURSOR_SIZE: UWimpyPoint = UWimpyPoint {x: 12, y: 16};
 
@@ -24,6 +27,8 @@ pub enum WebAppError {
     KeyEventBindFailure,
     RequestAnimationFrameFailure,
     ResizeEventBindFailure,
+    VisibilityEventBindFailure,
+    ManifestLoadFailure,
 }
 
 pub struct WebApp<TWimpyApp> {
@@ -106,12 +111,20 @@ where
             },
         }?;
 
-        let mut app_context = WimpyAppContext::create::<WimpyWebIO,TConfig>(WimpyContextCreationConfig {
+        let mut app_context = match WimpyAppContext::create::<WimpyWebIO,TConfig>(WimpyContextCreationConfig {
             manifest_path,
             input_device_hint: InputDevice::MouseAndKeyboard,
             graphics_provider,
             texture_stream_policy: StreamingPolicy::Retained,
-        }).await;
+            key_value_store_migrations: TWimpyApp::key_value_store_migrations(),
+            manifest_load_mode: TWimpyApp::manifest_load_mode(),
+        }).await {
+            Ok(value) => value,
+            Err(error) => {
+                log::error!("Manifest load failure: {}",error);
+                return Err(WebAppError::ManifestLoadFailure);
+            },
+        };
 
         let app = TWimpyApp::create(&mut app_context).await;
 
@@ -134,6 +147,13 @@ where
             app_ref.last_frame_time = app_ref.current_frame_time;
             app_ref.current_frame_time = now;
             app_ref.render_frame();
+            let delta_seconds = ((app_ref.current_frame_time - app_ref.last_frame_time) * 0.001) as f32;
+            let autosave = app_ref.app_context.update_key_value_store(delta_seconds);
+            app_ref.app_context.update_texture_streaming(delta_seconds);
+            let preload_requests = app_ref.app_context.take_preload_requests(PRELOAD_REQUESTS_PER_FRAME);
+            drop(app_ref);
+            Self::save_key_value_store(&app,autosave);
+            Self::load_preload_requests(&app,preload_requests);
             if let Err(error) = request_animation_frame(f.borrow().as_ref().unwrap()) {
                 log::error!("{:?}",error);
             }
@@ -153,6 +173,31 @@ where
         return Ok(());
     }
 
+    /// The app is not borrowed while the save is in flight
+    fn save_key_value_store(app: &Rc<RefCell<Self>>,data: Option<Vec<u8>>) {
+        let Some(data) = data else {
+            return;
+        };
+        let app = app.clone();
+        spawn_local(async move {
+            if let Err(error) = WimpyWebIO::save_key_value_store(&data).await {
+                log::error!("Key value store autosave failure: {:?}",error);
+                app.borrow_mut().app_context.key_value_store.mark_dirty();
+            }
+        });
+    }
+
+    /// The app is only borrowed to hand each result back
+    fn load_preload_requests(app: &Rc<RefCell<Self>>,requests: Vec<PreloadRequest>) {
+        for request in requests {
+            let app = app.clone();
+            spawn_local(async move {
+                let result = request.load::<WimpyWebIO>().await;
+                app.borrow_mut().app_context.complete_preload(result);
+            });
+        }
+    }
+
     fn update_input(&mut self) {
         self.gamepad_manager.update();
         let gamepad_state = create_gamepad_state(
@@ -259,6 +304,21 @@ where
             get_document()?.add_event_listener_with_callback("keyup",closure.as_ref().unchecked_ref()).map_err(|_|WebAppError::KeyEventBindFailure)?;
             closure.forget();
         }
+        {
+            let app = app.clone();
+            let closure = Closure::<dyn FnMut(_)>::new(move|_: Event| {
+                let Ok(document) = get_document() else {
+                    return;
+                };
+                if !document.hidden() {
+                    return;
+                }
+                let data = app.borrow_mut().app_context.flush_key_value_store();
+                Self::save_key_value_store(&app,data);
+            });
+            get_document()?.add_event_listener_with_callback("visibilitychange",closure.as_ref().unchecked_ref()).map_err(|_|WebAppError::VisibilityEventBindFailure)?;
+            closure.forget();
+        }
         if resize_config == ResizeConfig::FitWindow {
             let app = app.clone();
             let closure = Closure::<dyn FnMut(_)>::new(move|_: Event| {
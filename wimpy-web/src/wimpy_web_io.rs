@@ -1,7 +1,7 @@
 use std::path::Path;
 
 use wasm_bindgen::prelude::*;
-use web_sys::{ImageBitmap, js_sys::{Object, Reflect, Uint8Array}};
+use web_sys::{ImageBitmap, js_sys::{Date, Object, Reflect, Uint8Array}};
 use wgpu::{CopyExternalImageDestInfo, CopyExternalImageSourceInfo, Extent3d, ExternalImageSource, Origin2d, Origin3d, Queue, Texture};
 use wimpy_engine::{UWimpyPoint, app::{FileError, WimpyIO, WimpyImageData, WimpyImageDataWriter}};
 
@@ -12,9 +12,24 @@ extern "C" {
     #[wasm_bindgen(js_name = saveKeyValueStore)]
     async fn save_key_value_store_js(data: Vec<u8>) -> JsValue;
 
-    #[wasm_bindgen(js_name = saveKeyValueStore)]
+    #[wasm_bindgen(js_name = loadKeyValueStore)]
     async fn load_key_value_store_js() -> JsValue;
 
+    #[wasm_bindgen(js_name = saveSlot)]
+    async fn save_slot_js(name: String,data: Vec<u8>,metadata: Vec<u8>) -> JsValue;
+
+    #[wasm_bindgen(js_name = loadSlot)]
+    async fn load_slot_js(name: String) -> JsValue;
+
+    #[wasm_bindgen(js_name = loadSlotMetadata)]
+    async fn load_slot_metadata_js(name: String) -> JsValue;
+
+    #[wasm_bindgen(js_name = deleteSlot)]
+    async fn delete_slot_js(name: String) -> JsValue;
+
+    #[wasm_bindgen(js_name = listSlots)]
+    async fn list_slots_js() -> JsValue;
+
     #[wasm_bindgen(js_name = loadTextFile)]
     async fn load_text_file_js(path: String) -> JsValue;
 
@@ -23,6 +38,9 @@ extern "C" {
 
     #[wasm_bindgen(js_name = loadImageFile)]
     async fn load_image_file_js(path: String) -> JsValue;
+
+    #[wasm_bindgen(js_name = decodeImage)]
+    async fn decode_image_js(data: Vec<u8>) -> JsValue;
 }
 
 fn get_js_file_function_result(value: JsValue) -> Result<JsValue,FileError> {
@@ -60,6 +78,38 @@ impl WimpyIO for WimpyWebIO {
         }
     }
 
+    async fn save_slot(name: &str,data: &[u8],metadata: &[u8]) -> Result<(),FileError> {
+        _ = get_js_file_function_result(save_slot_js(name.to_string(),Vec::from(data),Vec::from(metadata)).await)?;
+        Ok(())
+    }
+
+    async fn load_slot(name: &str) -> Result<Vec<u8>,FileError> {
+        let js_value = get_js_file_function_result(load_slot_js(name.to_string()).await)?;
+        js_value_to_bytes(js_value)
+    }
+
+    async fn load_slot_metadata(name: &str) -> Result<Vec<u8>,FileError> {
+        let js_value = get_js_file_function_result(load_slot_metadata_js(name.to_string()).await)?;
+        js_value_to_bytes(js_value)
+    }
+
+    async fn delete_slot(name: &str) -> Result<(),FileError> {
+        _ = get_js_file_function_result(delete_slot_js(name.to_string()).await)?;
+        Ok(())
+    }
+
+    async fn list_slots() -> Result<Vec<String>,FileError> {
+        let js_value = get_js_file_function_result(list_slots_js().await)?;
+        match serde_wasm_bindgen::from_value::<Vec<String>>(js_value) {
+            Ok(value) => Ok(value),
+            Err(_) => Err(FileError::Internal),
+        }
+    }
+
+    fn unix_timestamp() -> u64 {
+        (Date::now() / 1000.0) as u64
+    }
+
     async fn load_binary_file(path: &Path) -> Result<Vec<u8>,FileError> {
         let path_str = path_to_str(path)?.to_string();
         let js_value = get_js_file_function_result(load_binary_file_js(path_str).await)?;
@@ -80,19 +130,28 @@ impl WimpyIO for WimpyWebIO {
         }
     }
 
-    async fn load_image_file(path: &Path) -> Result<WimpyImageData<'_>,FileError> {
+    async fn load_image_file(path: &Path) -> Result<WimpyImageData<'static>,FileError> {
         let path_str = path_to_str(path)?.to_string();
         let js_value = get_js_file_function_result(load_image_file_js(path_str).await)?;
-        if js_value.is_instance_of::<ImageBitmap>() {
-            let image = ImageBitmap::from(js_value);
-            let wrapper = ExternalImageSourceWrapper {
-                value: ExternalImageSource::ImageBitmap(image),
-            };
-            let image_data = WimpyImageData::Custom { data: Box::new(wrapper) };
-            Ok(image_data)
-        } else {
-            Err(FileError::Other)
-        }
+        js_value_to_image_data(js_value)
+    }
+
+    async fn decode_image(data: Vec<u8>) -> Result<WimpyImageData<'static>,FileError> {
+        let js_value = get_js_file_function_result(decode_image_js(data).await)?;
+        js_value_to_image_data(js_value)
+    }
+}
+
+fn js_value_to_image_data(js_value: JsValue) -> Result<WimpyImageData<'static>,FileError> {
+    if js_value.is_instance_of::<ImageBitmap>() {
+        let image = ImageBitmap::from(js_value);
+        let wrapper = ExternalImageSourceWrapper {
+            value: ExternalImageSource::ImageBitmap(image),
+        };
+        let image_data = WimpyImageData::Custom { data: Box::new(wrapper) };
+        Ok(image_data)
+    } else {
+        Err(FileError::Other)
     }
 }
 
@@ -132,6 +191,14 @@ impl WimpyImageDataWriter for ExternalImageSourceWrapper {
     }
 }
 
+fn js_value_to_bytes(value: JsValue) -> Result<Vec<u8>,FileError> {
+    if value.is_instance_of::<Uint8Array>() {
+        Ok(Uint8Array::from(value).to_vec())
+    } else {
+        Err(FileError::Internal)
+    }
+}
+
 fn path_to_str(path: &Path) -> Result<&str,FileError> {
     match path.to_str() {
         Some(value) => Ok(value),